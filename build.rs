@@ -0,0 +1,30 @@
+use std::env;
+use std::process::Command;
+
+/// Unit tests run outside of the interpreter: unlike the extension
+/// module, they're linked against libpython.
+fn main() {
+    println!("cargo:rerun-if-env-changed=PYO3_PYTHON");
+    let python = env::var("PYO3_PYTHON").unwrap_or_else(|_| "python3".to_string());
+    let output = Command::new(python)
+        .args([
+            "-c",
+            "import sysconfig; print(sysconfig.get_config_var('LIBDIR')); \
+             print(sysconfig.get_config_var('LDVERSION'))",
+        ])
+        .output();
+
+    // Tests are left to fail to link without python, not the module
+    let output = match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        _ => return,
+    };
+    let mut lines = output.lines();
+    if let (Some(libdir), Some(version)) = (lines.next(), lines.next()) {
+        println!("cargo:rustc-link-arg-tests=-L{}", libdir);
+        println!("cargo:rustc-link-arg-tests=-Wl,-rpath,{}", libdir);
+        println!("cargo:rustc-link-arg-tests=-lpython{}", version);
+    }
+}
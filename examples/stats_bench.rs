@@ -0,0 +1,54 @@
+// Times the statistics kernels of the python module on a single core:
+//     cargo run --release --example stats_bench [megapixels]
+#[allow(dead_code)]
+#[path = "../src/stats.rs"]
+mod stats;
+
+use std::env;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 5;
+
+/// Fastest of a few runs of `kernel`.
+fn time<F: Fn()>(kernel: F) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            kernel();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let megapixels: usize = env::args()
+        .nth(1)
+        .map(|value| value.parse().expect("megapixels should be an integer"))
+        .unwrap_or(40);
+    let values = megapixels * 1_000_000 * 3;
+
+    // Cheap noise, statistics don't depend on the content of pixels
+    let rgb8: Vec<u8> = (0..values).map(|i| (i * 2_654_435_761) as u8).collect();
+    let rgb16: Vec<u16> = (0..values).map(|i| (i * 2_654_435_761) as u16).collect();
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        let rgb8 = time(|| {
+            stats::get_image_statistics(&rgb8, 1);
+        });
+        let rgb16 = time(|| {
+            stats::get_image_statistics(&rgb16, 1);
+        });
+
+        println!(
+            "{} megapixels, single core, best of {} runs:",
+            megapixels, RUNS
+        );
+        println!("    rgb8    {:>6} ms", rgb8.as_millis());
+        println!("    rgb16   {:>6} ms", rgb16.as_millis());
+    });
+}
@@ -0,0 +1,31 @@
+/* C interface of cleanax, built with
+ *   cargo build --release --no-default-features --features ffi
+ *
+ * Options are a JSON object keyed like keyword arguments of clean(),
+ * or NULL. Results are JSON strings to release with cleanax_free():
+ * {"reports": [...]} or {"report": {...}}, {"error": "..."} on failure.
+ */
+#ifndef CLEANAX_H
+#define CLEANAX_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Check every file of root_folder. */
+char *cleanax_scan(const char *root_folder, const char *options);
+
+/* Check an image held in memory, name is reported as its path. */
+char *cleanax_check(const char *name, const uint8_t *data, size_t len, const char *options);
+
+/* Release a result of cleanax_scan or cleanax_check. */
+void cleanax_free(char *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
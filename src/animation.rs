@@ -0,0 +1,102 @@
+use crate::webp;
+use image::codecs::{gif::GifDecoder, png::PngDecoder};
+use image::{AnimationDecoder, DynamicImage, Frame, ImageError, ImageFormat, ImageResult};
+use std::io::Cursor;
+
+/// Frames sampled from an animated image (GIF, APNG, WebP).
+pub struct Animation {
+    /// Evenly spaced frames, starting with the first one.
+    pub frames: Vec<DynamicImage>,
+    /// Number of frames of the whole animation.
+    pub frame_count: usize,
+    /// Duration of the whole animation in milliseconds.
+    pub duration: f64,
+}
+
+/// Decoded frame along with its duration in milliseconds.
+type TimedFrame = ImageResult<(DynamicImage, f64)>;
+
+fn timed_frame(frame: ImageResult<Frame>) -> TimedFrame {
+    let frame = frame?;
+    let (numerator, denominator) = frame.delay().numer_denom_ms();
+
+    Ok((
+        DynamicImage::ImageRgba8(frame.into_buffer()),
+        numerator as f64 / denominator.max(1) as f64,
+    ))
+}
+
+/// Decode frames of given GIF, APNG or WebP file, keeping at most `samples`
+/// evenly spaced frames in memory. Return `None` if file is of another
+/// format. A still GIF or WebP is returned as a single frame animation.
+pub fn load_animation(data: &[u8], samples: usize) -> ImageResult<Option<Animation>> {
+    let format = image::guess_format(data).ok();
+    let reader = Cursor::new(data);
+
+    let frames: Box<dyn Iterator<Item = TimedFrame> + '_> = match format {
+        Some(ImageFormat::Gif) => Box::new(GifDecoder::new(reader)?.into_frames().map(timed_frame)),
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(reader)?;
+            if !decoder.is_apng() {
+                return Ok(None);
+            }
+            Box::new(decoder.apng().into_frames().map(timed_frame))
+        }
+        // The image crate only handles a subset of WebP
+        Some(ImageFormat::WebP) => Box::new(webp::open_frames(data)?),
+        _ => return Ok(None),
+    };
+
+    sample_frames(frames, samples.max(1)).map(Some)
+}
+
+/// Walk through every frame, keeping frames whose index is a multiple of
+/// a stride. The stride doubles each time too many frames are kept, so
+/// memory stays bounded without knowing the frame count in advance.
+fn sample_frames(
+    frames: impl Iterator<Item = TimedFrame>,
+    samples: usize,
+) -> ImageResult<Animation> {
+    let mut kept = Vec::new();
+    let mut stride = 1;
+    let mut frame_count = 0;
+    let mut duration = 0.0;
+
+    for frame in frames {
+        let (frame, delay) = frame?;
+        duration += delay;
+
+        if frame_count % stride == 0 {
+            kept.push(frame);
+
+            if kept.len() == 2 * samples {
+                kept = kept.into_iter().step_by(2).collect();
+                stride *= 2;
+            }
+        }
+
+        frame_count += 1;
+    }
+
+    if frame_count == 0 {
+        return Err(ImageError::IoError(
+            std::io::ErrorKind::UnexpectedEof.into(),
+        ));
+    }
+
+    // Bring kept frames down to the requested number
+    let frames = if kept.len() > samples {
+        let step = kept.len() as f64 / samples as f64;
+        (0..samples)
+            .map(|i| kept[(i as f64 * step) as usize].clone())
+            .collect()
+    } else {
+        kept
+    };
+
+    Ok(Animation {
+        frames,
+        frame_count,
+        duration,
+    })
+}
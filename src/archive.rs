@@ -0,0 +1,133 @@
+use crate::paths;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
+use zip::ZipArchive;
+
+/// Number of entries read ahead of the checks.
+const BUFFERED_ENTRIES: usize = 16;
+
+/// File stored in an archive.
+pub struct Entry {
+    /// Path of the file inside the archive,
+    /// `None` when the archive itself couldn't be read.
+    pub name: Option<String>,
+    pub data: io::Result<Vec<u8>>,
+}
+
+pub enum Kind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+/// Guess kind of archive from extension of given file.
+pub fn archive_kind(path: &str) -> Option<Kind> {
+    let path = path.to_lowercase();
+
+    if path.ends_with(".zip") {
+        Some(Kind::Zip)
+    } else if path.ends_with(".tar") {
+        Some(Kind::Tar)
+    } else if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+        Some(Kind::TarGz)
+    } else {
+        None
+    }
+}
+
+/// Check extension of given file for a zip or tar archive.
+pub fn is_archive(path: &str) -> bool {
+    archive_kind(path).is_some()
+}
+
+/// Check extension of given file for a plain or gzipped tar archive.
+pub fn is_tar(path: &str) -> bool {
+    matches!(archive_kind(path), Some(Kind::Tar) | Some(Kind::TarGz))
+}
+
+/// Open given tar archive for reading, decompressing it on the fly.
+pub fn open_tar(path: &str) -> io::Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(paths::decode(path))?);
+
+    match archive_kind(path) {
+        Some(Kind::TarGz) => Ok(Box::new(GzDecoder::new(file))),
+        _ => Ok(Box::new(file)),
+    }
+}
+
+/// Read every file of given archive from a background thread.
+/// Decompression is sequential, entries are handed over through a bounded
+/// channel so that checks run in parallel while only a few entries are
+/// held in memory at a time.
+pub fn read_entries(path: &str) -> Receiver<Entry> {
+    let (sender, receiver) = sync_channel(BUFFERED_ENTRIES);
+    let path = path.to_string();
+
+    thread::spawn(move || {
+        let result = match archive_kind(&path) {
+            Some(Kind::Zip) => read_zip(&path, &sender),
+            Some(_) => open_tar(&path).and_then(|file| read_tar(file, &sender)),
+            None => Ok(()),
+        };
+
+        if let Err(err) = result {
+            let _ = sender.send(Entry {
+                name: None,
+                data: Err(err),
+            });
+        }
+    });
+
+    receiver
+}
+
+fn read_zip(path: &str, sender: &SyncSender<Entry>) -> io::Result<()> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(paths::decode(path))?))?;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if !file.is_file() {
+            continue;
+        }
+
+        // Checksum is verified once the whole entry is read
+        let mut data = Vec::with_capacity(file.size() as usize);
+        let entry = Entry {
+            name: Some(file.name().to_string()),
+            data: file.read_to_end(&mut data).map(|_| data),
+        };
+
+        // Nobody is listening anymore
+        if sender.send(entry).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn read_tar(reader: impl Read, sender: &SyncSender<Entry>) -> io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+
+    for file in archive.entries()? {
+        let mut file = file?;
+        if !file.header().entry_type().is_file() {
+            continue;
+        }
+
+        let mut data = Vec::with_capacity(file.size() as usize);
+        let entry = Entry {
+            name: Some(file.path()?.to_string_lossy().into_owned()),
+            data: file.read_to_end(&mut data).map(|_| data),
+        };
+
+        if sender.send(entry).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
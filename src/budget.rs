@@ -0,0 +1,80 @@
+use crate::header;
+use std::cell::Cell;
+use std::sync::{Condvar, Mutex};
+
+/// Decoded bytes of an image whose header doesn't tell its dimensions,
+/// for each byte of its file.
+const EXPANSION_RATIO: u64 = 8;
+
+thread_local! {
+    /// Reservations held by the current thread.
+    static HELD: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Bytes an image takes in memory once decoded, along with its file.
+/// Dimensions are read from its header, counting 4 bytes per pixel.
+pub fn decoded_size(data: &[u8]) -> u64 {
+    let decoded = match header::dimensions(data) {
+        Some((width, height)) => width as u64 * height as u64 * 4,
+        None => data.len() as u64 * EXPANSION_RATIO,
+    };
+
+    data.len() as u64 + decoded
+}
+
+/// Bytes of images allowed in memory at once, shared by scanning threads.
+/// Threads wait for images in flight to be done with before decoding,
+/// so that concurrency adapts to the size of images.
+pub struct MemoryBudget {
+    limit: u64,
+    used: Mutex<u64>,
+    freed: Condvar,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: u64) -> Self {
+        MemoryBudget {
+            limit,
+            used: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Wait until given bytes fit in the budget, and hold them until the
+    /// reservation is dropped. An image larger than the whole budget waits
+    /// for every other one to be done with.
+    pub fn reserve(&self, bytes: u64) -> Reservation<'_> {
+        let bytes = bytes.min(self.limit);
+        let mut used = self.used.lock().unwrap();
+
+        // A thread waiting on rayon may run another file on top of the one
+        // it holds, waiting for itself would never end
+        if HELD.with(|held| held.get()) == 0 {
+            while *used + bytes > self.limit {
+                used = self.freed.wait(used).unwrap();
+            }
+        }
+
+        *used += bytes;
+        HELD.with(|held| held.set(held.get() + 1));
+
+        Reservation {
+            budget: self,
+            bytes,
+        }
+    }
+}
+
+/// Bytes held in a memory budget.
+pub struct Reservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        *self.budget.used.lock().unwrap() -= self.bytes;
+        HELD.with(|held| held.set(held.get() - 1));
+        self.budget.freed.notify_all();
+    }
+}
@@ -0,0 +1,432 @@
+use crate::paths;
+use crate::report::{Reason, Report};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::sync::{Mutex, OnceLock};
+use std::time::UNIX_EPOCH;
+
+/// First bytes of a cache file, followed by the format version.
+const MAGIC: &[u8; 4] = b"CLNX";
+const VERSION: u32 = 10;
+
+/// Size and modification time of a file, when these didn't change
+/// its cached report still holds.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Stamp {
+    size: u64,
+    /// Nanoseconds since epoch.
+    modified: u128,
+}
+
+impl Stamp {
+    pub fn of(path: &str) -> io::Result<Self> {
+        let metadata = fs::metadata(paths::decode(path))?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos());
+
+        Ok(Stamp {
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// Reports of a previous scan, keyed by path, along with those
+/// of the current scan to be saved for the next one.
+pub struct Cache {
+    path: String,
+    /// Options reports were produced with, a cache built with
+    /// other options doesn't hold.
+    fingerprint: String,
+    previous: HashMap<String, (Stamp, Report)>,
+    current: Mutex<Vec<(String, Stamp, Report)>>,
+}
+
+impl Cache {
+    /// Load cache file at given path, starting afresh if it doesn't exist,
+    /// was built with other options, or if `reuse` is false.
+    pub fn open(path: &str, fingerprint: String, reuse: bool) -> io::Result<Self> {
+        let previous = if reuse {
+            match File::open(path) {
+                // A damaged cache only costs a full scan
+                Ok(file) => {
+                    read_entries(&mut BufReader::new(file), &fingerprint).unwrap_or_default()
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+                Err(err) => return Err(err),
+            }
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Cache {
+            path: path.to_string(),
+            fingerprint,
+            previous,
+            current: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Cached report of given file, if it didn't change since.
+    pub fn get(&self, path: &str, stamp: Stamp) -> Option<Report> {
+        match self.previous.get(path) {
+            Some((cached, report)) if *cached == stamp => Some(report.clone()),
+            _ => None,
+        }
+    }
+
+    /// Keep report of given file for the next scan. Renames are left
+    /// out, they're only planned for the current scan.
+    pub fn insert(&self, path: &str, stamp: Stamp, report: &Report) {
+        let mut report = report.clone();
+        report.renamed = None;
+        self.current
+            .lock()
+            .unwrap()
+            .push((path.to_string(), stamp, report));
+    }
+
+    /// Write reports of the current scan, replacing the previous cache.
+    /// Reports of files the scan left out (filtered, sampled, or not
+    /// reached before an abort) are carried over as long as the files
+    /// exist. Writing goes to a temporary file first, an interrupted write
+    /// doesn't lose the previous cache.
+    pub fn save(&self) -> io::Result<()> {
+        let temporary = format!("{}.tmp", self.path);
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+
+        writer.write_all(MAGIC)?;
+        write_u32(&mut writer, VERSION)?;
+        write_str(&mut writer, &self.fingerprint)?;
+
+        let current = self.current.lock().unwrap();
+        let scanned: HashSet<&str> = current.iter().map(|(path, _, _)| path.as_str()).collect();
+        let carried: Vec<_> = self
+            .previous
+            .iter()
+            .filter(|(path, _)| !scanned.contains(path.as_str()))
+            .filter(|(path, _)| paths::decode(path).is_file())
+            .map(|(path, (stamp, report))| (path, stamp, report))
+            .collect();
+
+        write_u64(&mut writer, (current.len() + carried.len()) as u64)?;
+        let entries = current
+            .iter()
+            .map(|(path, stamp, report)| (path, stamp, report))
+            .chain(carried);
+        for (path, stamp, report) in entries {
+            write_str(&mut writer, path)?;
+            write_u64(&mut writer, stamp.size)?;
+            writer.write_all(&stamp.modified.to_le_bytes())?;
+            write_report(&mut writer, report)?;
+        }
+
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(temporary, &self.path)
+    }
+}
+
+fn read_entries(
+    reader: &mut impl Read,
+    fingerprint: &str,
+) -> io::Result<HashMap<String, (Stamp, Report)>> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+
+    // Unknown format or options, every file gets checked again
+    if &magic != MAGIC || read_u32(reader)? != VERSION || read_str(reader)? != fingerprint {
+        return Ok(HashMap::new());
+    }
+
+    let count = read_u64(reader)?;
+    let mut entries = HashMap::new();
+    for _ in 0..count {
+        let path = read_str(reader)?;
+        let size = read_u64(reader)?;
+        let mut modified = [0u8; 16];
+        reader.read_exact(&mut modified)?;
+        let report = read_report(reader)?;
+
+        let stamp = Stamp {
+            size,
+            modified: u128::from_le_bytes(modified),
+        };
+        entries.insert(path, (stamp, report));
+    }
+
+    Ok(entries)
+}
+
+pub fn write_report(writer: &mut impl Write, report: &Report) -> io::Result<()> {
+    write_str(writer, &report.path)?;
+
+    write_reasons(writer, &report.reasons)?;
+    write_reasons(writer, &report.warnings)?;
+    write_reasons(writer, &report.infos)?;
+    write_reasons(writer, &report.suspects)?;
+    write_u32(writer, report.decoder_warnings.len() as u32)?;
+    for warning in &report.decoder_warnings {
+        write_str(writer, warning)?;
+    }
+
+    write_u32(writer, report.metrics.len() as u32)?;
+    for (name, value) in &report.metrics {
+        write_str(writer, name)?;
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    write_option(writer, report.error.as_deref(), |writer, error| {
+        write_str(writer, error)
+    })?;
+    write_option(writer, report.hash, |writer, hash| write_u64(writer, hash))?;
+    write_u32(writer, report.oriented_hashes.len() as u32)?;
+    for hash in &report.oriented_hashes {
+        write_u64(writer, *hash)?;
+    }
+    write_option(writer, report.content_hash, |writer, hash| {
+        write_u64(writer, hash)
+    })?;
+    write_option(writer, report.pixel_hash, |writer, hash| {
+        write_u64(writer, hash)
+    })?;
+    write_option(writer, report.sop_instance_uid.as_deref(), |writer, uid| {
+        write_str(writer, uid)
+    })?;
+    write_option(writer, report.caption.as_deref(), |writer, caption| {
+        write_str(writer, caption)
+    })?;
+    write_option(writer, report.renamed.as_deref(), |writer, renamed| {
+        write_str(writer, renamed)
+    })?;
+
+    write_u32(writer, report.pages.len() as u32)?;
+    for page in &report.pages {
+        write_report(writer, page)?;
+    }
+
+    Ok(())
+}
+
+pub fn read_report(reader: &mut impl Read) -> io::Result<Report> {
+    let mut report = Report::new(&read_str(reader)?);
+    report.reasons = read_reasons(reader)?;
+    report.warnings = read_reasons(reader)?;
+    report.infos = read_reasons(reader)?;
+    report.suspects = read_reasons(reader)?;
+    for _ in 0..read_u32(reader)? {
+        report.decoder_warnings.push(read_str(reader)?);
+    }
+
+    for _ in 0..read_u32(reader)? {
+        let name = intern(&read_str(reader)?);
+        let mut value = [0u8; 8];
+        reader.read_exact(&mut value)?;
+        report.metric(name, f64::from_le_bytes(value));
+    }
+
+    report.error = read_option(reader, read_str)?;
+    report.hash = read_option(reader, read_u64)?;
+    for _ in 0..read_u32(reader)? {
+        report.oriented_hashes.push(read_u64(reader)?);
+    }
+    report.content_hash = read_option(reader, read_u64)?;
+    report.pixel_hash = read_option(reader, read_u64)?;
+    report.sop_instance_uid = read_option(reader, read_str)?;
+    report.caption = read_option(reader, read_str)?;
+    report.renamed = read_option(reader, read_str)?;
+
+    for _ in 0..read_u32(reader)? {
+        report.pages.push(read_report(reader)?);
+    }
+
+    Ok(report)
+}
+
+/// Write given reasons by bit, keeping their order.
+fn write_reasons(writer: &mut impl Write, reasons: &[Reason]) -> io::Result<()> {
+    write_u32(writer, reasons.len() as u32)?;
+    for reason in reasons {
+        write_u64(writer, reason.bit())?;
+    }
+    Ok(())
+}
+
+fn read_reasons(reader: &mut impl Read) -> io::Result<Vec<Reason>> {
+    let mut reasons = Vec::new();
+    for _ in 0..read_u32(reader)? {
+        let bit = read_u64(reader)?;
+        match Reason::ALL.iter().find(|reason| reason.bit() == bit) {
+            Some(reason) => reasons.push(*reason),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown reason")),
+        }
+    }
+    Ok(reasons)
+}
+
+/// Static version of given metric name, metrics are named by checks
+/// with string literals. Only a handful of names exist, each is leaked once.
+fn intern(name: &str) -> &'static str {
+    static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+    let mut names = NAMES.get_or_init(Default::default).lock().unwrap();
+    match names.get(name) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+            names.insert(name);
+            name
+        }
+    }
+}
+
+pub fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub fn write_u64(writer: &mut impl Write, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub fn write_str(writer: &mut impl Write, value: &str) -> io::Result<()> {
+    write_u32(writer, value.len() as u32)?;
+    writer.write_all(value.as_bytes())
+}
+
+pub fn write_option<W: Write, T>(
+    writer: &mut W,
+    value: Option<T>,
+    write: impl FnOnce(&mut W, T) -> io::Result<()>,
+) -> io::Result<()> {
+    match value {
+        Some(value) => {
+            writer.write_all(&[1])?;
+            write(writer, value)
+        }
+        None => writer.write_all(&[0]),
+    }
+}
+
+pub fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+pub fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub fn read_str(reader: &mut impl Read) -> io::Result<String> {
+    let mut bytes = vec![0u8; read_u32(reader)? as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn read_option<R: Read, T>(
+    reader: &mut R,
+    read: impl FnOnce(&mut R) -> io::Result<T>,
+) -> io::Result<Option<T>> {
+    let mut flag = [0u8; 1];
+    reader.read_exact(&mut flag)?;
+
+    match flag[0] {
+        0 => Ok(None),
+        _ => read(reader).map(Some),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::testing::scratch;
+
+    /// Report setting every field the format holds.
+    pub fn full_report(path: &str) -> Report {
+        let mut report = Report::new(path);
+        report.flag(Reason::SolidColor);
+        report.flag(Reason::LowEntropy);
+        report.warnings.push(Reason::Upscaled);
+        report.infos.push(Reason::Metadata);
+        report.suspects.push(Reason::Blurry);
+        report.decoder_warnings.push("truncated".to_string());
+        report.metric("blur", 12.5);
+        report.metric("entropy", -0.0);
+        report.error = Some("broken".to_string());
+        report.hash = Some(u64::MAX);
+        report.oriented_hashes = vec![1, 2];
+        report.content_hash = Some(3);
+        report.pixel_hash = Some(4);
+        report.sop_instance_uid = Some("1.2.3".to_string());
+        report.caption = Some("a.txt".to_string());
+        report.renamed = Some("a.png".to_string());
+        report.pages.push(Report::new("page"));
+        report
+    }
+
+    #[test]
+    fn reports_round_trip() {
+        let report = full_report("a.jpg");
+        let mut data = Vec::new();
+        write_report(&mut data, &report).unwrap();
+
+        let read = read_report(&mut data.as_slice()).unwrap();
+        assert_eq!(format!("{:?}", read), format!("{:?}", report));
+
+        // Every byte is needed
+        data.pop();
+        assert!(read_report(&mut data.as_slice()).is_err());
+    }
+
+    #[test]
+    fn cache_keeps_reports_of_unchanged_files() {
+        let folder = scratch("cache");
+        let file = |name: &str| folder.join(name).to_str().unwrap().to_string();
+        let (a, b, c) = (file("a.jpg"), file("b.jpg"), file("c.jpg"));
+        for path in [&a, &b, &c] {
+            fs::write(path, b"image").unwrap();
+        }
+        let cache_path = file("cache");
+        let stamp = |path: &str| Stamp::of(path).unwrap();
+
+        let cache = Cache::open(&cache_path, "options".to_string(), true).unwrap();
+        for path in [&a, &b, &c] {
+            assert!(cache.get(path, stamp(path)).is_none());
+            cache.insert(path, stamp(path), &full_report(path));
+        }
+        cache.save().unwrap();
+
+        // Renames are only planned for the scan that cached the report
+        let cache = Cache::open(&cache_path, "options".to_string(), true).unwrap();
+        let mut expected = full_report(&a);
+        expected.renamed = None;
+        let cached = cache.get(&a, stamp(&a)).unwrap();
+        assert_eq!(format!("{:?}", cached), format!("{:?}", expected));
+
+        // Files left out of a scan are carried over while they exist
+        fs::remove_file(&c).unwrap();
+        fs::write(&a, b"changed").unwrap();
+        assert!(cache.get(&a, stamp(&a)).is_none());
+        cache.insert(&a, stamp(&a), &Report::new(&a));
+        cache.save().unwrap();
+
+        let cache = Cache::open(&cache_path, "options".to_string(), true).unwrap();
+        assert!(cache.get(&a, stamp(&a)).unwrap().reasons.is_empty());
+        assert!(cache.get(&b, stamp(&b)).is_some());
+        assert!(!cache.previous.contains_key(&c));
+
+        // Neither other options nor a fresh start reuse it
+        let cache = Cache::open(&cache_path, "other".to_string(), true).unwrap();
+        assert!(cache.get(&b, stamp(&b)).is_none());
+        let cache = Cache::open(&cache_path, "options".to_string(), false).unwrap();
+        assert!(cache.get(&b, stamp(&b)).is_none());
+
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+use crate::paths;
+use crate::report::{Reason, Report};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Extension of caption files, named after the stem of their image.
+const EXTENSION: &str = "txt";
+
+/// Extensions of images a caption may go with.
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "webp", "gif", "bmp", "tif", "tiff", "avif", "heic", "heif",
+];
+
+/// Check if given file is a caption. The extension is matched exactly,
+/// as the caption of an image is looked up by it.
+pub fn is_caption(path: &str) -> bool {
+    paths::decode(path)
+        .extension()
+        .is_some_and(|extension| extension == EXTENSION)
+}
+
+/// Caption file of given image: `a.jpg` is captioned by `a.txt`.
+pub fn caption_path(image: &str) -> PathBuf {
+    paths::decode(image).with_extension(EXTENSION)
+}
+
+/// What's wrong with the caption of given image, if anything:
+/// it's missing, blank or isn't valid UTF-8.
+pub fn problem(image: &str) -> Option<Reason> {
+    match fs::read(caption_path(image)) {
+        Ok(data) => match String::from_utf8(data) {
+            Ok(text) if text.trim().is_empty() => Some(Reason::EmptyCaption),
+            Ok(_) => None,
+            Err(_) => Some(Reason::CaptionEncoding),
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Some(Reason::MissingCaption),
+        // Unreadable, as good as missing for a dataloader
+        Err(_) => Some(Reason::MissingCaption),
+    }
+}
+
+/// Check the caption of the image of given report, giving the report
+/// the verdict of the pair.
+pub fn check(report: &mut Report) {
+    let caption = caption_path(&report.path);
+    if caption.is_file() {
+        report.caption = Some(paths::encode(&caption));
+    }
+
+    if let Some(reason) = problem(&report.path) {
+        report.flag(reason);
+    }
+}
+
+/// Report of given caption file if no image goes with it.
+/// Captions of an image are checked along with it.
+pub fn orphan(caption: &str) -> Option<Report> {
+    if has_image(&paths::decode(caption)) {
+        return None;
+    }
+
+    let mut report = Report::new(caption);
+    report.flag(Reason::OrphanCaption);
+    Some(report)
+}
+
+/// Check if an image of the same stem lies next to given caption.
+/// Extensions are tried rather than listing the folder, which holds
+/// millions of files in large datasets.
+fn has_image(caption: &Path) -> bool {
+    IMAGE_EXTENSIONS.iter().any(|extension| {
+        caption.with_extension(extension).is_file()
+            || caption
+                .with_extension(extension.to_ascii_uppercase())
+                .is_file()
+    })
+}
@@ -0,0 +1,211 @@
+use crate::cache::{read_report, read_str, read_u32, write_report, write_str, write_u32};
+use crate::report::Report;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// First bytes of a checkpoint file, followed by the format version.
+const MAGIC: &[u8; 4] = b"CLNP";
+const VERSION: u32 = 10;
+
+/// Time between two writes of completed reports to disk.
+const INTERVAL: Duration = Duration::from_secs(10);
+
+struct Writer {
+    file: BufWriter<File>,
+    flushed: Instant,
+}
+
+/// Reports of files checked so far, appended to a file as
+/// the scan goes so that an interrupted scan can be resumed.
+pub struct Checkpoint {
+    path: String,
+    /// Reports of an interrupted scan, by the file or URL they come from.
+    completed: HashMap<String, Vec<Report>>,
+    writer: Mutex<Writer>,
+}
+
+impl Checkpoint {
+    /// Start a checkpoint at given path. With `resume`, reports of
+    /// the interrupted scan it holds are loaded and kept.
+    pub fn open(path: &str, fingerprint: &str, resume: bool) -> io::Result<Self> {
+        let (completed, file) = match fs::read(path) {
+            Ok(data) if resume => {
+                let (completed, length) = read_entries(&data, fingerprint)?;
+
+                // Drop the entry being written when the scan died
+                let mut file = OpenOptions::new().write(true).open(path)?;
+                file.set_len(length)?;
+                file.seek(SeekFrom::End(0))?;
+
+                (completed, BufWriter::new(file))
+            }
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {
+                let mut file = BufWriter::new(File::create(path)?);
+                file.write_all(MAGIC)?;
+                write_u32(&mut file, VERSION)?;
+                write_str(&mut file, fingerprint)?;
+
+                (HashMap::new(), file)
+            }
+        };
+
+        Ok(Checkpoint {
+            path: path.to_string(),
+            completed,
+            writer: Mutex::new(Writer {
+                file,
+                flushed: Instant::now(),
+            }),
+        })
+    }
+
+    /// Check if given file was checked before the interruption.
+    pub fn contains(&self, key: &str) -> bool {
+        self.completed.contains_key(key)
+    }
+
+    /// Reports of given file if it was checked before the interruption.
+    pub fn get(&self, key: &str) -> Option<Vec<Report>> {
+        self.completed.get(key).cloned()
+    }
+
+    /// Append reports of a checked file, writing them to disk
+    /// along with the previous ones once in a while.
+    pub fn record(&self, key: &str, reports: &[Report]) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+
+        write_str(&mut writer.file, key)?;
+        write_u32(&mut writer.file, reports.len() as u32)?;
+        for report in reports {
+            write_report(&mut writer.file, report)?;
+        }
+
+        if writer.flushed.elapsed() >= INTERVAL {
+            writer.file.flush()?;
+            writer.file.get_ref().sync_data()?;
+            writer.flushed = Instant::now();
+        }
+
+        Ok(())
+    }
+
+    /// Remove the checkpoint of a scan that went through.
+    pub fn finish(&self) -> io::Result<()> {
+        fs::remove_file(&self.path)
+    }
+}
+
+/// Read reports of a checkpoint, along with the length of its complete entries.
+fn read_entries(data: &[u8], fingerprint: &str) -> io::Result<(HashMap<String, Vec<Report>>, u64)> {
+    let mut reader = Cursor::new(data);
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC || read_u32(&mut reader)? != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a cleanax checkpoint",
+        ));
+    }
+    if read_str(&mut reader)? != fingerprint {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "checkpoint was written with other arguments, resume with the same ones",
+        ));
+    }
+
+    let mut completed = HashMap::new();
+    let mut length = reader.position();
+
+    // Entries of a scan that died may stop anywhere
+    while let Ok((key, reports)) = read_entry(&mut reader) {
+        completed.insert(key, reports);
+        length = reader.position();
+    }
+
+    Ok((completed, length))
+}
+
+fn read_entry(reader: &mut Cursor<&[u8]>) -> io::Result<(String, Vec<Report>)> {
+    let key = read_str(reader)?;
+    let count = read_u32(reader)?;
+
+    let mut reports = Vec::new();
+    for _ in 0..count {
+        reports.push(read_report(reader)?);
+    }
+
+    Ok((key, reports))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::tests::full_report;
+    use crate::testing::scratch;
+    use std::path::Path;
+
+    #[test]
+    fn resumed_checkpoints_hold_recorded_reports() {
+        let folder = scratch("checkpoint");
+        let path = folder.join("checkpoint").to_str().unwrap().to_string();
+
+        let checkpoint = Checkpoint::open(&path, "options", false).unwrap();
+        checkpoint.record("a.jpg", &[full_report("a.jpg")]).unwrap();
+        checkpoint
+            .record(
+                "b.zip",
+                &[Report::new("b.zip/1.jpg"), Report::new("b.zip/2.jpg")],
+            )
+            .unwrap();
+        drop(checkpoint);
+
+        // A scan dying while writing leaves part of an entry behind
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write_str(&mut file, "c.jpg").unwrap();
+        write_u32(&mut file, 1).unwrap();
+        drop(file);
+
+        let checkpoint = Checkpoint::open(&path, "options", true).unwrap();
+        let reports = checkpoint.get("a.jpg").unwrap();
+        assert_eq!(
+            format!("{:?}", reports),
+            format!("{:?}", [full_report("a.jpg")])
+        );
+        assert_eq!(checkpoint.get("b.zip").unwrap().len(), 2);
+        assert!(!checkpoint.contains("c.jpg"));
+
+        // Entries follow the complete ones
+        checkpoint.record("c.jpg", &[Report::new("c.jpg")]).unwrap();
+        drop(checkpoint);
+        let checkpoint = Checkpoint::open(&path, "options", true).unwrap();
+        assert!(checkpoint.contains("a.jpg") && checkpoint.contains("c.jpg"));
+
+        checkpoint.finish().unwrap();
+        assert!(!Path::new(&path).exists());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn checkpoints_resume_with_the_same_options_only() {
+        let folder = scratch("checkpoint-options");
+        let path = folder.join("checkpoint").to_str().unwrap().to_string();
+
+        let checkpoint = Checkpoint::open(&path, "options", false).unwrap();
+        checkpoint.record("a.jpg", &[Report::new("a.jpg")]).unwrap();
+        drop(checkpoint);
+
+        let err = Checkpoint::open(&path, "other", true).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        // Without resuming, it starts over
+        let checkpoint = Checkpoint::open(&path, "options", false).unwrap();
+        assert!(!checkpoint.contains("a.jpg"));
+
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
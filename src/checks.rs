@@ -0,0 +1,777 @@
+use crate::stats::{
+    channel_names, get_channel_moments, get_entropy, get_grayscale_statistics,
+    get_grayscale_statistics_with_alpha, get_image_histograms, get_image_statistics,
+    get_image_statistics_with_alpha, get_line_spreads, Pixel,
+};
+use image::{imageops, imageops::FilterType, DynamicImage, GenericImageView, GrayImage, RgbImage};
+use rayon::prelude::*;
+use std::borrow::Cow;
+
+/// Shrink given image so that its largest side is at most `max_dimension`,
+/// keeping its aspect ratio and pixel format. Pixels are sampled rather than
+/// averaged, so that the spread of colors is preserved.
+pub fn downscale(image: &DynamicImage, max_dimension: u32) -> Cow<'_, DynamicImage> {
+    let (width, height) = image.dimensions();
+    if width.max(height) <= max_dimension {
+        return Cow::Borrowed(image);
+    }
+
+    Cow::Owned(image.resize(max_dimension, max_dimension, FilterType::Nearest))
+}
+
+/// Check if every channel of an image varies too little for it to be
+/// anything but a solid color, given its variance on a 0-255 scale
+/// and the variance below which each channel is flat.
+pub fn is_solid_color(variance: &Pixel, thresholds: &Pixel) -> bool {
+    variance.is_below(thresholds)
+}
+
+/// Factor bringing variances of 16 bits channels to a 0-255 scale.
+const VARIANCE_16_TO_8: f64 = 1.0 / (257.0 * 257.0);
+
+/// Check if every channel of given image varies too little for it to be
+/// anything but a solid color, given the variance below which each channel
+/// is flat. Gray values are checked against the red, green and blue
+/// thresholds, alpha against the alpha one.
+/// BGR images are never considered as solid color.
+/// Statistics are computed on one pixel every `step`.
+pub fn check_solid_color(image: &DynamicImage, step: usize, thresholds: &Pixel) -> bool {
+    // Extract pixels
+    match image {
+        DynamicImage::ImageRgb8(pixels) => {
+            let buffer = pixels.as_raw();
+            let q = get_image_statistics(buffer, step);
+
+            is_solid_color(&q.1, thresholds)
+        }
+        DynamicImage::ImageRgba8(pixels) => {
+            let buffer = pixels.as_raw();
+            let q = get_image_statistics_with_alpha(buffer, step);
+
+            is_solid_color(&q.1, thresholds)
+        }
+        DynamicImage::ImageRgb16(pixels) => {
+            let buffer = pixels.as_raw();
+            let q = get_image_statistics(buffer, step);
+
+            is_solid_color(&q.1.scaled(VARIANCE_16_TO_8), thresholds)
+        }
+        DynamicImage::ImageRgba16(pixels) => {
+            let buffer = pixels.as_raw();
+            let q = get_image_statistics_with_alpha(buffer, step);
+
+            is_solid_color(&q.1.scaled(VARIANCE_16_TO_8), thresholds)
+        }
+        DynamicImage::ImageLuma8(pixels) => {
+            let buffer = pixels.as_raw();
+            let q = get_grayscale_statistics(buffer, step);
+
+            is_solid_color(&q.1, thresholds)
+        }
+        DynamicImage::ImageLuma16(pixels) => {
+            let buffer = pixels.as_raw();
+            let q = get_grayscale_statistics(buffer, step);
+
+            is_solid_color(&q.1.scaled(VARIANCE_16_TO_8), thresholds)
+        }
+        DynamicImage::ImageLumaA8(pixels) => {
+            let buffer = pixels.as_raw();
+            let q = get_grayscale_statistics_with_alpha(buffer, step);
+
+            is_solid_color(&q.1, thresholds)
+        }
+        DynamicImage::ImageLumaA16(pixels) => {
+            let buffer = pixels.as_raw();
+            let q = get_grayscale_statistics_with_alpha(buffer, step);
+
+            is_solid_color(&q.1.scaled(VARIANCE_16_TO_8), thresholds)
+        }
+        DynamicImage::ImageBgr8(_) => false,
+        DynamicImage::ImageBgra8(_) => false,
+    }
+}
+
+/// Number of channels of given image, and bits of each of them.
+pub fn channel_layout(image: &DynamicImage) -> (u8, u8) {
+    let color = image.color();
+    let channels = color.channel_count();
+
+    (channels, (color.bits_per_pixel() / channels as u16) as u8)
+}
+
+/// Check if every pixel of given color image has (almost) equal red,
+/// green and blue values, i.e. a grayscale image stored as color.
+/// `tolerance` is the maximum spread between channels of a pixel,
+/// expressed for 8 bits channels.
+pub fn check_grayscale(image: &DynamicImage, tolerance: u32) -> bool {
+    match image {
+        DynamicImage::ImageRgb8(pixels) => is_grayscale(pixels.as_raw(), 3, tolerance),
+        DynamicImage::ImageRgba8(pixels) => is_grayscale(pixels.as_raw(), 4, tolerance),
+        DynamicImage::ImageBgr8(pixels) => is_grayscale(pixels.as_raw(), 3, tolerance),
+        DynamicImage::ImageBgra8(pixels) => is_grayscale(pixels.as_raw(), 4, tolerance),
+        DynamicImage::ImageRgb16(pixels) => is_grayscale(pixels.as_raw(), 3, tolerance * 257),
+        DynamicImage::ImageRgba16(pixels) => is_grayscale(pixels.as_raw(), 4, tolerance * 257),
+        // Already stored as grayscale
+        _ => false,
+    }
+}
+
+fn is_grayscale<T: Sync + num::traits::PrimInt>(
+    buffer: &[T],
+    channels: usize,
+    tolerance: u32,
+) -> bool {
+    buffer.par_chunks(channels).all(|pixel| {
+        let r = pixel[0].to_u32().unwrap();
+        let g = pixel[1].to_u32().unwrap();
+        let b = pixel[2].to_u32().unwrap();
+
+        r.max(g).max(b) - r.min(g).min(b) <= tolerance
+    })
+}
+
+/// Mean squared difference between a grayscale image and the same image
+/// shrunk by `factor` then enlarged back to its size.
+fn round_trip_residual(luma: &GrayImage, factor: u32) -> f64 {
+    let (width, height) = luma.dimensions();
+
+    let small = imageops::resize(
+        luma,
+        (width / factor).max(1),
+        (height / factor).max(1),
+        FilterType::Triangle,
+    );
+    let round_trip = imageops::resize(&small, width, height, FilterType::Triangle);
+
+    luma.as_raw()
+        .par_iter()
+        .zip(round_trip.as_raw().par_iter())
+        .map(|(a, b)| (*a as f64 - *b as f64).powi(2))
+        .sum::<f64>()
+        / luma.as_raw().len() as f64
+}
+
+/// Estimate how much fine detail an image holds relatively to its coarse
+/// structure. The finest details are lost when shrinking by 2, while shrinking
+/// by 8 loses most of the image structure. An image upscaled from a much
+/// smaller source has no high frequency content, so the first round trip
+/// loses (almost) nothing compared to the second one and the score is low.
+/// Being a ratio, the score doesn't depend on resolution nor contrast.
+pub fn detail_score(image: &DynamicImage) -> f64 {
+    let luma = image.to_luma8();
+
+    let fine = round_trip_residual(&luma, 2);
+    let coarse = round_trip_residual(&luma, 8);
+
+    if coarse == 0.0 {
+        return 0.0;
+    }
+
+    (fine / coarse).sqrt()
+}
+
+/// Count distinct levels of each channel of given image, named after
+/// the channel (`levels_r`, `levels_g`, ...).
+pub fn distinct_levels(image: &DynamicImage) -> Vec<(&'static str, usize)> {
+    channel_names(image)
+        .iter()
+        .zip(get_image_histograms(image).iter())
+        .map(|(name, histogram)| {
+            let levels = histogram.iter().filter(|count| **count > 0).count();
+            (level_metric(name), levels)
+        })
+        .collect()
+}
+
+fn level_metric(channel: &str) -> &'static str {
+    match channel {
+        "l" => "levels_l",
+        "r" => "levels_r",
+        "g" => "levels_g",
+        "b" => "levels_b",
+        _ => "levels_a",
+    }
+}
+
+/// Mean and standard deviation of each channel of given image,
+/// named after the channel (`mean_r`, `std_r`, ...).
+pub fn channel_moments(image: &DynamicImage) -> Vec<(&'static str, f64)> {
+    channel_names(image)
+        .iter()
+        .zip(get_channel_moments(image))
+        .flat_map(|(name, (mean, std))| {
+            let (mean_name, std_name) = moment_metrics(name);
+            vec![(mean_name, mean), (std_name, std)]
+        })
+        .collect()
+}
+
+fn moment_metrics(channel: &str) -> (&'static str, &'static str) {
+    match channel {
+        "l" => ("mean_l", "std_l"),
+        "r" => ("mean_r", "std_r"),
+        "g" => ("mean_g", "std_g"),
+        "b" => ("mean_b", "std_b"),
+        _ => ("mean_a", "std_a"),
+    }
+}
+
+/// Deviation of the mean of each color channel from the mean of the three,
+/// on a 0-255 scale, named after the channel (`cast_r`, `cast_g`, `cast_b`).
+/// A channel far above the others tints the whole image with its color,
+/// one far below with the complementary color. None for gray images.
+pub fn color_cast(image: &DynamicImage) -> Option<[(&'static str, f64); 3]> {
+    let names = channel_names(image);
+    let moments = get_channel_moments(image);
+    let mean = |channel: &str| {
+        let index = names.iter().position(|name| *name == channel)?;
+        Some(moments[index].0)
+    };
+
+    let (r, g, b) = (mean("r")?, mean("g")?, mean("b")?);
+    let gray = (r + g + b) / 3.0;
+
+    Some([
+        ("cast_r", r - gray),
+        ("cast_g", g - gray),
+        ("cast_b", b - gray),
+    ])
+}
+
+/// Proportion of values at 0 and at 255 of the color channels clipping
+/// the most on each tail: crushed shadows, then blown highlights.
+/// 16 bits channels are brought back to 8 bits first, alpha is left out.
+pub fn clipping(image: &DynamicImage) -> (f64, f64) {
+    let (mut shadows, mut highlights) = (0f64, 0f64);
+
+    for (name, histogram) in channel_names(image).iter().zip(get_image_histograms(image)) {
+        if *name == "a" {
+            continue;
+        }
+
+        let count = histogram.iter().sum::<u64>() as f64;
+        shadows = shadows.max(histogram[0] as f64 / count);
+        highlights = highlights.max(histogram[255] as f64 / count);
+    }
+
+    (shadows, highlights)
+}
+
+/// Names of the features `color_features` describes an image with.
+pub const COLOR_FEATURES: [&str; 7] = [
+    "color_mean_r",
+    "color_mean_g",
+    "color_mean_b",
+    "color_std_r",
+    "color_std_g",
+    "color_std_b",
+    "saturation",
+];
+
+/// Describe the colors of given image, to compare it with the rest of its
+/// dataset: mean and standard deviation of each color channel, then mean
+/// saturation (spread between channels of a pixel), all on a 0-255 scale.
+/// Gray images have three equal channels, alpha is left out.
+pub fn color_features(image: &DynamicImage) -> [(&'static str, f64); 7] {
+    let names = channel_names(image);
+    let all_moments = get_channel_moments(image);
+    let moments = |channel: &str| {
+        // Gray images only have luminance
+        let index = names.iter().position(|name| *name == channel).unwrap_or(0);
+        all_moments[index]
+    };
+
+    let rgb = image.to_rgb8();
+    let spread: u64 = rgb
+        .as_raw()
+        .par_chunks(3 * 1024)
+        .map(|pixels| {
+            pixels
+                .chunks_exact(3)
+                .map(|pixel| {
+                    let max = pixel.iter().max().unwrap();
+                    let min = pixel.iter().min().unwrap();
+                    (max - min) as u64
+                })
+                .sum::<u64>()
+        })
+        .sum();
+    let saturation = spread as f64 / (rgb.len() / 3).max(1) as f64;
+
+    let (r, g, b) = (moments("r"), moments("g"), moments("b"));
+    let values = [r.0, g.0, b.0, r.1, g.1, b.1, saturation];
+    let mut features = [("", 0.0); 7];
+    for (feature, (name, value)) in features
+        .iter_mut()
+        .zip(COLOR_FEATURES.iter().zip(values.iter()))
+    {
+        *feature = (name, *value);
+    }
+    features
+}
+
+/// Resolutions of common monitors and phone screens, in landscape.
+const SCREEN_RESOLUTIONS: &[(u32, u32)] = &[
+    (1024, 768),
+    (1280, 720),
+    (1280, 800),
+    (1280, 1024),
+    (1334, 750),
+    (1366, 768),
+    (1440, 900),
+    (1536, 864),
+    (1600, 900),
+    (1680, 1050),
+    (1920, 1080),
+    (1920, 1200),
+    (2160, 1080),
+    (2340, 1080),
+    (2400, 1080),
+    (2436, 1125),
+    (2532, 1170),
+    (2560, 1440),
+    (2560, 1600),
+    (2688, 1242),
+    (2778, 1284),
+    (2796, 1290),
+    (2880, 1800),
+    (3024, 1964),
+    (3440, 1440),
+    (3840, 2160),
+];
+
+/// Check if given dimensions are exactly those of a common screen,
+/// in either orientation.
+pub fn is_screen_resolution(width: u32, height: u32) -> bool {
+    let landscape = (width.max(height), width.min(height));
+    SCREEN_RESOLUTIONS.contains(&landscape)
+}
+
+/// Side of the square blocks flat regions are made of.
+const FLAT_BLOCK: usize = 8;
+
+/// Proportion of given luminance covered by blocks of a single level,
+/// as backgrounds of user interfaces are. Photos, even of a clear sky,
+/// hold some noise.
+pub fn flat_area(luma: &GrayImage) -> f64 {
+    let (width, height) = (luma.width() as usize, luma.height() as usize);
+    let blocks = (width / FLAT_BLOCK) * (height / FLAT_BLOCK);
+    if blocks == 0 {
+        return 0.0;
+    }
+
+    let flat: usize = luma
+        .as_raw()
+        .par_chunks(width * FLAT_BLOCK)
+        .filter(|rows| rows.len() == width * FLAT_BLOCK)
+        .map(|rows| {
+            (0..width / FLAT_BLOCK)
+                .filter(|block| {
+                    let first = rows[block * FLAT_BLOCK];
+                    rows.chunks_exact(width).all(|row| {
+                        row[block * FLAT_BLOCK..(block + 1) * FLAT_BLOCK]
+                            .iter()
+                            .all(|value| *value == first)
+                    })
+                })
+                .count()
+        })
+        .sum();
+
+    flat as f64 / blocks as f64
+}
+
+/// Luminance jump between neighbouring pixels making a sharp edge,
+/// as rendered text and borders have.
+const SHARP_EDGE: u8 = 96;
+
+/// Proportion of horizontally neighbouring pixels of given luminance
+/// separated by a sharp edge. Text makes a lot of them, while edges of
+/// photographed objects are spread over a few pixels.
+pub fn sharp_edges(luma: &GrayImage) -> f64 {
+    let width = luma.width() as usize;
+    if width < 2 || luma.height() == 0 {
+        return 0.0;
+    }
+
+    let edges: usize = luma
+        .as_raw()
+        .par_chunks(width)
+        .map(|row| {
+            row.windows(2)
+                .filter(|pair| pair[0].abs_diff(pair[1]) >= SHARP_EDGE)
+                .count()
+        })
+        .sum();
+
+    edges as f64 / ((width - 1) * luma.height() as usize) as f64
+}
+
+/// Proportion of the width and height of an image its corners span,
+/// where logos and overlaid credits sit.
+const CORNER_WIDTH: u32 = 4;
+const CORNER_HEIGHT: u32 = 6;
+
+/// Highest proportion of sharp edges among the four corners of given
+/// luminance, as opaque logos and overlaid text make.
+pub fn corner_edges(luma: &GrayImage) -> f64 {
+    let (width, height) = luma.dimensions();
+    let (corner_width, corner_height) = (width / CORNER_WIDTH, height / CORNER_HEIGHT);
+    let (right, bottom) = (width - corner_width, height - corner_height);
+
+    [(0, 0), (right, 0), (0, bottom), (right, bottom)]
+        .iter()
+        .map(|(x, y)| {
+            let corner = imageops::crop_imm(luma, *x, *y, corner_width, corner_height);
+            sharp_edges(&corner.to_image())
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Largest side of the thumbnail periodic patterns are looked for in.
+const PERIODICITY_SIZE: u32 = 256;
+/// Smallest shift, in thumbnail pixels, a pattern is looked for at.
+/// Below it, neighbouring details correlate anyway.
+const MIN_PERIOD: usize = 8;
+
+/// Highest correlation between the fine details of given luminance and
+/// the same details shifted horizontally or vertically, as a watermark
+/// tiled over the whole image makes. Photos of regular textures (bricks,
+/// fabric) score high too.
+pub fn periodicity(luma: &GrayImage) -> f64 {
+    let small = thumbnail(luma);
+    let (width, height) = (small.width() as usize, small.height() as usize);
+    if width / 2 <= MIN_PERIOD && height / 2 <= MIN_PERIOD {
+        return 0.0;
+    }
+
+    // The laplacian keeps details, rid of the smooth structure of the image
+    let raw = small.as_raw();
+    let at = |x: usize, y: usize| raw[y * width + x] as f64;
+    let mut details = vec![0f64; width * height];
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            details[y * width + x] =
+                4.0 * at(x, y) - at(x - 1, y) - at(x + 1, y) - at(x, y - 1) - at(x, y + 1);
+        }
+    }
+
+    let shifts: Vec<(usize, usize)> = (MIN_PERIOD..width / 2)
+        .map(|dx| (dx, 0))
+        .chain((MIN_PERIOD..height / 2).map(|dy| (0, dy)))
+        .collect();
+
+    shifts
+        .into_par_iter()
+        .map(|(dx, dy)| correlation(&details, width, height, dx, dy))
+        .reduce(|| 0.0, f64::max)
+}
+
+/// How faithfully a tile repeats along rows or columns of given luminance:
+/// the correlation between its details and the same details shifted
+/// by the period that correlates best, and by twice that period, the lowest
+/// of both. Texture atlases, sprite sheets and stitching failures repeat
+/// three times or more and score close to 1, regular textures of photos
+/// don't repeat as faithfully.
+pub fn tile_repetition(luma: &GrayImage) -> f64 {
+    let small = thumbnail(luma);
+    let (width, height) = (small.width() as usize, small.height() as usize);
+    if width / 3 < MIN_PERIOD && height / 3 < MIN_PERIOD {
+        return 0.0;
+    }
+
+    // Tiles seldom span a whole number of thumbnail pixels, a band of
+    // frequencies survives resampling where finest details don't
+    let fine = imageops::blur(&small, 1.0);
+    let coarse = imageops::blur(&small, 4.0);
+    let details: Vec<f64> = fine
+        .as_raw()
+        .iter()
+        .zip(coarse.as_raw())
+        .map(|(fine, coarse)| *fine as f64 - *coarse as f64)
+        .collect();
+    let correlation = |(dx, dy)| correlation(&details, width, height, dx, dy);
+
+    let repetition = |shifts: Vec<(usize, usize)>| {
+        let period = shifts
+            .into_par_iter()
+            .map(|shift| (correlation(shift), shift))
+            .reduce_with(|a, b| if b.0 > a.0 { b } else { a });
+
+        // Twice a rounded period is up to a pixel off
+        match period {
+            Some((score, (dx, dy))) => {
+                let twice = [0, 1, 2]
+                    .iter()
+                    .map(|offset| match dx {
+                        0 => (0, (2 * dy + offset).saturating_sub(1)),
+                        _ => ((2 * dx + offset).saturating_sub(1), 0),
+                    })
+                    .filter(|(dx, dy)| *dx < width && *dy < height)
+                    .map(correlation)
+                    .fold(0.0, f64::max);
+                score.min(twice)
+            }
+            None => 0.0,
+        }
+    };
+
+    let rows = repetition((MIN_PERIOD..=width / 3).map(|dx| (dx, 0)).collect());
+    let columns = repetition((MIN_PERIOD..=height / 3).map(|dy| (0, dy)).collect());
+    rows.max(columns)
+}
+
+/// Copy of given luminance whose largest side is at most `PERIODICITY_SIZE`.
+fn thumbnail(luma: &GrayImage) -> GrayImage {
+    let (width, height) = luma.dimensions();
+    let scale = PERIODICITY_SIZE as f64 / width.max(height) as f64;
+    if scale >= 1.0 {
+        return luma.clone();
+    }
+
+    imageops::resize(
+        luma,
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+        FilterType::Triangle,
+    )
+}
+
+/// Correlation between given values, laid out in rows of `width`,
+/// and the same values shifted by `dx` and `dy`, where they overlap.
+fn correlation(values: &[f64], width: usize, height: usize, dx: usize, dy: usize) -> f64 {
+    let (mut product, mut squares, mut shifted_squares) = (0f64, 0f64, 0f64);
+    for y in 0..height - dy {
+        for x in 0..width - dx {
+            let (value, shifted) = (values[y * width + x], values[(y + dy) * width + x + dx]);
+            product += value * shifted;
+            squares += value * value;
+            shifted_squares += shifted * shifted;
+        }
+    }
+
+    if squares == 0.0 || shifted_squares == 0.0 {
+        return 0.0;
+    }
+    product / (squares * shifted_squares).sqrt()
+}
+
+/// Side of the tiles content is measured in.
+const CONTENT_TILE: usize = 8;
+
+/// Proportion of the tiles of given image holding content, a pixel whose
+/// channels depart from the background color by more than `tolerance`.
+/// Background is the median color of the border of the image.
+pub fn content_area(image: &DynamicImage, tolerance: u8) -> f64 {
+    let rgb = image.to_rgb8();
+    let (width, height) = (rgb.width() as usize, rgb.height() as usize);
+    if width == 0 || height == 0 {
+        return 0.0;
+    }
+
+    let background = border_color(&rgb);
+    let tiles_per_row = width.div_ceil(CONTENT_TILE);
+    let tiles = tiles_per_row * height.div_ceil(CONTENT_TILE);
+
+    let content: usize = rgb
+        .as_raw()
+        .par_chunks(width * 3 * CONTENT_TILE)
+        .map(|rows| {
+            let mut content = vec![false; tiles_per_row];
+            for row in rows.chunks_exact(width * 3) {
+                for (x, pixel) in row.chunks_exact(3).enumerate() {
+                    if pixel
+                        .iter()
+                        .zip(&background)
+                        .any(|(value, background)| value.abs_diff(*background) > tolerance)
+                    {
+                        content[x / CONTENT_TILE] = true;
+                    }
+                }
+            }
+            content.iter().filter(|content| **content).count()
+        })
+        .sum();
+
+    content as f64 / tiles as f64
+}
+
+/// Median of each channel over the border of given image.
+fn border_color(rgb: &RgbImage) -> [u8; 3] {
+    let (width, height) = rgb.dimensions();
+    let border: Vec<_> = (0..width)
+        .flat_map(|x| [rgb.get_pixel(x, 0), rgb.get_pixel(x, height - 1)])
+        .chain((0..height).flat_map(|y| [rgb.get_pixel(0, y), rgb.get_pixel(width - 1, y)]))
+        .collect();
+
+    let mut color = [0; 3];
+    for (channel, median) in color.iter_mut().enumerate() {
+        let mut values: Vec<u8> = border.iter().map(|pixel| pixel[channel]).collect();
+        values.sort_unstable();
+        *median = values[values.len() / 2];
+    }
+
+    color
+}
+
+/// Tiles along each side of the grid content is located on.
+const CONTENT_GRID: u32 = 8;
+
+/// Bounding box of the tiles of an 8 by 8 grid over given luminance that
+/// hold content, their variance exceeding `threshold`: left, top, right
+/// and bottom, as proportions of the width and height of the image.
+/// None if every tile is background.
+pub fn content_box(luma: &GrayImage, threshold: f64) -> Option<[f64; 4]> {
+    let (width, height) = luma.dimensions();
+    let bounds =
+        |tile: u32, side: u32| (tile * side / CONTENT_GRID, (tile + 1) * side / CONTENT_GRID);
+
+    let mut content: Option<[u32; 4]> = None;
+    for row in 0..CONTENT_GRID {
+        let (top, bottom) = bounds(row, height);
+        for column in 0..CONTENT_GRID {
+            let (left, right) = bounds(column, width);
+            if left == right || top == bottom {
+                continue;
+            }
+
+            let tile = imageops::crop_imm(luma, left, top, right - left, bottom - top);
+            let (mut sum, mut squares) = (0.0, 0.0);
+            for (_, _, pixel) in tile.pixels() {
+                let value = pixel[0] as f64;
+                sum += value;
+                squares += value * value;
+            }
+            let count = ((right - left) * (bottom - top)) as f64;
+            let variance = squares / count - (sum / count).powi(2);
+            if variance <= threshold {
+                continue;
+            }
+
+            let found = content.get_or_insert([column, row, column, row]);
+            found[0] = found[0].min(column);
+            found[1] = found[1].min(row);
+            found[2] = found[2].max(column + 1);
+            found[3] = found[3].max(row + 1);
+        }
+    }
+
+    let grid = CONTENT_GRID as f64;
+    content.map(|[left, top, right, bottom]| {
+        [
+            left as f64 / grid,
+            top as f64 / grid,
+            right as f64 / grid,
+            bottom as f64 / grid,
+        ]
+    })
+}
+
+/// Check if given content box lies within `max_extent` of one side of
+/// the image, two for a corner, the rest being background.
+pub fn hugs_edge(content: &[f64; 4], max_extent: f64) -> bool {
+    let [left, top, right, bottom] = *content;
+    right <= max_extent
+        || bottom <= max_extent
+        || left >= 1.0 - max_extent
+        || top >= 1.0 - max_extent
+}
+
+/// Variance of the laplacian of given image luminance. Sharp images
+/// have strong edges and a high variance, blurry ones a low variance.
+pub fn blur_score(image: &DynamicImage) -> f64 {
+    let luma = image.to_luma8();
+    let (width, height) = (luma.width() as usize, luma.height() as usize);
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let raw = luma.as_raw();
+    let (sum, squares) = (1..height - 1)
+        .into_par_iter()
+        .map(|y| {
+            let (mut sum, mut squares) = (0f64, 0f64);
+            for x in 1..width - 1 {
+                let at = |x: usize, y: usize| raw[y * width + x] as f64;
+                let laplacian =
+                    at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1) - 4.0 * at(x, y);
+                sum += laplacian;
+                squares += laplacian * laplacian;
+            }
+            (sum, squares)
+        })
+        .reduce(|| (0.0, 0.0), |a, b| (a.0 + b.0, a.1 + b.1));
+
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+
+    squares / count - mean * mean
+}
+
+/// Entropy of the luminance of given image, in bits. Photos spread
+/// their levels and score 6 to 8, flat graphics and near blank images
+/// hold a handful of levels and score a lot less.
+pub fn entropy_score(image: &DynamicImage) -> f64 {
+    get_entropy(&image.to_luma8())
+}
+
+/// Check if every color channel of given image holds very few distinct
+/// levels relatively to what its size allows, indicating heavy posterization.
+pub fn check_banding(
+    image: &DynamicImage,
+    levels: &[(&'static str, usize)],
+    min_level_ratio: f64,
+) -> bool {
+    let (width, height) = image.dimensions();
+    // A tiny image can't hold every level
+    let possible_levels = (width as u64 * height as u64).min(256) as f64;
+
+    levels
+        .iter()
+        // Transparency commonly holds a couple of levels only
+        .filter(|(name, _)| *name != "levels_a")
+        .all(|(_, levels)| (*levels as f64 / possible_levels) < min_level_ratio)
+}
+
+/// Thickest run of constant lines still considered as a sensor defect.
+/// Thicker runs are more likely to be flat areas of the picture.
+const MAX_DEAD_LINE_WIDTH: usize = 3;
+
+/// Count lines (rows or columns) of constant luminance inside the image,
+/// given the spread of luminance along each of them. Only thin runs are
+/// counted, and runs touching the image border are ignored as these are
+/// usually letterboxing or padding rather than stuck sensor lines.
+fn count_dead_lines(spreads: &[u8], tolerance: u8) -> usize {
+    let mut count = 0;
+    let mut i = 0;
+
+    while i < spreads.len() {
+        if spreads[i] > tolerance {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < spreads.len() && spreads[i] <= tolerance {
+            i += 1;
+        }
+
+        let touches_border = start == 0 || i == spreads.len();
+        if !touches_border && i - start <= MAX_DEAD_LINE_WIDTH {
+            count += i - start;
+        }
+    }
+
+    count
+}
+
+/// Count constant rows and columns spanning given image, such as stuck
+/// sensor lines or scanner streaks. Return `(rows, columns)`.
+pub fn dead_lines(image: &DynamicImage, tolerance: u8) -> (usize, usize) {
+    let (rows, columns) = get_line_spreads(&image.to_luma8());
+
+    (
+        count_dead_lines(&rows, tolerance),
+        count_dead_lines(&columns, tolerance),
+    )
+}
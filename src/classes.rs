@@ -0,0 +1,86 @@
+use crate::paths;
+use crate::report::Report;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Number of images of a class, as subfolders of an ImageFolder tree
+/// hold them, before and after cleaning.
+pub struct ClassCounts {
+    pub before: usize,
+    pub after: usize,
+}
+
+impl ClassCounts {
+    /// Share of the images of the class that got flagged.
+    pub fn loss(&self) -> f64 {
+        match self.before {
+            0 => 0.0,
+            before => (before - self.after) as f64 / before as f64,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.after == 0
+    }
+
+    /// Convert counts to a python dictionary, telling whether the class
+    /// lost more than `max_loss` of its images.
+    pub fn to_dict<'py>(&self, py: Python<'py>, max_loss: f64) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+
+        dict.set_item("before", self.before)?;
+        dict.set_item("after", self.after)?;
+        dict.set_item("removed", self.before - self.after)?;
+        dict.set_item("loss", self.loss())?;
+        dict.set_item("flagged", self.loss() > max_loss)?;
+        dict.set_item("empty", self.is_empty())?;
+
+        Ok(dict)
+    }
+}
+
+/// Count images of every class of given root folder, a class being one of
+/// its subfolders. Files directly under root folder belong to no class.
+pub fn count_classes(root: &str, reports: &[Report]) -> io::Result<BTreeMap<String, ClassCounts>> {
+    let mut classes = BTreeMap::new();
+
+    // Classes without any file are counted too
+    for entry in fs::read_dir(paths::decode(root))? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            let name = paths::encode(Path::new(&entry.file_name()));
+            classes.insert(
+                name,
+                ClassCounts {
+                    before: 0,
+                    after: 0,
+                },
+            );
+        }
+    }
+
+    for report in reports {
+        let relative = match Path::new(&report.path).strip_prefix(root) {
+            Ok(relative) => relative,
+            Err(_) => continue,
+        };
+        let mut components = relative.components();
+        let class = match (components.next(), components.next()) {
+            (Some(class), Some(_)) => class.as_os_str().to_string_lossy(),
+            _ => continue,
+        };
+
+        if let Some(counts) = classes.get_mut(class.as_ref()) {
+            counts.before += 1;
+            if !report.is_flagged() {
+                counts.after += 1;
+            }
+        }
+    }
+
+    Ok(classes)
+}
@@ -0,0 +1,563 @@
+use crate::config::Config;
+use crate::paths::{FsPath, Roots};
+use crate::report::Columns;
+use crate::summary::ScanSummary;
+use crate::{flagged, run, run_summarized, to_list};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use pyo3::PyObjectProtocol;
+
+/// Options of a scan, built one check at a time and reusable
+/// across scans: `Cleaner().solid_color(threshold=30).blur(min_sharpness=50).recursive()`.
+/// Options are the keyword arguments `clean` takes, each builder
+/// method setting those of a check and returning the cleaner.
+#[pyclass]
+pub struct Cleaner {
+    options: Py<PyDict>,
+}
+
+impl Cleaner {
+    /// Build cleaner holding given keyword arguments.
+    pub fn from_kwargs(py: Python, kwargs: Option<&PyDict>) -> PyResult<Self> {
+        let mut cleaner = Cleaner {
+            options: PyDict::new(py).into(),
+        };
+        if let Some(kwargs) = kwargs {
+            cleaner.set(py, kwargs)?;
+        }
+
+        Ok(cleaner)
+    }
+
+    /// Set given options, rejecting invalid ones right away
+    /// rather than when scanning.
+    fn set(&mut self, py: Python, options: &PyDict) -> PyResult<()> {
+        let updated = self.options.as_ref(py).copy()?;
+        for (key, value) in options {
+            if !value.is_none() {
+                updated.set_item(key, value)?;
+            }
+        }
+
+        Config::from_kwargs(Some(updated))?;
+        self.options = updated.into();
+        Ok(())
+    }
+
+    /// Options of a scan, along with given ones overriding them.
+    fn options<'py>(&self, py: Python<'py>, kwargs: Option<&PyDict>) -> PyResult<&'py PyDict> {
+        let options = PyDict::new(py);
+        for (key, value) in self.options.as_ref(py) {
+            options.set_item(key, value)?;
+        }
+        if let Some(kwargs) = kwargs {
+            for (key, value) in kwargs {
+                options.set_item(key, value)?;
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Builder methods take every option of a check,
+/// those left to None keep their current value.
+macro_rules! options {
+    ($py:expr, $($key:expr => $value:expr),* $(,)?) => {{
+        let options = PyDict::new($py);
+        $(options.set_item($key, $value)?;)*
+        options
+    }};
+}
+
+#[pymethods]
+impl Cleaner {
+    #[new]
+    #[args(kwargs = "**")]
+    fn new(py: Python, kwargs: Option<&PyDict>) -> PyResult<Self> {
+        Cleaner::from_kwargs(py, kwargs)
+    }
+
+    /// Set any option, as a keyword argument of `clean`.
+    #[args(kwargs = "**")]
+    fn option<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        kwargs: Option<&PyDict>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        if let Some(kwargs) = kwargs {
+            slf.set(py, kwargs)?;
+        }
+        Ok(slf)
+    }
+
+    /// Tune detection of solid color images.
+    #[args(threshold = "None", ignore_alpha = "None")]
+    fn solid_color<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        threshold: Option<PyObject>,
+        ignore_alpha: Option<bool>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py,
+                "solid_color_threshold" => threshold,
+                "solid_color_ignore_alpha" => ignore_alpha,
+            ),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag color images whose channels are equal everywhere.
+    #[args(tolerance = "None")]
+    fn grayscale<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        tolerance: Option<u32>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py, "grayscale" => true, "grayscale_tolerance" => tolerance),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag images upscaled from a much smaller source.
+    #[args(min_detail = "None")]
+    fn upscaled<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        min_detail: Option<f64>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py, "upscaled" => true, "min_detail" => min_detail),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag blurry images.
+    #[args(min_sharpness = "None")]
+    fn blur<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        min_sharpness: Option<f64>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py, "blur" => true, "min_sharpness" => min_sharpness),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag images holding too little information.
+    #[args(min_entropy = "None")]
+    fn entropy<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        min_entropy: Option<f64>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py, "entropy" => true, "min_entropy" => min_entropy),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag images with heavy color banding.
+    #[args(min_level_ratio = "None")]
+    fn banding<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        min_level_ratio: Option<f64>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py, "banding" => true, "min_level_ratio" => min_level_ratio),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag images holding dead rows or columns of pixels.
+    #[args(tolerance = "None")]
+    fn dead_lines<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        tolerance: Option<u8>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py, "dead_lines" => true, "dead_line_tolerance" => tolerance),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag images tinted by one color channel dominating the others.
+    #[args(max_cast = "None")]
+    fn color_cast<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        max_cast: Option<f64>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py, "color_cast" => true, "max_color_cast" => max_cast),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag images with many pixels crushed to black or blown to white.
+    #[args(max_shadows = "None", max_highlights = "None")]
+    fn clipping<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        max_shadows: Option<f64>,
+        max_highlights: Option<f64>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py,
+                "clipping" => true,
+                "max_shadow_clipping" => max_shadows,
+                "max_highlight_clipping" => max_highlights,
+            ),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag screenshots among photos, showing at least `signals` signals.
+    #[args(signals = "None", min_flat_area = "None", min_sharp_edges = "None")]
+    fn screenshots<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        signals: Option<usize>,
+        min_flat_area: Option<f64>,
+        min_sharp_edges: Option<f64>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py,
+                "screenshots" => true,
+                "screenshot_signals" => signals,
+                "min_flat_area" => min_flat_area,
+                "min_sharp_edges" => min_sharp_edges,
+            ),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag images carrying a watermark or an overlaid logo.
+    #[args(min_corner_edges = "None", min_periodicity = "None")]
+    fn watermarks<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        min_corner_edges: Option<f64>,
+        min_periodicity: Option<f64>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py,
+                "watermarks" => true,
+                "min_corner_edges" => min_corner_edges,
+                "min_periodicity" => min_periodicity,
+            ),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag blank images but for a small foreground region.
+    #[args(min_content_area = "None", tolerance = "None")]
+    fn near_blank<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        min_content_area: Option<f64>,
+        tolerance: Option<u8>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py,
+                "near_blank" => true,
+                "min_content_area" => min_content_area,
+                "background_tolerance" => tolerance,
+            ),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag images whose content hugs one edge or corner, the rest being
+    /// background.
+    #[args(max_extent = "None", variance = "None")]
+    fn edge_content<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        max_extent: Option<f64>,
+        variance: Option<f64>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py,
+                "edge_content" => true,
+                "max_content_extent" => max_extent,
+                "content_variance" => variance,
+            ),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag images made of the same tile repeated many times.
+    #[args(min_repetition = "None")]
+    fn repeated_tiles<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        min_repetition: Option<f64>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py,
+                "repeated_tiles" => true,
+                "min_tile_repetition" => min_repetition,
+            ),
+        )?;
+        Ok(slf)
+    }
+
+    /// Run images through given ONNX classifier, flagging those whose score
+    /// is above given threshold, or below it ("below").
+    #[args(output = "None", threshold = "None", flag = "None")]
+    fn model<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        path: String,
+        output: Option<usize>,
+        threshold: Option<f64>,
+        flag: Option<String>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py,
+                "model" => path,
+                "model_output" => output,
+                "model_threshold" => threshold,
+                "model_flag" => flag,
+            ),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag images breaking the contract of the dataset on channels and bit
+    /// depth, as `contract(channels=3, bit_depth=8)`, or convert them in the
+    /// cleaned copy with `fix`.
+    #[args(channels = "None", bit_depth = "None", fix = "None")]
+    fn contract<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        channels: Option<u8>,
+        bit_depth: Option<u8>,
+        fix: Option<bool>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py,
+                "min_channels" => channels,
+                "max_channels" => channels,
+                "min_bit_depth" => bit_depth,
+                "max_bit_depth" => bit_depth,
+                "fix_contract" => fix,
+            ),
+        )?;
+        Ok(slf)
+    }
+
+    /// Check `{stem}.txt` captions of images, flagging images whose caption
+    /// is missing, blank or isn't UTF-8, and captions without an image.
+    fn captions<'p>(mut slf: PyRefMut<'p, Self>, py: Python) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(py, options!(py, "captions" => true,))?;
+        Ok(slf)
+    }
+
+    /// Flag images by their EXIF metadata: taken out of a date range, by
+    /// one of given cameras, without a capture date, or with or without
+    /// GPS coordinates ("present" or "absent").
+    #[args(
+        taken_after = "None",
+        taken_before = "None",
+        cameras = "None",
+        require_timestamp = "None",
+        gps = "None"
+    )]
+    fn metadata<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        taken_after: Option<String>,
+        taken_before: Option<String>,
+        cameras: Option<Vec<String>>,
+        require_timestamp: Option<bool>,
+        gps: Option<String>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py,
+                "taken_after" => taken_after,
+                "taken_before" => taken_before,
+                "flag_cameras" => cameras,
+                "require_timestamp" => require_timestamp,
+                "flag_gps" => gps,
+            ),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag images looking like one of the placeholders of given folder.
+    #[args(distance = "None")]
+    fn placeholders<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        folder: FsPath,
+        distance: Option<u32>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py, "placeholders" => folder, "placeholder_distance" => distance),
+        )?;
+        Ok(slf)
+    }
+
+    /// Flag duplicated images, keeping one of each group.
+    #[args(distance = "None", keep = "None")]
+    fn duplicates<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        distance: Option<u32>,
+        keep: Option<String>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(
+            py,
+            options!(py,
+                "duplicates" => true,
+                "duplicate_distance" => distance,
+                "keep_duplicates" => keep,
+            ),
+        )?;
+        Ok(slf)
+    }
+
+    /// Look into subfolders, or not.
+    #[args(enabled = "true")]
+    fn recursive<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        enabled: bool,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(py, options!(py, "recursive" => enabled))?;
+        Ok(slf)
+    }
+
+    /// Scan only files matching one of given paths or glob patterns.
+    #[args(patterns = "*")]
+    fn include<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        patterns: Vec<String>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(py, options!(py, "include" => patterns))?;
+        Ok(slf)
+    }
+
+    /// Leave out files matching one of given paths or glob patterns.
+    #[args(patterns = "*")]
+    fn exclude<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        patterns: Vec<String>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(py, options!(py, "exclude" => patterns))?;
+        Ok(slf)
+    }
+
+    /// Never flag files matching one of given paths or glob patterns.
+    #[args(patterns = "*")]
+    fn protect<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python,
+        patterns: Vec<String>,
+    ) -> PyResult<PyRefMut<'p, Self>> {
+        slf.set(py, options!(py, "protect" => patterns))?;
+        Ok(slf)
+    }
+
+    /// Options set so far, as keyword arguments of `clean`.
+    #[getter]
+    fn get_options<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        self.options(py, None)
+    }
+
+    /// Select images to delete from root folder, as `clean` does.
+    /// Keyword arguments override options for this scan only.
+    #[args(
+        root_folder,
+        "*",
+        as_dataframe = "false",
+        as_arrays = "false",
+        with_summary = "false",
+        kwargs = "**"
+    )]
+    pub fn clean(
+        &self,
+        py: Python,
+        root_folder: Roots,
+        as_dataframe: bool,
+        as_arrays: bool,
+        with_summary: bool,
+        kwargs: Option<&PyDict>,
+    ) -> PyResult<PyObject> {
+        let (reports, summary) =
+            run_summarized(py, &root_folder.0, Some(self.options(py, kwargs)?))?;
+
+        let result: PyObject = if as_arrays {
+            Columns::new(&reports).to_arrays(py)?.into()
+        } else if as_dataframe {
+            let columns = Columns::new(&reports).to_dict(py)?;
+            py.import("pandas")?.call1("DataFrame", (columns,))?.into()
+        } else {
+            PyList::new(py, flagged(reports).into_iter().map(FsPath)).into()
+        };
+
+        match with_summary {
+            true => Ok((result, ScanSummary::from(summary)).into_py(py)),
+            false => Ok(result),
+        }
+    }
+
+    /// Run checks over every file of root folder, as `scan` does.
+    /// Keyword arguments override options for this scan only.
+    #[args(root_folder, kwargs = "**")]
+    pub fn scan<'py>(
+        &self,
+        py: Python<'py>,
+        root_folder: Roots,
+        kwargs: Option<&PyDict>,
+    ) -> PyResult<&'py PyList> {
+        to_list(
+            py,
+            run(py, &root_folder.0, Some(self.options(py, kwargs)?))?,
+        )
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for Cleaner {
+    fn __repr__(&self) -> PyResult<String> {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+
+        let options = self
+            .options
+            .as_ref(py)
+            .iter()
+            .map(|(key, value)| Ok(format!("{}={}", key, value.repr()?)))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        Ok(format!("Cleaner({})", options.join(", ")))
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(feature = "cloud")]
+use std::io;
+
+/// Check if given root folder is an object store prefix.
+pub fn is_remote(root: &str) -> bool {
+    root.starts_with("s3://") || root.starts_with("gs://")
+}
+
+/// Objects of a bucket, listed and read without syncing them locally.
+/// Credentials are taken from the environment (`AWS_ACCESS_KEY_ID`,
+/// `GOOGLE_SERVICE_ACCOUNT`, ...).
+#[cfg(feature = "cloud")]
+pub struct Bucket {
+    scheme: &'static str,
+    bucket: String,
+    prefix: Option<object_store::path::Path>,
+    store: Box<dyn object_store::ObjectStore>,
+    runtime: tokio::runtime::Runtime,
+}
+
+#[cfg(feature = "cloud")]
+impl Bucket {
+    /// Connect to bucket of given `s3://bucket/prefix` or `gs://bucket/prefix`.
+    pub fn open(root: &str) -> io::Result<Self> {
+        use object_store::{aws::AmazonS3Builder, gcp::GoogleCloudStorageBuilder};
+
+        let (scheme, rest) = root.split_once("://").unwrap();
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+
+        let store: Box<dyn object_store::ObjectStore> = match scheme {
+            "s3" => Box::new(
+                AmazonS3Builder::from_env()
+                    .with_bucket_name(bucket)
+                    .build()
+                    .map_err(io::Error::other)?,
+            ),
+            _ => Box::new(
+                GoogleCloudStorageBuilder::from_env()
+                    .with_bucket_name(bucket)
+                    .build()
+                    .map_err(io::Error::other)?,
+            ),
+        };
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+
+        Ok(Bucket {
+            scheme: if scheme == "s3" { "s3" } else { "gs" },
+            bucket: bucket.to_string(),
+            prefix: Some(prefix)
+                .filter(|prefix| !prefix.is_empty())
+                .map(object_store::path::Path::from),
+            store,
+            runtime,
+        })
+    }
+
+    /// List keys of objects directly under prefix, like a folder.
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        let listing = self
+            .runtime
+            .block_on(self.store.list_with_delimiter(self.prefix.as_ref()))
+            .map_err(io::Error::other)?;
+
+        Ok(listing
+            .objects
+            .into_iter()
+            .map(|object| object.location.to_string())
+            .collect())
+    }
+
+    /// Read content of object of given key.
+    pub fn read(&self, key: &str) -> io::Result<Vec<u8>> {
+        let location = object_store::path::Path::from(key);
+
+        let bytes = self
+            .runtime
+            .block_on(async { self.store.get(&location).await?.bytes().await })
+            .map_err(io::Error::other)?;
+
+        Ok(bytes.to_vec())
+    }
+
+    /// Full URL of object of given key.
+    pub fn url(&self, key: &str) -> String {
+        format!("{}://{}/{}", self.scheme, self.bucket, key)
+    }
+}
@@ -0,0 +1,65 @@
+use crate::labels::{ambiguous_names, Removed};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Image id as a set key, ids are numbers or strings depending on tools.
+fn image_id(id: Option<&Value>) -> String {
+    id.map(Value::to_string).unwrap_or_default()
+}
+
+/// Remove images matching removed files from given COCO annotations,
+/// along with their annotations, and write the result to output.
+/// Return the number of removed images and annotations.
+pub fn sync_coco(
+    annotations: &str,
+    removed: &[String],
+    output: &str,
+) -> io::Result<(usize, usize)> {
+    let mut coco: Value = serde_json::from_reader(BufReader::new(File::open(annotations)?))?;
+    let removed = Removed::new(removed, annotations)?;
+
+    let images = coco
+        .get_mut("images")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| invalid("annotations have no images list"))?;
+
+    let file_name = |image: &Value| {
+        image
+            .get("file_name")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string()
+    };
+    let file_names: Vec<String> = images.iter().map(file_name).collect();
+    let ambiguous = ambiguous_names(file_names.iter().map(String::as_str));
+
+    let mut removed_ids = HashSet::new();
+    images.retain(|image| {
+        if removed.contains(&file_name(image), &ambiguous) {
+            removed_ids.insert(image_id(image.get("id")));
+            false
+        } else {
+            true
+        }
+    });
+
+    // Annotations without image would break loaders
+    let mut removed_annotations = 0;
+    if let Some(entries) = coco.get_mut("annotations").and_then(Value::as_array_mut) {
+        let count = entries.len();
+        entries.retain(|entry| !removed_ids.contains(&image_id(entry.get("image_id"))));
+        removed_annotations = count - entries.len();
+    }
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    serde_json::to_writer(&mut writer, &coco)?;
+    writer.flush()?;
+
+    Ok((removed_ids.len(), removed_annotations))
+}
@@ -0,0 +1,1481 @@
+use crate::config_file;
+use crate::exif;
+use crate::patterns::Patterns;
+use crate::report::{Reason, Report, Severities, Severity};
+use crate::sampling;
+use crate::stats::Pixel;
+use crate::tags;
+use crate::walk::Modified;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{self, IsTerminal};
+
+/// Invalid option, raised as a `TypeError` or a `ValueError` in python.
+#[derive(Debug)]
+pub enum OptionError {
+    Type(String),
+    Value(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionError::Type(message) | OptionError::Value(message) => f.write_str(message),
+            OptionError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<io::Error> for OptionError {
+    fn from(err: io::Error) -> Self {
+        OptionError::Io(err)
+    }
+}
+
+impl From<OptionError> for PyErr {
+    fn from(err: OptionError) -> Self {
+        match err {
+            OptionError::Type(message) => PyTypeError::new_err(message),
+            OptionError::Value(message) => PyValueError::new_err(message),
+            OptionError::Io(err) => err.into(),
+        }
+    }
+}
+
+/// Types options are given as, read from their parsed value.
+trait Extract<'a>: Sized {
+    /// What values of the type look like, for error messages.
+    const EXPECTED: &'static str;
+
+    fn extract(value: &'a Value) -> Option<Self>;
+}
+
+impl<'a> Extract<'a> for bool {
+    const EXPECTED: &'static str = "a boolean";
+
+    fn extract(value: &'a Value) -> Option<Self> {
+        value.as_bool()
+    }
+}
+
+impl<'a> Extract<'a> for f64 {
+    const EXPECTED: &'static str = "a number";
+
+    fn extract(value: &'a Value) -> Option<Self> {
+        value.as_f64()
+    }
+}
+
+impl<'a> Extract<'a> for f32 {
+    const EXPECTED: &'static str = "a number";
+
+    fn extract(value: &'a Value) -> Option<Self> {
+        value.as_f64().map(|value| value as f32)
+    }
+}
+
+macro_rules! extract_integers {
+    ($($integer:ty),*) => {$(
+        impl<'a> Extract<'a> for $integer {
+            const EXPECTED: &'static str = "a positive integer";
+
+            fn extract(value: &'a Value) -> Option<Self> {
+                value.as_u64()?.try_into().ok()
+            }
+        }
+    )*};
+}
+
+extract_integers!(u8, u32, u64, usize);
+
+impl<'a> Extract<'a> for &'a str {
+    const EXPECTED: &'static str = "a string";
+
+    fn extract(value: &'a Value) -> Option<Self> {
+        value.as_str()
+    }
+}
+
+impl<'a> Extract<'a> for String {
+    const EXPECTED: &'static str = "a string";
+
+    fn extract(value: &'a Value) -> Option<Self> {
+        value.as_str().map(str::to_string)
+    }
+}
+
+impl<'a, T: Extract<'a>> Extract<'a> for Option<T> {
+    const EXPECTED: &'static str = T::EXPECTED;
+
+    fn extract(value: &'a Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(None),
+            value => T::extract(value).map(Some),
+        }
+    }
+}
+
+impl<'a, T: Extract<'a>> Extract<'a> for Vec<T> {
+    const EXPECTED: &'static str = "a list";
+
+    fn extract(value: &'a Value) -> Option<Self> {
+        value.as_array()?.iter().map(T::extract).collect()
+    }
+}
+
+impl<'a> Extract<'a> for HashMap<String, String> {
+    const EXPECTED: &'static str = "a dictionary of strings";
+
+    fn extract(value: &'a Value) -> Option<Self> {
+        value
+            .as_object()?
+            .iter()
+            .map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+            .collect()
+    }
+}
+
+/// Read option `key` from its value, rejecting values of another type.
+fn extract<'a, T: Extract<'a>>(key: &str, value: &'a Value) -> Result<T, OptionError> {
+    T::extract(value)
+        .ok_or_else(|| OptionError::Type(format!("{} must be {}, not {}", key, T::EXPECTED, value)))
+}
+
+/// What to do with animated images (GIF, APNG).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationPolicy {
+    /// Check sampled frames, keep the animation if they pass.
+    Keep,
+    /// Flag every animation.
+    Flag,
+    /// Check the first frame only, which is the one kept in a cleaned dataset.
+    FirstFrame,
+}
+
+impl AnimationPolicy {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "keep" => Ok(AnimationPolicy::Keep),
+            "flag" => Ok(AnimationPolicy::Flag),
+            "first_frame" => Ok(AnimationPolicy::FirstFrame),
+            _ => Err(OptionError::Value(format!(
+                "keep_animations must be 'keep', 'flag' or 'first_frame', not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Which image of a group of duplicates is kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeepPolicy {
+    /// The first one by path.
+    First,
+    /// The one with the most pixels.
+    Resolution,
+    /// The largest file.
+    FileSize,
+    /// The one with the highest blur score.
+    Sharpest,
+    /// The one with the shortest path.
+    ShortestPath,
+}
+
+impl KeepPolicy {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "first" => Ok(KeepPolicy::First),
+            "resolution" => Ok(KeepPolicy::Resolution),
+            "file_size" => Ok(KeepPolicy::FileSize),
+            "sharpest" => Ok(KeepPolicy::Sharpest),
+            "shortest_path" => Ok(KeepPolicy::ShortestPath),
+            _ => Err(OptionError::Value(format!(
+                "keep_duplicates must be 'first', 'resolution', 'file_size', 'sharpest' or 'shortest_path', not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// How alike files must be to be duplicates, each level including
+/// the stricter ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateMatch {
+    /// Same content.
+    Bytes,
+    /// Same decoded pixels, whatever their encoding.
+    Pixels,
+    /// Close perceptual hashes.
+    Perceptual,
+}
+
+impl DuplicateMatch {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "bytes" => Ok(DuplicateMatch::Bytes),
+            "pixels" => Ok(DuplicateMatch::Pixels),
+            "perceptual" => Ok(DuplicateMatch::Perceptual),
+            _ => Err(OptionError::Value(format!(
+                "duplicate_match must be 'bytes', 'pixels' or 'perceptual', not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Order frames of a burst follow each other in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BurstOrder {
+    /// By path, as cameras number frames.
+    Name,
+    /// By EXIF capture date, then path.
+    Time,
+}
+
+impl BurstOrder {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "name" => Ok(BurstOrder::Name),
+            "time" => Ok(BurstOrder::Time),
+            _ => Err(OptionError::Value(format!(
+                "burst_order must be 'name' or 'time', not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// How thoroughly files are checked to be decodable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verify {
+    /// From their header alone, other checks being skipped.
+    Fast,
+    /// By decoding them.
+    Full,
+    /// By decoding them, flagging damage decoders put up with as well.
+    Strict,
+}
+
+impl Verify {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "fast" => Ok(Verify::Fast),
+            "full" => Ok(Verify::Full),
+            "strict" => Ok(Verify::Strict),
+            _ => Err(OptionError::Value(format!(
+                "verify must be 'fast', 'full' or 'strict', not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Where verdicts are written next to files, instead of deleting them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagTarget {
+    /// To a JSON file next to each image.
+    Sidecar,
+    /// To extended attributes of each image.
+    Xattr,
+}
+
+impl TagTarget {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "sidecar" => Ok(TagTarget::Sidecar),
+            "xattr" => Ok(TagTarget::Xattr),
+            _ => Err(OptionError::Value(format!(
+                "tag must be 'sidecar' or 'xattr', not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Order reports are returned in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Path,
+}
+
+impl SortKey {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "path" => Ok(SortKey::Path),
+            _ => Err(OptionError::Value(format!(
+                "sort must be 'path' or None, not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// What to do with symbolic links found in scanned folders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Check them as the files they point to, under their own path.
+    Keep,
+    /// Leave them out of reports.
+    Skip,
+    /// Check the files and folders they point to under their resolved path,
+    /// once even if several links point to them.
+    Resolve,
+}
+
+impl SymlinkPolicy {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "keep" => Ok(SymlinkPolicy::Keep),
+            "skip" => Ok(SymlinkPolicy::Skip),
+            "resolve" => Ok(SymlinkPolicy::Resolve),
+            _ => Err(OptionError::Value(format!(
+                "follow_symlinks must be 'keep', 'skip' or 'resolve', not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Which images are flagged according to the GPS coordinates
+/// in their EXIF metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpsPolicy {
+    /// Those holding coordinates, which may leak where they were taken.
+    Present,
+    /// Those without coordinates.
+    Absent,
+}
+
+impl GpsPolicy {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "present" => Ok(GpsPolicy::Present),
+            "absent" => Ok(GpsPolicy::Absent),
+            _ => Err(OptionError::Value(format!(
+                "flag_gps must be 'present' or 'absent', not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Parse given date option into the form EXIF dates take.
+fn parse_date(key: &str, value: Option<&str>) -> Result<Option<String>, OptionError> {
+    match value {
+        Some(date) => match exif::parse_date(date) {
+            Some(date) => Ok(Some(date)),
+            None => Err(OptionError::Value(format!(
+                "{} must be a date as 'YYYY-MM-DD' or 'YYYY-MM-DD HH:MM:SS', not '{}'",
+                key, date
+            ))),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Parse given time option, as seconds since epoch or a date string (UTC).
+/// A `datetime` is given as its timestamp.
+fn parse_time(key: &str, value: &Value) -> Result<Option<f64>, OptionError> {
+    match value {
+        Value::Null => Ok(None),
+        Value::String(date) => Ok(parse_date(key, Some(date))?.map(|date| exif::timestamp(&date))),
+        value => match value.as_f64() {
+            Some(seconds) => Ok(Some(seconds)),
+            None => Err(OptionError::Type(format!(
+                "{} must be a timestamp, a date or a datetime",
+                key
+            ))),
+        },
+    }
+}
+
+/// Parse severities of reasons, given as a dictionary
+/// like `{"upscaled": "warn"}`. Reasons left out keep their default one.
+fn parse_severities(value: &Value) -> Result<Severities, OptionError> {
+    let mut severities = Severities::default();
+
+    for (reason, severity) in extract::<HashMap<String, String>>("severity", value)? {
+        let reason = Reason::parse(&reason).ok_or_else(|| {
+            OptionError::Value(format!("severity given for unknown reason '{}'", reason))
+        })?;
+        let severity = match severity.as_str() {
+            "delete" => Severity::Delete,
+            "warn" => Severity::Warn,
+            "info" => Severity::Info,
+            "suspect" => Severity::Suspect,
+            _ => {
+                return Err(OptionError::Value(format!(
+                    "severity must be 'delete', 'warn', 'info' or 'suspect', not '{}'",
+                    severity
+                )))
+            }
+        };
+        severities.set(reason, severity);
+    }
+
+    Ok(severities)
+}
+
+/// Options of given keyword arguments, along with those of the config file
+/// `config_file` points to. Keyword arguments take precedence over the file.
+fn with_config_file(mut options: Map<String, Value>) -> Result<Map<String, Value>, OptionError> {
+    let path: String = match options.remove("config_file") {
+        Some(path) => extract("config_file", &path)?,
+        None => return Ok(options),
+    };
+
+    let mut merged = config_file::load(&path)?;
+    merged.extend(options);
+
+    Ok(merged)
+}
+
+/// How progress of a scan is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// A progress bar, for terminals.
+    Bar,
+    /// Periodic log lines, for CI jobs and cron tasks.
+    Log,
+    /// Nothing, for library use.
+    None,
+}
+
+impl ProgressMode {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "bar" => Ok(ProgressMode::Bar),
+            "log" => Ok(ProgressMode::Log),
+            "none" => Ok(ProgressMode::None),
+            _ => Err(OptionError::Value(format!(
+                "progress must be 'bar', 'log' or 'none', not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Format kept images are re-encoded to in a cleaned dataset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+}
+
+impl OutputFormat {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "jpeg" | "jpg" => Ok(OutputFormat::Jpeg),
+            "png" => Ok(OutputFormat::Png),
+            _ => Err(OptionError::Value(format!(
+                "output_format must be 'jpeg' or 'png', not '{}'",
+                value
+            ))),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Png => "png",
+        }
+    }
+}
+
+/// Layout of the input tensor of a model, channels coming
+/// before or after rows and columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelLayout {
+    Nchw,
+    Nhwc,
+}
+
+impl ModelLayout {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "nchw" => Ok(ModelLayout::Nchw),
+            "nhwc" => Ok(ModelLayout::Nhwc),
+            _ => Err(OptionError::Value(format!(
+                "model_layout must be 'nchw' or 'nhwc', not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Side of the threshold of a model images are flagged on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelFlag {
+    Above,
+    Below,
+}
+
+impl ModelFlag {
+    fn parse(value: &str) -> Result<Self, OptionError> {
+        match value {
+            "above" => Ok(ModelFlag::Above),
+            "below" => Ok(ModelFlag::Below),
+            _ => Err(OptionError::Value(format!(
+                "model_flag must be 'above' or 'below', not '{}'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Parse a value for each of the red, green and blue channels.
+fn parse_channels(key: &str, value: &Value) -> Result<[f32; 3], OptionError> {
+    match extract::<Vec<f32>>(key, value)?.as_slice() {
+        [r, g, b] => Ok([*r, *g, *b]),
+        values => Err(OptionError::Value(format!(
+            "{} must hold 3 values, not {}",
+            key,
+            values.len()
+        ))),
+    }
+}
+
+/// Variance below which a channel is flat by default.
+const SOLID_COLOR_THRESHOLD: f64 = 20.0;
+
+/// Parse solid color thresholds, given as a single one for every channel,
+/// or one for red, green and blue then optionally alpha.
+fn parse_thresholds(value: &Value) -> Result<[f64; 4], OptionError> {
+    if let Some(threshold) = value.as_f64() {
+        return Ok([threshold; 4]);
+    }
+
+    match extract::<Vec<f64>>("solid_color_threshold", value)?.as_slice() {
+        [r, g, b] => Ok([*r, *g, *b, SOLID_COLOR_THRESHOLD]),
+        [r, g, b, a] => Ok([*r, *g, *b, *a]),
+        thresholds => Err(OptionError::Value(format!(
+            "solid_color_threshold must hold 3 or 4 values, not {}",
+            thresholds.len()
+        ))),
+    }
+}
+
+/// Options accepted by `clean` and `scan` as keyword arguments.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Folder of reference placeholder images ("image not available", ...).
+    pub placeholders: Option<String>,
+    /// Maximum hamming distance between two perceptual hashes
+    /// for an image to be considered as a placeholder.
+    pub placeholder_distance: u32,
+    /// Files scanned, every one if empty.
+    pub include: Patterns,
+    /// Files left out of the scan, even if included.
+    pub exclude: Patterns,
+    /// Files never flagged, whatever checks find.
+    pub protect: Patterns,
+    /// Files modified before this time, in seconds since epoch,
+    /// are left out of the scan.
+    pub modified_after: Option<f64>,
+    /// Files modified at this time or later are left out of the scan.
+    pub modified_before: Option<f64>,
+    /// Fraction of files scanned, picked at random.
+    pub sample: Option<f64>,
+    /// Number of files scanned, picked at random.
+    pub sample_n: Option<usize>,
+    /// Seed of the random pick of files, the same one
+    /// picking the same files.
+    pub sample_seed: u64,
+    /// What each reason means for a file, only those of "delete" severity
+    /// getting it flagged.
+    pub severity: Severities,
+    /// Flag color images whose channels are all equal.
+    pub grayscale: bool,
+    /// Maximum spread between channels of a pixel considered as gray.
+    pub grayscale_tolerance: u32,
+    /// Flag images upscaled from a smaller source.
+    pub upscaled: bool,
+    /// Detail score under which an image is considered as upscaled.
+    pub min_detail: f64,
+    /// Flag blurry images.
+    pub blur: bool,
+    /// Blur score under which an image is considered as blurry.
+    pub min_sharpness: f64,
+    /// Flag images holding too little information.
+    pub entropy: bool,
+    /// Entropy of the luminance, in bits, under which an image
+    /// is considered as holding too little information.
+    pub min_entropy: f64,
+    /// Flag images with heavy color banding.
+    pub banding: bool,
+    /// Proportion of the 256 possible levels a color channel
+    /// must hold for an image not to be considered as banded.
+    pub min_level_ratio: f64,
+    /// Flag images crossed by constant rows or columns (stuck sensor lines).
+    pub dead_lines: bool,
+    /// Maximum luminance spread along a line considered as constant.
+    pub dead_line_tolerance: u8,
+    /// Flag images tinted by one color channel dominating the others.
+    pub color_cast: bool,
+    /// Maximum deviation of a channel mean from the mean of the three,
+    /// on a 0-255 scale, before an image is considered as tinted.
+    pub max_color_cast: f64,
+    /// Flag images with many pixels crushed to black or blown to white.
+    pub clipping: bool,
+    /// Proportion of values of a channel that may sit at 0.
+    pub max_shadow_clipping: f64,
+    /// Proportion of values of a channel that may sit at 255.
+    pub max_highlight_clipping: f64,
+    /// Flag screenshots among photos.
+    pub screenshots: bool,
+    /// Number of screenshot signals an image must show to be flagged.
+    pub screenshot_signals: usize,
+    /// Proportion of an image covered by flat blocks, from which
+    /// it's considered as showing a user interface.
+    pub min_flat_area: f64,
+    /// Proportion of neighbouring pixels separated by a sharp edge,
+    /// from which an image is considered as showing text.
+    pub min_sharp_edges: f64,
+    /// Flag images carrying a watermark or an overlaid logo.
+    pub watermarks: bool,
+    /// Proportion of sharp edges in a corner from which it's
+    /// considered as holding an overlay.
+    pub min_corner_edges: f64,
+    /// Correlation of an image with itself shifted, from which
+    /// it's considered as holding a tiled watermark.
+    pub min_periodicity: f64,
+    /// Flag blank images but for a small foreground region.
+    pub near_blank: bool,
+    /// Proportion of an image holding content, under which it's
+    /// considered as blank.
+    pub min_content_area: f64,
+    /// Maximum difference with the background color of a channel
+    /// of a pixel still considered as background.
+    pub background_tolerance: u8,
+    /// Flag images whose content hugs one edge or corner while the rest
+    /// is background, as badly cropped product photos.
+    pub edge_content: bool,
+    /// Variance of the luminance of a tile from which it holds content.
+    pub content_variance: f64,
+    /// Proportion of the width or height of an image from one side
+    /// within which content is considered as hugging it.
+    pub max_content_extent: f64,
+    /// Flag images made of the same tile repeated many times.
+    pub repeated_tiles: bool,
+    /// Correlation of an image with itself shifted by one and two tiles,
+    /// from which it's considered as made of a repeated tile.
+    pub min_tile_repetition: f64,
+    /// Check `{stem}.txt` caption files going with images,
+    /// flagging captions without an image too.
+    pub captions: bool,
+    /// ONNX classifier images are run through.
+    pub model: Option<String>,
+    /// ONNX Runtime library, `ORT_DYLIB_PATH` or the one of the system if unset.
+    pub model_runtime: Option<String>,
+    /// Side of the square images are resized to for the model.
+    pub model_input_size: u32,
+    /// Layout of the input tensor of the model.
+    pub model_layout: ModelLayout,
+    /// Mean of each channel subtracted from pixels scaled to 0-1.
+    pub model_mean: [f32; 3],
+    /// Standard deviation of each channel pixels are divided by.
+    pub model_std: [f32; 3],
+    /// Index of the score flagged images are told by, among
+    /// those the model gives for an image.
+    pub model_output: usize,
+    /// Score images are flagged from.
+    pub model_threshold: f64,
+    /// Whether images scoring above the threshold are flagged, or below.
+    pub model_flag: ModelFlag,
+    /// Images run through the model at once, when that many are waiting.
+    pub model_batch_size: usize,
+    /// Flag images taken before this date, according to EXIF metadata,
+    /// as "YYYY:MM:DD HH:MM:SS".
+    pub taken_after: Option<String>,
+    /// Flag images taken at this date or later.
+    pub taken_before: Option<String>,
+    /// Flag images taken by a camera whose make and model hold one of
+    /// these names, lowercase.
+    pub flag_cameras: Vec<String>,
+    /// Flag images without a capture date.
+    pub require_timestamp: bool,
+    /// Flag images according to their GPS coordinates.
+    pub flag_gps: Option<GpsPolicy>,
+    /// Flag images whose colors are far from those of the rest of the dataset.
+    pub outliers: bool,
+    /// Distance to the color distribution of the dataset, in robust
+    /// standard deviations, from which an image is an outlier.
+    pub max_outlier_score: f64,
+    /// Report problems decoders put up with, as suspect by default.
+    pub decoder_warnings: bool,
+    /// How thoroughly files are checked to be decodable.
+    pub verify: Verify,
+    /// Flag files whose extension names another format than their content.
+    pub wrong_extensions: bool,
+    /// Bytes per pixel below which a file is too small for its dimensions.
+    pub min_bytes_per_pixel: Option<f64>,
+    /// Give kept files the extension of their format instead of flagging
+    /// them, renaming them in place or in the cleaned copy.
+    pub fix_extensions: bool,
+    /// Channels images of the dataset have at least.
+    pub min_channels: Option<u8>,
+    /// Channels images of the dataset have at most.
+    pub max_channels: Option<u8>,
+    /// Bits per channel images of the dataset have at least.
+    pub min_bit_depth: Option<u8>,
+    /// Bits per channel images of the dataset have at most.
+    pub max_bit_depth: Option<u8>,
+    /// Convert kept images breaking the contract on channels or bit depth
+    /// in the cleaned copy instead of flagging them.
+    pub fix_contract: bool,
+    /// What to do with animated images.
+    pub keep_animations: AnimationPolicy,
+    /// Maximum number of frames of an animation to run checks on.
+    pub animation_samples: usize,
+    /// Number of evenly spaced frames of a video to run checks on,
+    /// videos being flagged as such when 0.
+    pub video_frames: usize,
+    /// Check JPEG previews embedded in camera RAW files
+    /// instead of decoding sensor data.
+    pub raw_preview: bool,
+    /// Check files of subfolders too, instead of flagging subfolders.
+    pub recursive: bool,
+    /// What to do with symbolic links found in scanned folders.
+    pub follow_symlinks: SymlinkPolicy,
+    /// Check files stored in zip and tar archives instead of
+    /// flagging archives as non-image files.
+    pub archives: bool,
+    /// Check tar files as WebDataset shards, one report per sample.
+    pub webdataset: bool,
+    /// Folder where shards are written back without flagged samples.
+    pub webdataset_output: Option<String>,
+    /// Maximum number of simultaneous downloads when scanning URLs.
+    pub http_concurrency: usize,
+    /// Seconds after which a download is given up.
+    pub http_timeout: f64,
+    /// Size in bytes above which a download is given up.
+    pub max_download_size: u64,
+    /// Column of a CSV manifest holding paths,
+    /// manifests are read as one path per line otherwise.
+    pub manifest_column: Option<String>,
+    /// Folder where files passing every check are copied,
+    /// mirroring their place under root folder.
+    pub copy_clean_to: Option<String>,
+    /// Hard link kept files instead of copying them.
+    pub hard_link: bool,
+    /// Format kept images are re-encoded to, instead of being copied.
+    pub output_format: Option<OutputFormat>,
+    /// Kept images larger than this are shrunk, keeping their aspect ratio.
+    pub max_dimension: Option<u32>,
+    /// Quality of re-encoded JPEG images, from 1 to 100.
+    pub jpeg_quality: u8,
+    /// Store kept color images whose channels are all equal as grayscale.
+    pub grayscale_to_luma: bool,
+    /// Re-encode kept images without their EXIF, XMP and GPS metadata.
+    pub strip_metadata: bool,
+    /// Variance on a 0-255 scale below which a channel is flat,
+    /// for red, green, blue and alpha.
+    pub solid_color_threshold: [f64; 4],
+    /// Only check color channels for solid color images.
+    pub solid_color_ignore_alpha: bool,
+    /// Record statistics of the color channels of every image, to compute
+    /// normalization statistics of the dataset.
+    pub normalization: bool,
+    /// Largest side of the copy solid color and exposure statistics
+    /// are computed on, larger images are shrunk first.
+    pub stats_max_dimension: Option<u32>,
+    /// Fraction of pixels solid color statistics are computed on,
+    /// instead of shrinking the image.
+    pub stats_sampling: Option<f64>,
+    /// Compute solid color statistics, blur scores and entropies on the GPU.
+    pub gpu: bool,
+    /// How progress of a scan is shown, a bar if stderr is a terminal
+    /// and log lines otherwise by default.
+    pub progress: ProgressMode,
+    /// Order reports are returned in, whatever order threads checked
+    /// files in. Unsorted if unset.
+    pub sort: Option<SortKey>,
+    /// Bytes of images decoded at once, scanning threads wait
+    /// for others to be done when it's exceeded.
+    pub memory_budget: Option<u64>,
+    /// Width or height, read from headers, past which files aren't decoded.
+    pub max_decode_dimension: Option<u32>,
+    /// Bytes of decoded pixels, estimated from headers, past which files
+    /// aren't decoded.
+    pub max_decoded_bytes: Option<u64>,
+    /// Seconds decoding a file may take before it's given up on.
+    pub decode_timeout: Option<f64>,
+    /// Bytes read per second by scanning threads together, if limited.
+    pub max_read_rate: Option<u64>,
+    /// Files read per second by scanning threads together, if limited.
+    pub max_reads_per_second: Option<f64>,
+    /// Threads reading files ahead of checks, files are read by
+    /// the threads checking them if unset.
+    pub io_threads: Option<usize>,
+    /// Threads decoding and checking images, one per core if unset.
+    pub compute_threads: Option<usize>,
+    /// Files flagged past which the scan is aborted, as soon as it's
+    /// reached, a misconfigured threshold being more likely than that
+    /// many bad files.
+    pub max_flagged: Option<usize>,
+    /// Share of files flagged past which the scan is aborted, once it's over.
+    pub max_flagged_fraction: Option<f64>,
+    /// Group images that are copies of each other or look alike,
+    /// flagging all of them but one.
+    pub duplicates: bool,
+    /// Maximum hamming distance between perceptual hashes of near-duplicates.
+    pub duplicate_distance: u32,
+    /// How alike files must be to be duplicates.
+    pub duplicate_match: DuplicateMatch,
+    /// Also group images mirrored or rotated by 90 degrees from each other.
+    pub duplicate_orientations: bool,
+    /// Which image of a group of duplicates is kept.
+    pub keep_duplicates: KeepPolicy,
+    /// Group files sharing an inode, which are the same file, flagging
+    /// all of them but one as duplicates.
+    pub hardlinks: bool,
+    /// Group images of a folder following each other and looking alike,
+    /// flagging all of them but the sharpest.
+    pub bursts: bool,
+    /// Maximum hamming distance between perceptual hashes of
+    /// consecutive frames of a burst.
+    pub burst_distance: u32,
+    /// Order frames of a burst follow each other in.
+    pub burst_order: BurstOrder,
+    /// Maximum seconds between consecutive frames of a burst,
+    /// when ordered by time.
+    pub max_burst_gap: f64,
+    /// File where hashes of every scanned image are kept, so that
+    /// duplicates of images scanned before are found too.
+    pub hash_db: Option<String>,
+    /// Parquet file where a row of metrics is written for every file.
+    pub profile: Option<String>,
+    /// JSON file where figures of the scan are written, to monitor it.
+    pub run_summary: Option<String>,
+    /// File where content hashes of the files of the cleaned dataset are
+    /// written, for `verify_manifest` to tell later which ones changed.
+    pub hash_manifest: Option<String>,
+    /// Record time spent in each stage of checks and on files of each
+    /// extension, written to the run summary.
+    pub timings: bool,
+    /// Prometheus textfile where the same figures are written.
+    pub prometheus_textfile: Option<String>,
+    /// Where verdicts are written next to files, if anywhere.
+    pub tag: Option<TagTarget>,
+    /// File where reports are kept between scans, so that files
+    /// whose size and modification time didn't change aren't checked again.
+    pub cache: Option<String>,
+    /// Check every file again, ignoring reports of the cache.
+    pub no_cache: bool,
+    /// File where reports are written as the scan goes.
+    pub checkpoint: Option<String>,
+    /// Continue the scan interrupted while writing the checkpoint.
+    pub resume: bool,
+    /// File where destructive actions are recorded, to be undone.
+    pub journal: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            placeholders: None,
+            placeholder_distance: 8,
+            include: Patterns::default(),
+            exclude: Patterns::default(),
+            protect: Patterns::default(),
+            modified_after: None,
+            modified_before: None,
+            sample: None,
+            sample_n: None,
+            sample_seed: 0,
+            severity: Severities::default(),
+            grayscale: false,
+            grayscale_tolerance: 2,
+            upscaled: false,
+            min_detail: 0.22,
+            blur: false,
+            min_sharpness: 100.0,
+            entropy: false,
+            min_entropy: 3.0,
+            banding: false,
+            min_level_ratio: 0.125,
+            dead_lines: false,
+            dead_line_tolerance: 2,
+            color_cast: false,
+            max_color_cast: 30.0,
+            clipping: false,
+            max_shadow_clipping: 0.1,
+            max_highlight_clipping: 0.1,
+            screenshots: false,
+            screenshot_signals: 3,
+            min_flat_area: 0.3,
+            min_sharp_edges: 0.01,
+            watermarks: false,
+            min_corner_edges: 0.005,
+            min_periodicity: 0.5,
+            near_blank: false,
+            min_content_area: 0.02,
+            background_tolerance: 16,
+            edge_content: false,
+            content_variance: 20.0,
+            max_content_extent: 0.4,
+            repeated_tiles: false,
+            min_tile_repetition: 0.95,
+            captions: false,
+            model: None,
+            model_runtime: None,
+            model_input_size: 224,
+            model_layout: ModelLayout::Nchw,
+            // Those of ImageNet, which most classifiers are trained from
+            model_mean: [0.485, 0.456, 0.406],
+            model_std: [0.229, 0.224, 0.225],
+            model_output: 0,
+            model_threshold: 0.5,
+            model_flag: ModelFlag::Above,
+            model_batch_size: 32,
+            taken_after: None,
+            taken_before: None,
+            flag_cameras: Vec::new(),
+            require_timestamp: false,
+            flag_gps: None,
+            outliers: false,
+            max_outlier_score: 6.0,
+            decoder_warnings: false,
+            verify: Verify::Full,
+            wrong_extensions: false,
+            min_bytes_per_pixel: None,
+            fix_extensions: false,
+            min_channels: None,
+            max_channels: None,
+            min_bit_depth: None,
+            max_bit_depth: None,
+            fix_contract: false,
+            keep_animations: AnimationPolicy::Keep,
+            animation_samples: 4,
+            video_frames: 0,
+            raw_preview: false,
+            recursive: false,
+            follow_symlinks: SymlinkPolicy::Keep,
+            archives: false,
+            webdataset: false,
+            webdataset_output: None,
+            http_concurrency: 8,
+            http_timeout: 30.0,
+            max_download_size: 64 * 1024 * 1024,
+            manifest_column: None,
+            copy_clean_to: None,
+            hard_link: false,
+            output_format: None,
+            max_dimension: None,
+            jpeg_quality: 90,
+            grayscale_to_luma: false,
+            strip_metadata: false,
+            solid_color_threshold: [SOLID_COLOR_THRESHOLD; 4],
+            solid_color_ignore_alpha: false,
+            normalization: false,
+            stats_max_dimension: Some(256),
+            stats_sampling: None,
+            gpu: false,
+            progress: match io::stderr().is_terminal() {
+                true => ProgressMode::Bar,
+                false => ProgressMode::Log,
+            },
+            memory_budget: None,
+            max_decode_dimension: None,
+            max_decoded_bytes: None,
+            decode_timeout: None,
+            max_read_rate: None,
+            max_reads_per_second: None,
+            sort: Some(SortKey::Path),
+            io_threads: None,
+            compute_threads: None,
+            max_flagged: None,
+            max_flagged_fraction: None,
+            duplicates: false,
+            duplicate_distance: 4,
+            duplicate_match: DuplicateMatch::Perceptual,
+            duplicate_orientations: false,
+            keep_duplicates: KeepPolicy::First,
+            bursts: false,
+            burst_distance: 3,
+            burst_order: BurstOrder::Name,
+            max_burst_gap: 2.0,
+            hardlinks: false,
+            hash_db: None,
+            profile: None,
+            run_summary: None,
+            hash_manifest: None,
+            timings: false,
+            prometheus_textfile: None,
+            tag: None,
+            cache: None,
+            no_cache: false,
+            checkpoint: None,
+            resume: false,
+            journal: None,
+        }
+    }
+}
+
+impl Config {
+    /// Build configuration from python keyword arguments.
+    /// Unknown arguments are rejected the same way python would.
+    pub fn from_kwargs(kwargs: Option<&PyDict>) -> PyResult<Self> {
+        let options = match kwargs {
+            Some(kwargs) => config_file::to_options(kwargs)?,
+            None => Map::new(),
+        };
+
+        Ok(Config::from_options(options)?)
+    }
+
+    /// Build configuration from options keyed like keyword arguments,
+    /// as the C interface and config files give them.
+    pub fn from_options(options: Map<String, Value>) -> Result<Self, OptionError> {
+        let mut config = Config::default();
+
+        for (key, value) in &with_config_file(options)? {
+            let key = key.as_str();
+            match key {
+                "placeholders" => config.placeholders = extract(key, value)?,
+                "include" => {
+                    config.include = Patterns::new("include", &extract::<Vec<String>>(key, value)?)?
+                }
+                "exclude" => {
+                    config.exclude = Patterns::new("exclude", &extract::<Vec<String>>(key, value)?)?
+                }
+                "protect" => {
+                    config.protect = Patterns::new("protect", &extract::<Vec<String>>(key, value)?)?
+                }
+                "severity" => config.severity = parse_severities(value)?,
+                "modified_after" => config.modified_after = parse_time(key, value)?,
+                "modified_before" => config.modified_before = parse_time(key, value)?,
+                "sample" => config.sample = extract(key, value)?,
+                "sample_n" => config.sample_n = extract(key, value)?,
+                "sample_seed" => config.sample_seed = extract(key, value)?,
+                "placeholder_distance" => config.placeholder_distance = extract(key, value)?,
+                "grayscale" => config.grayscale = extract(key, value)?,
+                "grayscale_tolerance" => config.grayscale_tolerance = extract(key, value)?,
+                "upscaled" => config.upscaled = extract(key, value)?,
+                "min_detail" => config.min_detail = extract(key, value)?,
+                "blur" => config.blur = extract(key, value)?,
+                "min_sharpness" => config.min_sharpness = extract(key, value)?,
+                "entropy" => config.entropy = extract(key, value)?,
+                "min_entropy" => config.min_entropy = extract(key, value)?,
+                "banding" => config.banding = extract(key, value)?,
+                "min_level_ratio" => config.min_level_ratio = extract(key, value)?,
+                "dead_lines" => config.dead_lines = extract(key, value)?,
+                "dead_line_tolerance" => config.dead_line_tolerance = extract(key, value)?,
+                "color_cast" => config.color_cast = extract(key, value)?,
+                "max_color_cast" => config.max_color_cast = extract(key, value)?,
+                "clipping" => config.clipping = extract(key, value)?,
+                "max_shadow_clipping" => config.max_shadow_clipping = extract(key, value)?,
+                "max_highlight_clipping" => config.max_highlight_clipping = extract(key, value)?,
+                "screenshots" => config.screenshots = extract(key, value)?,
+                "screenshot_signals" => config.screenshot_signals = extract(key, value)?,
+                "min_flat_area" => config.min_flat_area = extract(key, value)?,
+                "min_sharp_edges" => config.min_sharp_edges = extract(key, value)?,
+                "watermarks" => config.watermarks = extract(key, value)?,
+                "min_corner_edges" => config.min_corner_edges = extract(key, value)?,
+                "min_periodicity" => config.min_periodicity = extract(key, value)?,
+                "near_blank" => config.near_blank = extract(key, value)?,
+                "min_content_area" => config.min_content_area = extract(key, value)?,
+                "background_tolerance" => config.background_tolerance = extract(key, value)?,
+                "edge_content" => config.edge_content = extract(key, value)?,
+                "content_variance" => config.content_variance = extract(key, value)?,
+                "max_content_extent" => config.max_content_extent = extract(key, value)?,
+                "repeated_tiles" => config.repeated_tiles = extract(key, value)?,
+                "min_tile_repetition" => config.min_tile_repetition = extract(key, value)?,
+                "captions" => config.captions = extract(key, value)?,
+                "model" => config.model = extract(key, value)?,
+                "model_runtime" => config.model_runtime = extract(key, value)?,
+                "model_input_size" => config.model_input_size = extract(key, value)?,
+                "model_layout" => config.model_layout = ModelLayout::parse(extract(key, value)?)?,
+                "model_mean" => config.model_mean = parse_channels(key, value)?,
+                "model_std" => config.model_std = parse_channels(key, value)?,
+                "model_output" => config.model_output = extract(key, value)?,
+                "model_threshold" => config.model_threshold = extract(key, value)?,
+                "model_flag" => config.model_flag = ModelFlag::parse(extract(key, value)?)?,
+                "model_batch_size" => config.model_batch_size = extract(key, value)?,
+                "taken_after" => config.taken_after = parse_date(key, extract(key, value)?)?,
+                "taken_before" => config.taken_before = parse_date(key, extract(key, value)?)?,
+                "flag_cameras" => {
+                    config.flag_cameras = extract::<Vec<String>>(key, value)?
+                        .iter()
+                        .map(|camera| camera.to_lowercase())
+                        .collect()
+                }
+                "require_timestamp" => config.require_timestamp = extract(key, value)?,
+                "outliers" => config.outliers = extract(key, value)?,
+                "max_outlier_score" => config.max_outlier_score = extract(key, value)?,
+                "decoder_warnings" => config.decoder_warnings = extract(key, value)?,
+                "verify" => config.verify = Verify::parse(extract(key, value)?)?,
+                "wrong_extensions" => config.wrong_extensions = extract(key, value)?,
+                "min_bytes_per_pixel" => config.min_bytes_per_pixel = extract(key, value)?,
+                "fix_extensions" => config.fix_extensions = extract(key, value)?,
+                "min_channels" => config.min_channels = extract(key, value)?,
+                "max_channels" => config.max_channels = extract(key, value)?,
+                "min_bit_depth" => config.min_bit_depth = extract(key, value)?,
+                "max_bit_depth" => config.max_bit_depth = extract(key, value)?,
+                "fix_contract" => config.fix_contract = extract(key, value)?,
+                "flag_gps" => {
+                    config.flag_gps = match extract::<Option<&str>>(key, value)? {
+                        Some(policy) => Some(GpsPolicy::parse(policy)?),
+                        None => None,
+                    }
+                }
+                "keep_animations" => {
+                    config.keep_animations = AnimationPolicy::parse(extract(key, value)?)?
+                }
+                "animation_samples" => config.animation_samples = extract(key, value)?,
+                "video_frames" => config.video_frames = extract(key, value)?,
+                "raw_preview" => config.raw_preview = extract(key, value)?,
+                "recursive" => config.recursive = extract(key, value)?,
+                "follow_symlinks" => {
+                    config.follow_symlinks = SymlinkPolicy::parse(extract(key, value)?)?
+                }
+                "archives" => config.archives = extract(key, value)?,
+                "webdataset" => config.webdataset = extract(key, value)?,
+                "webdataset_output" => config.webdataset_output = extract(key, value)?,
+                "http_concurrency" => config.http_concurrency = extract(key, value)?,
+                "http_timeout" => config.http_timeout = extract(key, value)?,
+                "max_download_size" => config.max_download_size = extract(key, value)?,
+                "manifest_column" => config.manifest_column = extract(key, value)?,
+                "copy_clean_to" => config.copy_clean_to = extract(key, value)?,
+                "hard_link" => config.hard_link = extract(key, value)?,
+                "output_format" => {
+                    config.output_format = match extract::<Option<&str>>(key, value)? {
+                        Some(format) => Some(OutputFormat::parse(format)?),
+                        None => None,
+                    }
+                }
+                "max_dimension" => config.max_dimension = extract(key, value)?,
+                "jpeg_quality" => config.jpeg_quality = extract(key, value)?,
+                "grayscale_to_luma" => config.grayscale_to_luma = extract(key, value)?,
+                "strip_metadata" => config.strip_metadata = extract(key, value)?,
+                "solid_color_threshold" => config.solid_color_threshold = parse_thresholds(value)?,
+                "solid_color_ignore_alpha" => {
+                    config.solid_color_ignore_alpha = extract(key, value)?
+                }
+                "normalization" => config.normalization = extract(key, value)?,
+                "stats_max_dimension" => config.stats_max_dimension = extract(key, value)?,
+                "stats_sampling" => config.stats_sampling = extract(key, value)?,
+                "gpu" => config.gpu = extract(key, value)?,
+                "progress" => config.progress = ProgressMode::parse(extract(key, value)?)?,
+                "memory_budget" => config.memory_budget = extract(key, value)?,
+                "max_decode_dimension" => config.max_decode_dimension = extract(key, value)?,
+                "max_decoded_bytes" => config.max_decoded_bytes = extract(key, value)?,
+                "decode_timeout" => config.decode_timeout = extract(key, value)?,
+                "max_read_rate" => config.max_read_rate = extract(key, value)?,
+                "max_reads_per_second" => config.max_reads_per_second = extract(key, value)?,
+                "io_threads" => config.io_threads = extract(key, value)?,
+                "sort" => {
+                    config.sort = match extract::<Option<&str>>(key, value)? {
+                        Some(key) => Some(SortKey::parse(key)?),
+                        None => None,
+                    }
+                }
+                "compute_threads" => config.compute_threads = extract(key, value)?,
+                "max_flagged" => config.max_flagged = extract(key, value)?,
+                "max_flagged_fraction" => config.max_flagged_fraction = extract(key, value)?,
+                "duplicates" => config.duplicates = extract(key, value)?,
+                "hardlinks" => config.hardlinks = extract(key, value)?,
+                "duplicate_distance" => config.duplicate_distance = extract(key, value)?,
+                "duplicate_match" => {
+                    config.duplicate_match = DuplicateMatch::parse(extract(key, value)?)?
+                }
+                "duplicate_orientations" => config.duplicate_orientations = extract(key, value)?,
+                "keep_duplicates" => {
+                    config.keep_duplicates = KeepPolicy::parse(extract(key, value)?)?
+                }
+                "bursts" => config.bursts = extract(key, value)?,
+                "burst_distance" => config.burst_distance = extract(key, value)?,
+                "burst_order" => config.burst_order = BurstOrder::parse(extract(key, value)?)?,
+                "max_burst_gap" => config.max_burst_gap = extract(key, value)?,
+                "hash_db" => config.hash_db = extract(key, value)?,
+                "profile" => config.profile = extract(key, value)?,
+                "run_summary" => config.run_summary = extract(key, value)?,
+                "hash_manifest" => config.hash_manifest = extract(key, value)?,
+                "timings" => config.timings = extract(key, value)?,
+                "tag" => {
+                    config.tag = match extract::<Option<&str>>(key, value)? {
+                        Some(target) => Some(TagTarget::parse(target)?),
+                        None => None,
+                    }
+                }
+                "prometheus_textfile" => config.prometheus_textfile = extract(key, value)?,
+                "cache" => config.cache = extract(key, value)?,
+                "no_cache" => config.no_cache = extract(key, value)?,
+                "checkpoint" => config.checkpoint = extract(key, value)?,
+                "resume" => config.resume = extract(key, value)?,
+                "journal" => config.journal = extract(key, value)?,
+                _ => {
+                    return Err(OptionError::Type(format!(
+                        "got an unexpected keyword argument '{}'",
+                        key
+                    )))
+                }
+            }
+        }
+
+        if let Some(sampling) = config.stats_sampling {
+            if !(sampling > 0.0 && sampling <= 1.0) {
+                return Err(OptionError::Value(format!(
+                    "stats_sampling must be between 0 and 1, not {}",
+                    sampling
+                )));
+            }
+        }
+
+        if let Some(sample) = config.sample {
+            if !(sample > 0.0 && sample <= 1.0) {
+                return Err(OptionError::Value(format!(
+                    "sample must be between 0 and 1, not {}",
+                    sample
+                )));
+            }
+        }
+        if config.min_channels > config.max_channels && config.max_channels.is_some() {
+            return Err(OptionError::Value(
+                "min_channels can't be above max_channels".into(),
+            ));
+        }
+        if config.min_bit_depth > config.max_bit_depth && config.max_bit_depth.is_some() {
+            return Err(OptionError::Value(
+                "min_bit_depth can't be above max_bit_depth".into(),
+            ));
+        }
+        if config.sample.is_some() && config.sample_n.is_some() {
+            return Err(OptionError::Value(
+                "sample and sample_n can't be used together".into(),
+            ));
+        }
+
+        if config.memory_budget == Some(0) {
+            return Err(OptionError::Value("memory_budget must be positive".into()));
+        }
+        if config.decode_timeout.is_some_and(|timeout| timeout <= 0.0) {
+            return Err(OptionError::Value("decode_timeout must be positive".into()));
+        }
+        if config.max_read_rate == Some(0)
+            || config.max_reads_per_second.is_some_and(|rate| rate <= 0.0)
+        {
+            return Err(OptionError::Value(
+                "max_read_rate and max_reads_per_second must be positive".into(),
+            ));
+        }
+        if config.io_threads == Some(0) || config.compute_threads == Some(0) {
+            return Err(OptionError::Value(
+                "io_threads and compute_threads must be positive".into(),
+            ));
+        }
+
+        if config
+            .max_flagged_fraction
+            .is_some_and(|fraction| !(0.0..=1.0).contains(&fraction))
+        {
+            return Err(OptionError::Value(
+                "max_flagged_fraction must be between 0 and 1".into(),
+            ));
+        }
+
+        // Hashes are only kept while looking for duplicates
+        if config.hash_db.is_some() {
+            config.duplicates = true;
+        }
+
+        if config.resume && config.checkpoint.is_none() {
+            return Err(OptionError::Value(
+                "resume requires a checkpoint file".into(),
+            ));
+        }
+
+        Ok(config)
+    }
+
+    /// Options reports depend on, a cache or checkpoint
+    /// written with other ones doesn't hold. Options picking which files
+    /// are scanned or protected, or where kept files are written, leave
+    /// reports of other files as they are. Whether contracts are fixed
+    /// rather than flagged depends on the cleaned copy, and is kept.
+    pub fn fingerprint(&self) -> String {
+        let config = Config {
+            fix_contract: self.fixes_contract(),
+            include: Patterns::default(),
+            exclude: Patterns::default(),
+            protect: Patterns::default(),
+            modified_after: None,
+            modified_before: None,
+            sample: None,
+            sample_n: None,
+            sample_seed: 0,
+            copy_clean_to: None,
+            hard_link: false,
+            output_format: None,
+            max_dimension: None,
+            jpeg_quality: 0,
+            grayscale_to_luma: false,
+            strip_metadata: false,
+            webdataset_output: None,
+            cache: None,
+            no_cache: false,
+            checkpoint: None,
+            resume: false,
+            journal: None,
+            hash_db: None,
+            progress: ProgressMode::None,
+            sort: None,
+            io_threads: None,
+            compute_threads: None,
+            max_flagged: None,
+            max_flagged_fraction: None,
+            max_read_rate: None,
+            max_reads_per_second: None,
+            run_summary: None,
+            hash_manifest: None,
+            timings: false,
+            prometheus_textfile: None,
+            tag: None,
+            model_runtime: None,
+            model_batch_size: 0,
+            ..self.clone()
+        };
+
+        format!("{:?}", config)
+    }
+
+    /// Fail if more files are flagged than allowed, out of given files
+    /// checked so far, so that nothing gets deleted. The share of files
+    /// flagged is only weighed once every file was checked.
+    pub fn check_flagged(&self, flagged: usize, files: Option<usize>) -> io::Result<()> {
+        let fraction = files.map(|files| flagged as f64 / files.max(1) as f64);
+        let limit = match (self.max_flagged, self.max_flagged_fraction, fraction) {
+            (Some(max), _, _) if flagged > max => format!("max_flagged ({})", max),
+            (_, Some(max), Some(fraction)) if fraction > max => {
+                format!("max_flagged_fraction ({})", max)
+            }
+            _ => return Ok(()),
+        };
+
+        Err(io::Error::other(format!(
+            "{} files flagged{}, above {}: scan aborted, nothing was deleted. \
+             Check thresholds or raise the limit",
+            flagged,
+            fraction.map_or(String::new(), |fraction| format!(
+                " ({:.1}%)",
+                fraction * 100.0
+            )),
+            limit
+        )))
+    }
+
+    /// Variances below which each channel of a solid color image is,
+    /// alpha being left out if ignored.
+    pub fn solid_color_thresholds(&self) -> Pixel {
+        let mut thresholds = Pixel::from_channels(&self.solid_color_threshold);
+        if self.solid_color_ignore_alpha {
+            thresholds.a = f64::INFINITY;
+        }
+        thresholds
+    }
+
+    /// Visit one pixel every this many when computing solid color statistics.
+    pub fn stats_step(&self) -> usize {
+        match self.stats_sampling {
+            Some(sampling) => (1.0 / sampling).round() as usize,
+            None => 1,
+        }
+    }
+
+    /// Check if given file is scanned, according to include
+    /// and exclude patterns. Sidecars of tagged files never are.
+    pub fn selects(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.contains(path))
+            && !self.exclude.contains(path)
+            && !tags::is_sidecar(path)
+    }
+
+    /// Range of modification times of files scanned.
+    pub fn modified(&self) -> Modified {
+        Modified {
+            after: self.modified_after,
+            before: self.modified_before,
+        }
+    }
+
+    /// Check if given file is part of the random sample of files
+    /// scanned, if only a fraction of them is.
+    pub fn samples(&self, path: &str) -> bool {
+        match self.sample {
+            Some(fraction) => sampling::keeps(path, fraction, self.sample_seed),
+            None => true,
+        }
+    }
+
+    /// Sort reasons of given report by severity, clearing those
+    /// that would get it deleted if its file is protected.
+    pub fn settle(&self, report: &mut Report) {
+        report.apply_severities(&self.severity);
+        if self.protect.contains(&report.path) {
+            report.reasons.clear();
+        }
+    }
+
+    /// Check if duplicates are looked for, keeping them by given policy.
+    pub fn keeps_duplicates_by(&self, policy: KeepPolicy) -> bool {
+        (self.duplicates || self.hardlinks) && self.keep_duplicates == policy
+    }
+
+    /// Check if images are filtered on their EXIF metadata.
+    pub fn filters_metadata(&self) -> bool {
+        self.taken_after.is_some()
+            || self.taken_before.is_some()
+            || !self.flag_cameras.is_empty()
+            || self.require_timestamp
+            || self.flag_gps.is_some()
+    }
+
+    /// Check if the dataset has a contract on channels or bit depth.
+    pub fn has_contract(&self) -> bool {
+        self.min_channels.is_some()
+            || self.max_channels.is_some()
+            || self.min_bit_depth.is_some()
+            || self.max_bit_depth.is_some()
+    }
+
+    /// Channels and bit depth honoring the contract of the dataset closest
+    /// to given ones, which are returned as is if they honor it already.
+    pub fn contract_layout(&self, (channels, bit_depth): (u8, u8)) -> (u8, u8) {
+        (
+            channels.clamp(
+                self.min_channels.unwrap_or(0),
+                self.max_channels.unwrap_or(u8::MAX),
+            ),
+            bit_depth.clamp(
+                self.min_bit_depth.unwrap_or(0),
+                self.max_bit_depth.unwrap_or(u8::MAX),
+            ),
+        )
+    }
+
+    /// Check if an image of given channels and bit depth breaks the
+    /// contract of the dataset.
+    pub fn breaks_contract(&self, layout: (u8, u8)) -> bool {
+        self.contract_layout(layout) != layout
+    }
+
+    /// Check if images breaking the contract are converted in the cleaned
+    /// copy rather than flagged.
+    pub fn fixes_contract(&self) -> bool {
+        self.fix_contract && self.copy_clean_to.is_some()
+    }
+
+    /// Check if kept images are re-encoded rather than copied.
+    pub fn normalizes(&self) -> bool {
+        self.output_format.is_some()
+            || self.max_dimension.is_some()
+            || self.grayscale_to_luma
+            || self.strip_metadata
+    }
+}
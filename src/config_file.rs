@@ -0,0 +1,98 @@
+use crate::config::OptionError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple};
+use serde_json::{Map, Number, Value};
+use std::fs;
+use std::path::Path;
+
+/// Load options from a TOML, YAML or JSON file, keyed by their keyword
+/// argument names. Options taking a dictionary are given as a table.
+pub fn load(path: &str) -> Result<Map<String, Value>, OptionError> {
+    let invalid = |err: &dyn ToString| {
+        OptionError::Value(format!("invalid config file {}: {}", path, err.to_string()))
+    };
+
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if !matches!(extension.as_str(), "toml" | "yaml" | "yml" | "json") {
+        return Err(OptionError::Value(format!(
+            "config file must be a .toml, .yaml or .json file, not {}",
+            path
+        )));
+    }
+
+    let content = fs::read_to_string(path)?;
+    let options: Value = match extension.as_str() {
+        "toml" => toml::from_str(&content).map_err(|err| invalid(&err))?,
+        "yaml" | "yml" => serde_yaml::from_str(&content).map_err(|err| invalid(&err))?,
+        _ => serde_json::from_str(&content).map_err(|err| invalid(&err))?,
+    };
+
+    match options {
+        Value::Object(options) => Ok(options),
+        _ => Err(invalid(&"options must be a table")),
+    }
+}
+
+/// Convert keyword argument `key` to the value options are parsed from.
+/// Objects with a `timestamp` method, like `datetime`, are given as it.
+fn from_py(key: &str, value: &PyAny) -> PyResult<Value> {
+    if value.is_none() {
+        return Ok(Value::Null);
+    }
+    // Booleans are integers to python, they're checked first
+    if let Ok(value) = value.downcast::<PyBool>() {
+        return Ok(Value::Bool(value.is_true()));
+    }
+    if value.is_instance::<PyLong>()? {
+        return Ok(match value.extract::<i64>() {
+            Ok(integer) => integer.into(),
+            Err(_) => value.extract::<u64>()?.into(),
+        });
+    }
+    if value.is_instance::<PyFloat>()? {
+        return Number::from_f64(value.extract()?)
+            .map(Value::Number)
+            .ok_or_else(|| {
+                OptionError::Value(format!("{} must be a finite number, not {}", key, value)).into()
+            });
+    }
+    if let Ok(value) = value.downcast::<PyString>() {
+        return Ok(Value::String(value.to_str()?.to_string()));
+    }
+    if value.is_instance::<PyList>()? || value.is_instance::<PyTuple>()? {
+        return value.iter()?.map(|value| from_py(key, value?)).collect();
+    }
+    if let Ok(value) = value.downcast::<PyDict>() {
+        return value
+            .iter()
+            .map(|(entry, value)| Ok((entry.extract::<String>()?, from_py(key, value)?)))
+            .collect::<PyResult<Map<_, _>>>()
+            .map(Value::Object);
+    }
+    if value.hasattr("timestamp")? {
+        return from_py(key, value.call_method0("timestamp")?);
+    }
+
+    Err(OptionError::Type(format!(
+        "{} can't be given as {}",
+        key,
+        value.get_type().name()?
+    ))
+    .into())
+}
+
+/// Options given as keyword arguments, keyed by their name.
+pub fn to_options(kwargs: &PyDict) -> PyResult<Map<String, Value>> {
+    kwargs
+        .iter()
+        .map(|(key, value)| {
+            let key: String = key.extract()?;
+            let value = from_py(&key, value)?;
+            Ok((key, value))
+        })
+        .collect()
+}
@@ -0,0 +1,100 @@
+use image::error::{DecodingError, ImageFormatHint};
+use image::{DynamicImage, ImageError, ImageResult};
+use std::path::Path;
+
+/// Offset of the magic bytes, after the preamble.
+const MAGIC_OFFSET: usize = 128;
+
+fn dicom_error(message: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("DICOM".to_string()),
+        message,
+    ))
+}
+
+/// Check given file for a DICOM file, by its magic bytes following
+/// the preamble, or by its extension for files written without one.
+pub fn is_dicom(name: &str, data: &[u8]) -> bool {
+    data.get(MAGIC_OFFSET..MAGIC_OFFSET + 4) == Some(b"DICM")
+        || Path::new(name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("dcm"))
+}
+
+/// Decode the first frame of given DICOM file, and return it along with
+/// the SOP Instance UID of the file. Grayscale frames are windowed as the
+/// file describes, or stretched between their extreme values when it
+/// doesn't, to 16 bits, or 8 bits when stored on as few.
+#[cfg(feature = "dicom")]
+pub fn load_dicom(data: &[u8]) -> ImageResult<(DynamicImage, Option<String>)> {
+    use dicom_dictionary_std::tags;
+    use dicom_object::OpenFileOptions;
+    use dicom_pixeldata::PixelDecoder;
+    use image::{ImageBuffer, Rgb};
+
+    // The preamble is told apart from files written without one
+    let object = OpenFileOptions::new()
+        .from_reader(data)
+        .map_err(|err| dicom_error(err.to_string()))?;
+    let uid = object
+        .element_opt(tags::SOP_INSTANCE_UID)
+        .ok()
+        .flatten()
+        .and_then(|element| element.to_str().ok())
+        .map(|uid| uid.trim_end_matches(['\0', ' ']).to_string());
+
+    let pixels = object
+        .decode_pixel_data_frame(0)
+        .map_err(|err| dicom_error(err.to_string()))?;
+
+    if pixels.samples_per_pixel() == 3 {
+        let frame = pixels
+            .to_vec_frame::<u8>(0)
+            .map_err(|err| dicom_error(err.to_string()))?;
+        let image = ImageBuffer::<Rgb<u8>, _>::from_raw(pixels.columns(), pixels.rows(), frame)
+            .ok_or_else(|| dicom_error("Pixel data doesn't match image size"))?;
+        return Ok((DynamicImage::ImageRgb8(image), uid));
+    }
+
+    let image = match pixels.bits_stored() > 8 {
+        true => DynamicImage::ImageLuma16(windowed(&pixels)?),
+        false => DynamicImage::ImageLuma8(windowed(&pixels)?),
+    };
+
+    Ok((image, uid))
+}
+
+/// Window the first frame of given grayscale pixel data to `T`.
+#[cfg(feature = "dicom")]
+fn windowed<T>(
+    pixels: &dicom_pixeldata::DecodedPixelData,
+) -> ImageResult<image::ImageBuffer<image::Luma<T>, Vec<T>>>
+where
+    T: image::Primitive + Send + Sync + 'static,
+{
+    use dicom_pixeldata::{ConvertOptions, PhotometricInterpretation, VoiLutOption};
+
+    // Without a window in the file, any will do as long as it spans the values
+    let frame = |voi_lut| {
+        pixels.to_vec_frame_with_options::<T>(0, &ConvertOptions::new().with_voi_lut(voi_lut))
+    };
+    let mut frame = frame(VoiLutOption::First)
+        .or_else(|_| frame(VoiLutOption::Normalize))
+        .map_err(|err| dicom_error(err.to_string()))?;
+
+    // Values of MONOCHROME1 frames get darker as they increase
+    if *pixels.photometric_interpretation() == PhotometricInterpretation::Monochrome1 {
+        let max = T::max_value();
+        frame.iter_mut().for_each(|value| *value = max - *value);
+    }
+
+    image::ImageBuffer::from_raw(pixels.columns(), pixels.rows(), frame)
+        .ok_or_else(|| dicom_error("Pixel data doesn't match image size"))
+}
+
+/// DICOM decoding isn't compiled in.
+#[cfg(not(feature = "dicom"))]
+pub fn load_dicom(_data: &[u8]) -> ImageResult<(DynamicImage, Option<String>)> {
+    Err(dicom_error("DICOM decoding requires the `dicom` feature"))
+}
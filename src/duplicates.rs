@@ -0,0 +1,337 @@
+use crate::config::{BurstOrder, Config, DuplicateMatch, KeepPolicy};
+use crate::hash;
+use crate::hash_index::HashIndex;
+use crate::paths;
+use crate::report::{Reason, Report};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Group of files that are copies of each other or look alike.
+pub struct Cluster {
+    /// Every file has the same content, rather than only looking alike.
+    pub exact: bool,
+    /// Paths of the files, the kept one first.
+    pub paths: Vec<String>,
+}
+
+impl Cluster {
+    /// Convert cluster to a python dictionary.
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+
+        dict.set_item("exact", self.exact)?;
+        let paths: Vec<PyObject> = self
+            .paths
+            .iter()
+            .map(|path| paths::to_py(py, path))
+            .collect();
+        dict.set_item("kept", &paths[0])?;
+        dict.set_item("paths", paths)?;
+
+        Ok(dict)
+    }
+}
+
+/// Representative of the set given element belongs to.
+fn find(parents: &mut [usize], mut element: usize) -> usize {
+    while parents[element] != element {
+        // Halve the path on the way
+        parents[element] = parents[parents[element]];
+        element = parents[element];
+    }
+    element
+}
+
+/// How much a file is worth keeping according to given policy, the higher
+/// the better. Files missing the metric it relies on come last.
+fn preference(report: &Report, policy: KeepPolicy) -> f64 {
+    let metric = |name| report.metrics.get(name).copied();
+    let value = match policy {
+        KeepPolicy::First => Some(0.0),
+        KeepPolicy::Resolution => metric("width").zip(metric("height")).map(|(w, h)| w * h),
+        KeepPolicy::FileSize => metric("file_size"),
+        KeepPolicy::Sharpest => metric("blur"),
+        KeepPolicy::ShortestPath => Some(-(report.path.chars().count() as f64)),
+    };
+
+    value
+        .filter(|value| !value.is_nan())
+        .unwrap_or(f64::NEG_INFINITY)
+}
+
+/// Group reports of files with the same content, the same pixels, or
+/// whose perceptual hashes are within `max_distance` of each other, as
+/// far as `matching` goes, directly or through other files, in any
+/// orientation of theirs that was hashed. Files without hashes, as
+/// undecodable ones, stay alone.
+fn find_clusters(
+    reports: &[&Report],
+    max_distance: u32,
+    matching: DuplicateMatch,
+) -> Vec<Vec<usize>> {
+    let mut parents: Vec<usize> = (0..reports.len()).collect();
+
+    let exact_hashes: [fn(&Report) -> Option<u64>; 2] =
+        [|report| report.content_hash, |report| report.pixel_hash];
+    for exact_hash in exact_hashes {
+        let mut firsts = HashMap::new();
+        for (index, report) in reports.iter().enumerate() {
+            if let Some(hash) = exact_hash(report) {
+                let first = *firsts.entry(hash).or_insert(index);
+                let (a, b) = (find(&mut parents, first), find(&mut parents, index));
+                parents[b] = a;
+            }
+        }
+    }
+
+    if matching != DuplicateMatch::Perceptual {
+        return groups(&mut parents);
+    }
+
+    let hashed: Vec<usize> = (0..reports.len())
+        .filter(|index| reports[*index].hash.is_some())
+        .collect();
+    let index = HashIndex::new(hashed.iter().map(|i| reports[*i].hash.unwrap()).collect());
+    for (id, report) in hashed.iter().enumerate() {
+        let hashes = std::iter::once(reports[*report].hash.unwrap())
+            .chain(reports[*report].oriented_hashes.iter().copied());
+        for (other, _) in hashes.flat_map(|hash| index.query(hash, max_distance)) {
+            // Known files from a hash database don't have orientations, pairs
+            // are only found from one side
+            if other != id {
+                let (a, b) = (
+                    find(&mut parents, *report),
+                    find(&mut parents, hashed[other]),
+                );
+                parents[b] = a;
+            }
+        }
+    }
+
+    groups(&mut parents)
+}
+
+/// Sets of more than one element, given the parent of each element.
+fn groups(parents: &mut [usize]) -> Vec<Vec<usize>> {
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for index in 0..parents.len() {
+        let root = find(parents, index);
+        groups.entry(root).or_default().push(index);
+    }
+
+    groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .collect()
+}
+
+/// Identifier of the file given path points to, shared by its hard links
+/// and symbolic links to it.
+#[cfg(unix)]
+fn file_id(path: &str) -> Option<(u64, u64)> {
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+
+    fs::metadata(paths::decode(path))
+        .ok()
+        .map(|metadata| (metadata.dev(), metadata.ino()))
+}
+
+/// Files can't be told apart without inodes, as on Windows.
+#[cfg(not(unix))]
+fn file_id(_path: &str) -> Option<(u64, u64)> {
+    None
+}
+
+/// Put reports of duplicates in clusters, flagging every file of a cluster
+/// but the kept one, chosen by given policy. Known files, scanned before,
+/// are kept first and aren't flagged, clusters only holding them are left out.
+pub fn flag_duplicates(
+    reports: &mut [Report],
+    known: &[Report],
+    max_distance: u32,
+    matching: DuplicateMatch,
+    keep: KeepPolicy,
+) -> Vec<Cluster> {
+    let all: Vec<&Report> = known.iter().chain(reports.iter()).collect();
+    let mut clusters = find_clusters(&all, max_distance, matching);
+    clusters.retain(|members| members.iter().any(|member| *member >= known.len()));
+
+    let found = rank_clusters(&all, known.len(), &mut clusters, keep);
+    flag_clusters(reports, known.len(), &clusters);
+
+    found
+}
+
+/// Check if given frames, following each other, belong to the same burst:
+/// they're in the same folder, look alike and, ordered by time, were
+/// taken shortly one after the other.
+fn same_burst(previous: &Report, next: &Report, config: &Config) -> bool {
+    let parent = |report: &Report| {
+        paths::decode(&report.path)
+            .parent()
+            .map(|p| p.to_path_buf())
+    };
+    let alike = match (previous.hash, next.hash) {
+        (Some(a), Some(b)) => hash::distance(a, b) <= config.burst_distance,
+        _ => false,
+    };
+    let close = match config.burst_order {
+        BurstOrder::Name => true,
+        BurstOrder::Time => match (previous.metrics.get("taken"), next.metrics.get("taken")) {
+            (Some(a), Some(b)) => b - a <= config.max_burst_gap,
+            _ => false,
+        },
+    };
+
+    alike && close && parent(previous) == parent(next)
+}
+
+/// Group images following each other and looking alike in bursts, in the
+/// order configuration asks, flagging every frame of a burst but the
+/// sharpest. Frames tell their burst in the `burst` metric. Files already
+/// flagged are left out.
+pub fn flag_bursts(reports: &mut [Report], config: &Config) {
+    let mut frames: Vec<usize> = (0..reports.len())
+        .filter(|index| !reports[*index].is_flagged() && reports[*index].hash.is_some())
+        .collect();
+    frames.sort_by(|a, b| {
+        let (first, second) = (&reports[*a], &reports[*b]);
+        let time = match config.burst_order {
+            BurstOrder::Name => Ordering::Equal,
+            BurstOrder::Time => {
+                let taken = |report: &Report| report.metrics.get("taken").copied();
+                // Frames without a date come last, and don't form bursts
+                match (taken(first), taken(second)) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+                    (a, b) => b.is_some().cmp(&a.is_some()),
+                }
+            }
+        };
+        time.then_with(|| first.path.cmp(&second.path))
+    });
+
+    let mut bursts: Vec<Vec<usize>> = Vec::new();
+    for (previous, next) in frames.iter().zip(frames.iter().skip(1)) {
+        if !same_burst(&reports[*previous], &reports[*next], config) {
+            continue;
+        }
+
+        match bursts.last_mut() {
+            Some(burst) if burst.last() == Some(previous) => burst.push(*next),
+            _ => bursts.push(vec![*previous, *next]),
+        }
+    }
+
+    for (id, burst) in bursts.iter().enumerate() {
+        let sharpest = burst
+            .iter()
+            .copied()
+            .reduce(|best, frame| {
+                match preference(&reports[frame], KeepPolicy::Sharpest)
+                    > preference(&reports[best], KeepPolicy::Sharpest)
+                {
+                    true => frame,
+                    false => best,
+                }
+            })
+            .unwrap();
+
+        for frame in burst {
+            let report = &mut reports[*frame];
+            report.metric("burst", id as f64);
+            if *frame != sharpest {
+                report.flag(Reason::Burst);
+            }
+        }
+    }
+}
+
+/// Put reports of files sharing an inode in clusters, as exact duplicates,
+/// flagging every file of a cluster but the kept one.
+pub fn flag_hardlinks(reports: &mut [Report], keep: KeepPolicy) -> Vec<Cluster> {
+    let mut files: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (index, report) in reports.iter().enumerate() {
+        if let Some(id) = file_id(&report.path) {
+            files.entry(id).or_default().push(index);
+        }
+    }
+
+    let all: Vec<&Report> = reports.iter().collect();
+    let mut clusters: Vec<Vec<usize>> = files
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .collect();
+
+    let found = rank_clusters(&all, 0, &mut clusters, keep)
+        .into_iter()
+        .map(|cluster| Cluster {
+            exact: true,
+            ..cluster
+        })
+        .collect();
+    flag_clusters(reports, 0, &clusters);
+
+    found
+}
+
+/// Sort members of clusters, the kept one first, and clusters by path of
+/// their kept file. The first `known` reports were scanned before.
+fn rank_clusters(
+    all: &[&Report],
+    known: usize,
+    clusters: &mut [Vec<usize>],
+    keep: KeepPolicy,
+) -> Vec<Cluster> {
+    // Keep a known file, or one that passed other checks, the preferred one
+    // then the first by path
+    for members in clusters.iter_mut() {
+        members.sort_by(|a, b| {
+            let (first, second) = (all[*a], all[*b]);
+            (*a >= known, first.is_flagged())
+                .cmp(&(*b >= known, second.is_flagged()))
+                .then_with(|| {
+                    preference(second, keep)
+                        .partial_cmp(&preference(first, keep))
+                        .unwrap_or(Ordering::Equal)
+                })
+                .then_with(|| first.path.cmp(&second.path))
+        });
+    }
+    clusters.sort_by_key(|members| &all[members[0]].path);
+
+    clusters
+        .iter()
+        .map(|members| {
+            let content_hash = all[members[0]].content_hash;
+            Cluster {
+                exact: members
+                    .iter()
+                    .all(|member| all[*member].content_hash == content_hash),
+                paths: members
+                    .iter()
+                    .map(|member| all[*member].path.clone())
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Tell reports their cluster, flagging all but the first of each.
+fn flag_clusters(reports: &mut [Report], known: usize, clusters: &[Vec<usize>]) {
+    for (id, members) in clusters.iter().enumerate() {
+        for (rank, member) in members.iter().enumerate() {
+            if *member < known {
+                continue;
+            }
+
+            let report = &mut reports[*member - known];
+            report.cluster = Some(id);
+            if rank > 0 {
+                report.flag(Reason::Duplicate);
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use std::convert::TryInto;
+
+/// TIFF tags read from EXIF metadata.
+const MAKE: u16 = 0x010f;
+const MODEL: u16 = 0x0110;
+const ORIENTATION: u16 = 0x0112;
+const DATE_TIME: u16 = 0x0132;
+const EXIF_IFD: u16 = 0x8769;
+const GPS_IFD: u16 = 0x8825;
+const DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+/// TIFF types of text and 16 bits values.
+const ASCII: u16 = 2;
+const SHORT: u16 = 3;
+
+/// EXIF fields files can be filtered on, all empty for files without EXIF.
+#[derive(Debug, Default)]
+pub struct Exif {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// When the picture was taken, as `parse_date` gives it.
+    pub taken: Option<String>,
+    /// Whether GPS coordinates are recorded.
+    pub gps: bool,
+    /// How pixels must be turned to be displayed upright,
+    /// from 1 (as they are) to 8.
+    pub orientation: Option<u16>,
+    /// Whether an EXIF block was found but isn't a valid TIFF one,
+    /// or its first directory is cut short.
+    pub broken: bool,
+}
+
+impl Exif {
+    /// Read EXIF metadata of given file. It's looked for in JPEG, PNG and
+    /// WebP files, and TIFF based ones such as most camera RAW files.
+    pub fn read(data: &[u8]) -> Self {
+        let tiff = if data.starts_with(&[0xff, 0xd8]) {
+            jpeg_exif(data)
+        } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            png_exif(data)
+        } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+            webp_exif(data)
+        } else if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+            Some(data)
+        } else {
+            None
+        };
+
+        match tiff {
+            Some(tiff) => Tiff::new(tiff.strip_prefix(b"Exif\0\0").unwrap_or(tiff)).exif(),
+            None => Exif::default(),
+        }
+    }
+
+    /// Check if a camera is named, as pictures do and screenshots
+    /// or rendered images don't.
+    pub fn has_camera(&self) -> bool {
+        self.make.is_some() || self.model.is_some()
+    }
+
+    /// Make and model of the camera, as a single name.
+    pub fn camera(&self) -> String {
+        let names: Vec<&str> = self
+            .make
+            .iter()
+            .chain(self.model.iter())
+            .map(String::as_str)
+            .collect();
+        names.join(" ")
+    }
+}
+
+/// Parse given date, "YYYY-MM-DD" optionally followed by "HH:MM:SS", into
+/// the form EXIF dates take, which orders as it compares: "YYYY:MM:DD
+/// HH:MM:SS". EXIF dates parse too, unset ones ("0000:00:00 00:00:00") don't.
+pub fn parse_date(date: &str) -> Option<String> {
+    let (day, time) = match date.trim().split_once(' ') {
+        Some((day, time)) => (day, time.trim()),
+        None => (date.trim(), "00:00:00"),
+    };
+
+    let day: Vec<&str> = day.split(['-', ':']).collect();
+    let time: Vec<&str> = time.split(':').collect();
+    let digits = |field: &str, width: usize| {
+        field.len() == width && field.bytes().all(|byte| byte.is_ascii_digit())
+    };
+    let valid_day = matches!(day.as_slice(), [year, month, day]
+        if digits(year, 4) && digits(month, 2) && digits(day, 2) && *month != "00" && *day != "00");
+    let valid_time = matches!(time.as_slice(), [hours, minutes, seconds]
+        if digits(hours, 2) && digits(minutes, 2) && digits(seconds, 2));
+
+    match valid_day && valid_time {
+        true => Some(format!("{} {}", day.join(":"), time.join(":"))),
+        false => None,
+    }
+}
+
+/// Seconds between the Unix epoch and given date, as `parse_date` gives it.
+/// Dates are taken as UTC, EXIF doesn't tell their time zone.
+pub fn timestamp(date: &str) -> f64 {
+    let field = |at: usize, width: usize| date[at..at + width].parse::<i64>().unwrap_or(0);
+    let (year, month, day) = (field(0, 4), field(5, 2), field(8, 2));
+    let seconds = field(11, 2) * 3600 + field(14, 2) * 60 + field(17, 2);
+
+    // Days since the epoch, years starting in March so that leap days come last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    (days * 86400 + seconds) as f64
+}
+/// EXIF block of a JPEG file, held by an APP1 segment before image data.
+fn jpeg_exif(data: &[u8]) -> Option<&[u8]> {
+    let mut at = 2;
+    loop {
+        let marker = *data.get(at + 1)?;
+        let length = u16::from_be_bytes([*data.get(at + 2)?, *data.get(at + 3)?]) as usize;
+        let segment = data.get(at + 4..at + 2 + length)?;
+
+        match marker {
+            0xe1 if segment.starts_with(b"Exif\0\0") => return Some(segment),
+            // Start of scan, metadata is over
+            0xda => return None,
+            _ => at += 2 + length,
+        }
+    }
+}
+
+/// EXIF block of a PNG file, held by an eXIf chunk.
+fn png_exif(data: &[u8]) -> Option<&[u8]> {
+    let mut at = 8;
+    loop {
+        let length = u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?) as usize;
+        let kind = data.get(at + 4..at + 8)?;
+        let chunk = data.get(at + 8..at + 8 + length)?;
+
+        match kind {
+            b"eXIf" => return Some(chunk),
+            b"IDAT" | b"IEND" => return None,
+            _ => at += 12 + length,
+        }
+    }
+}
+
+/// EXIF block of a WebP file, held by an EXIF chunk.
+fn webp_exif(data: &[u8]) -> Option<&[u8]> {
+    let mut at = 12;
+    loop {
+        let kind = data.get(at..at + 4)?;
+        let length = u32::from_le_bytes(data.get(at + 4..at + 8)?.try_into().ok()?) as usize;
+        let chunk = data.get(at + 8..at + 8 + length)?;
+
+        match kind {
+            b"EXIF" => return Some(chunk),
+            // Chunks are padded to an even size
+            _ => at += 8 + length + length % 2,
+        }
+    }
+}
+
+/// TIFF block EXIF metadata is stored as, offsets being relative to its start.
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Tiff {
+            data,
+            big_endian: data.starts_with(b"MM"),
+        }
+    }
+
+    fn u16_at(&self, at: usize) -> Option<u16> {
+        let bytes = [*self.data.get(at)?, *self.data.get(at + 1)?];
+        Some(match self.big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        })
+    }
+
+    fn u32_at(&self, at: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(at..at + 4)?.try_into().ok()?;
+        Some(match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    }
+
+    /// Tags of the image file directory at given offset, along with the
+    /// offset of their entry.
+    fn entries(&self, directory: usize) -> Vec<(u16, usize)> {
+        let count = self.u16_at(directory).unwrap_or(0) as usize;
+
+        (0..count)
+            .map(|index| directory + 2 + index * 12)
+            .map_while(|entry| Some((self.u16_at(entry)?, entry)))
+            .collect()
+    }
+
+    /// Check if the image file directory at given offset is whole.
+    fn is_whole(&self, directory: usize) -> bool {
+        let valid = self.data.starts_with(b"II*\0") || self.data.starts_with(b"MM\0*");
+        match self.u16_at(directory) {
+            Some(count) => valid && self.data.len() >= directory + 2 + count as usize * 12,
+            None => false,
+        }
+    }
+
+    /// Text held by given entry, trimmed. None if there's none.
+    fn text(&self, entry: usize) -> Option<String> {
+        if self.u16_at(entry + 2)? != ASCII {
+            return None;
+        }
+
+        // Values of 4 bytes or less are held by the entry itself
+        let length = self.u32_at(entry + 4)? as usize;
+        let at = match length <= 4 {
+            true => entry + 8,
+            false => self.u32_at(entry + 8)? as usize,
+        };
+        let text = String::from_utf8_lossy(self.data.get(at..at + length)?);
+        let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+
+        match text.is_empty() {
+            true => None,
+            false => Some(text.to_string()),
+        }
+    }
+
+    /// 16 bits value held by given entry.
+    fn short(&self, entry: usize) -> Option<u16> {
+        match self.u16_at(entry + 2)? {
+            SHORT => self.u16_at(entry + 8),
+            _ => None,
+        }
+    }
+
+    fn exif(&self) -> Exif {
+        let mut exif = Exif::default();
+        let mut date_time = None;
+
+        let ifd0 = self.u32_at(4).unwrap_or(0) as usize;
+        exif.broken = !self.is_whole(ifd0);
+        for (tag, entry) in self.entries(ifd0) {
+            match tag {
+                MAKE => exif.make = self.text(entry),
+                MODEL => exif.model = self.text(entry),
+                ORIENTATION => exif.orientation = self.short(entry),
+                DATE_TIME => date_time = self.text(entry),
+                GPS_IFD => exif.gps = true,
+                EXIF_IFD => {
+                    let directory = self.u32_at(entry + 8).unwrap_or(0) as usize;
+                    for (tag, entry) in self.entries(directory) {
+                        if tag == DATE_TIME_ORIGINAL {
+                            exif.taken = self.text(entry);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // When the file was last changed stands in for when it was taken
+        exif.taken = exif
+            .taken
+            .or(date_time)
+            .and_then(|taken| parse_date(&taken));
+
+        exif
+    }
+}
@@ -0,0 +1,114 @@
+use crate::config::Config;
+use crate::scanner::Scanner;
+use crate::{paths, run_scanner};
+use serde_json::{json, Map, Value};
+use std::any::Any;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+/// Parse options given as a JSON object keyed like keyword arguments
+/// of `clean`, null being no option at all. They're checked by the
+/// parser keyword arguments and config files go through, without
+/// the interpreter.
+unsafe fn parse_config(options: *const c_char) -> Result<Config, String> {
+    let options = match options.is_null() {
+        true => Map::new(),
+        false => match serde_json::from_slice(CStr::from_ptr(options).to_bytes()) {
+            Ok(Value::Object(options)) => options,
+            Ok(_) => return Err("invalid options: expected a JSON object".to_string()),
+            Err(err) => return Err(format!("invalid options: {}", err)),
+        },
+    };
+
+    Config::from_options(options).map_err(|err| err.to_string())
+}
+
+/// Message a panic was raised with.
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => "unknown panic".to_string(),
+        },
+    }
+}
+
+/// Hand the result of `run` over to C, as a JSON string to release with
+/// `cleanax_free`. Failures are given as `{"error": "..."}`, panics too
+/// as they can't unwind into C.
+fn to_c(run: impl FnOnce() -> Result<Value, String>) -> *mut c_char {
+    let result = panic::catch_unwind(AssertUnwindSafe(run))
+        .unwrap_or_else(|panic| Err(format!("panicked: {}", panic_message(panic))));
+    let json = match result {
+        Ok(value) => value,
+        Err(err) => json!({ "error": err }),
+    };
+
+    // JSON escapes control characters, NUL included
+    CString::new(json.to_string()).unwrap().into_raw()
+}
+
+/// Check every file of root folder, returning `{"reports": [...]}`.
+///
+/// # Safety
+/// `root_folder` must be a NUL terminated string, `options` too or null.
+#[no_mangle]
+pub unsafe extern "C" fn cleanax_scan(
+    root_folder: *const c_char,
+    options: *const c_char,
+) -> *mut c_char {
+    to_c(|| {
+        let root_folder = paths::from_bytes(CStr::from_ptr(root_folder).to_bytes());
+        let config = parse_config(options)?;
+        let scanner = Scanner::new(config.clone()).map_err(|err| err.to_string())?;
+        let (reports, _, _) = run_scanner(scanner, &config, |scanner| {
+            scanner.scan(std::slice::from_ref(&root_folder))
+        })
+        .map_err(|err| err.to_string())?;
+
+        let reports: Vec<Value> = reports.iter().map(|report| report.to_json()).collect();
+        Ok(json!({ "reports": reports }))
+    })
+}
+
+/// Check an image held in memory, returning `{"report": {...}}`.
+/// Name is reported as its path, its extension may help decoding.
+///
+/// # Safety
+/// `name` must be a NUL terminated string, `options` too or null,
+/// and `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cleanax_check(
+    name: *const c_char,
+    data: *const u8,
+    len: usize,
+    options: *const c_char,
+) -> *mut c_char {
+    to_c(|| {
+        let name = paths::from_bytes(CStr::from_ptr(name).to_bytes());
+        let data = match data.is_null() {
+            true => &[],
+            false => slice::from_raw_parts(data, len),
+        };
+        let config = parse_config(options)?;
+        let scanner = Scanner::new(config.clone()).map_err(|err| err.to_string())?;
+        let mut report = scanner.check_bytes(&name, data);
+        config.settle(&mut report);
+
+        Ok(json!({ "report": report.to_json() }))
+    })
+}
+
+/// Release a result returned by `cleanax_scan` or `cleanax_check`.
+///
+/// # Safety
+/// `result` must come from one of them, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cleanax_free(result: *mut c_char) {
+    if !result.is_null() {
+        drop(CString::from_raw(result));
+    }
+}
@@ -0,0 +1,565 @@
+use crate::stats::Pixel;
+use image::DynamicImage;
+use std::io;
+
+/// Statistics of an image computed on the GPU.
+pub struct GpuStatistics {
+    /// Mean and variance of each channel, as `get_image_statistics` computes them.
+    pub moments: (Pixel, Pixel),
+    /// Variance of the laplacian of luminance, if asked.
+    pub blur: Option<f64>,
+    /// Entropy of the luminance histogram, if asked.
+    pub entropy: Option<f64>,
+}
+
+/// Pixels covered by a workgroup, each one writing a partial sum.
+#[cfg(feature = "gpu")]
+const GROUP_PIXELS: u32 = 256 * 64;
+
+/// Images sent to the GPU at once, when that many are waiting.
+#[cfg(feature = "gpu")]
+const BATCH_SIZE: usize = 32;
+
+/// Pixels uploaded at once, within the default storage binding size.
+/// Larger images are left to the CPU.
+#[cfg(feature = "gpu")]
+const BATCH_PIXELS: usize = 32 * 1024 * 1024;
+
+/// Image waiting for its statistics.
+#[cfg(feature = "gpu")]
+struct Job {
+    /// RGBA pixels packed in a u32 each, alpha being 0 for RGB images.
+    pixels: Vec<u32>,
+    width: u32,
+    blur: bool,
+    entropy: bool,
+    result: std::sync::mpsc::SyncSender<GpuStatistics>,
+}
+
+/// Pixel statistics computed on the GPU. Images sent by scanning threads
+/// are gathered in batches by a thread owning the device, uploaded
+/// together and run through the kernels of `gpu.wgsl` in a single submission.
+#[cfg(feature = "gpu")]
+pub struct Gpu {
+    jobs: std::sync::mpsc::Sender<Job>,
+}
+
+#[cfg(feature = "gpu")]
+impl Gpu {
+    /// Open the most powerful GPU around.
+    pub fn new() -> io::Result<Self> {
+        let device = Device::open()?;
+
+        let (jobs, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || device.serve(receiver));
+
+        Ok(Gpu { jobs })
+    }
+
+    /// Compute statistics of given 8 bits RGB or RGBA image, and its blur
+    /// score and entropy if asked. Return nothing for other images, too
+    /// large ones included, which are left to the CPU.
+    pub fn statistics(
+        &self,
+        image: &DynamicImage,
+        blur: bool,
+        entropy: bool,
+    ) -> Option<GpuStatistics> {
+        let (width, pixels): (u32, Vec<u32>) = match image {
+            DynamicImage::ImageRgb8(pixels) => (
+                pixels.width(),
+                pixels
+                    .as_raw()
+                    .chunks_exact(3)
+                    .map(|p| u32::from_le_bytes([p[0], p[1], p[2], 0]))
+                    .collect(),
+            ),
+            DynamicImage::ImageRgba8(pixels) => (
+                pixels.width(),
+                pixels
+                    .as_raw()
+                    .chunks_exact(4)
+                    .map(|p| u32::from_le_bytes([p[0], p[1], p[2], p[3]]))
+                    .collect(),
+            ),
+            _ => return None,
+        };
+
+        if pixels.is_empty() || pixels.len() > BATCH_PIXELS {
+            return None;
+        }
+
+        let (result, receiver) = std::sync::mpsc::sync_channel(1);
+        let job = Job {
+            pixels,
+            width,
+            blur,
+            entropy,
+            result,
+        };
+        self.jobs.send(job).ok()?;
+
+        receiver.recv().ok()
+    }
+}
+
+/// Where an image lies in the buffers of a batch.
+#[cfg(feature = "gpu")]
+struct Placement {
+    offset: u32,
+    count: u32,
+    width: u32,
+    /// First workgroup of the image.
+    output: u32,
+    groups: u32,
+    /// Index of the image in the batch, and of its histogram.
+    index: u32,
+}
+
+#[cfg(feature = "gpu")]
+impl Placement {
+    /// Uniform parameters of the kernels, see `Params` in `gpu.wgsl`,
+    /// padded to 16 bytes.
+    fn params(&self, mean: [u32; 4]) -> Vec<u8> {
+        [self.offset, self.count, self.width, self.output]
+            .iter()
+            .chain(mean.iter())
+            .chain([self.index, 0, 0, 0].iter())
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+}
+
+#[cfg(feature = "gpu")]
+struct Device {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    channel_sums: wgpu::ComputePipeline,
+    deviations: wgpu::ComputePipeline,
+    laplacian: wgpu::ComputePipeline,
+    histogram: wgpu::ComputePipeline,
+}
+
+#[cfg(feature = "gpu")]
+impl Device {
+    fn open() -> io::Result<Self> {
+        let instance =
+            wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .map_err(|err| io::Error::new(io::ErrorKind::NotFound, err))?;
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))
+                .map_err(io::Error::other)?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("cleanax"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                storage(0, true),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(2, false),
+                storage(3, false),
+                storage(4, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[Some(&layout)],
+            ..Default::default()
+        });
+
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        Ok(Device {
+            channel_sums: pipeline("channel_sums"),
+            deviations: pipeline("deviations"),
+            laplacian: pipeline("laplacian"),
+            histogram: pipeline("histogram"),
+            device,
+            queue,
+            layout,
+        })
+    }
+
+    /// Run jobs until every scanner using the GPU is gone. Jobs waiting
+    /// together are gathered, up to a batch worth of pixels.
+    fn serve(self, jobs: std::sync::mpsc::Receiver<Job>) {
+        let mut next = None;
+
+        while let Some(first) = next.take().or_else(|| jobs.recv().ok()) {
+            let mut pixels = first.pixels.len();
+            let mut batch = vec![first];
+
+            while batch.len() < BATCH_SIZE {
+                match jobs.try_recv() {
+                    Ok(job) if pixels + job.pixels.len() <= BATCH_PIXELS => {
+                        pixels += job.pixels.len();
+                        batch.push(job);
+                    }
+                    Ok(job) => {
+                        next = Some(job);
+                        break;
+                    }
+                    Err(_) => break,
+                }
+            }
+
+            // Dropping jobs lets scanning threads fall back to the CPU
+            let results = self.run(&batch).unwrap_or_default();
+            for (job, statistics) in batch.iter().zip(results) {
+                // The scanning thread may be gone
+                job.result.send(statistics).ok();
+            }
+        }
+    }
+
+    /// Compute statistics of a batch of images. Means are needed for
+    /// variances, so pixels go through a first pass summing channels
+    /// (along with the laplacian and the luminance histogram), then a
+    /// second one summing deviations.
+    /// Return nothing if the GPU failed, leaving the batch to the CPU.
+    fn run(&self, batch: &[Job]) -> Option<Vec<GpuStatistics>> {
+        use wgpu::util::DeviceExt;
+
+        let mut placements = Vec::new();
+        let (mut offset, mut output) = (0, 0);
+        for (index, job) in batch.iter().enumerate() {
+            let count = job.pixels.len() as u32;
+            let groups = count.div_ceil(GROUP_PIXELS);
+            placements.push(Placement {
+                offset,
+                count,
+                width: job.width,
+                output,
+                groups,
+                index: index as u32,
+            });
+            offset += count;
+            output += groups;
+        }
+
+        let pixels: Vec<u8> = batch
+            .iter()
+            .flat_map(|job| &job.pixels)
+            .flat_map(|pixel| pixel.to_le_bytes())
+            .collect();
+        let pixels = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("pixels"),
+                contents: &pixels,
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+        let output_buffer = |label, size| {
+            self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        };
+        let sums = output_buffer("sums", output as u64 * 16);
+        let laplacians = output_buffer("laplacians", output as u64 * 8);
+        // Buffers start zeroed, histograms are counted into right away
+        let histograms = output_buffer("histograms", batch.len() as u64 * 256 * 4);
+        let buffers = [&pixels, &sums, &laplacians, &histograms];
+
+        // Channel sums, laplacian and histogram
+        let zeros = vec![[0; 4]; batch.len()];
+        self.dispatch(&placements, &zeros, buffers, |index| {
+            let mut pipelines = vec![&self.channel_sums];
+            if batch[index].blur {
+                pipelines.push(&self.laplacian);
+            }
+            if batch[index].entropy {
+                pipelines.push(&self.histogram);
+            }
+            pipelines
+        });
+        let channel_sums = self.read_u32(&sums)?;
+        let laplacian_sums = self.read_f32(&laplacians)?;
+        let histogram_counts = self.read_u32(&histograms)?;
+
+        let totals: Vec<[u64; 4]> = placements
+            .iter()
+            .map(|placement| group_sums(&channel_sums, placement))
+            .collect();
+        let means: Vec<[u32; 4]> = totals
+            .iter()
+            .zip(&placements)
+            .map(|(total, placement)| total.map(|sum| (sum / placement.count as u64) as u32))
+            .collect();
+
+        // Squared deviations from means
+        self.dispatch(&placements, &means, buffers, |_| vec![&self.deviations]);
+        let deviation_sums = self.read_u32(&sums)?;
+
+        let statistics = placements
+            .iter()
+            .zip(batch)
+            .zip(totals.iter().zip(&means))
+            .map(|((placement, job), (total, mean))| {
+                let count = placement.count as f64;
+                let deviations = group_sums(&deviation_sums, placement);
+
+                // Deviations are taken from the truncated mean, which adds
+                // the square of the difference to the variance
+                let mut means = [0.0; 4];
+                let mut variances = [0.0; 4];
+                for channel in 0..4 {
+                    means[channel] = total[channel] as f64 / count;
+                    let offset = means[channel] - mean[channel] as f64;
+                    variances[channel] = deviations[channel] as f64 / count - offset * offset;
+                }
+                let moments = (
+                    Pixel::from_channels(&means),
+                    Pixel::from_channels(&variances),
+                );
+
+                let blur = match job.blur {
+                    true => Some(laplacian_variance(&laplacian_sums, placement)),
+                    false => None,
+                };
+                let entropy = match job.entropy {
+                    true => Some(luma_entropy(&histogram_counts, placement)),
+                    false => None,
+                };
+
+                GpuStatistics {
+                    moments,
+                    blur,
+                    entropy,
+                }
+            })
+            .collect();
+
+        Some(statistics)
+    }
+
+    /// Run pipelines given by `pipelines` over every placed image,
+    /// in a single submission. Deviations are taken from given means.
+    /// Buffers are bound in order, pixels first, as `gpu.wgsl` declares them.
+    fn dispatch<'a>(
+        &'a self,
+        placements: &[Placement],
+        means: &[[u32; 4]],
+        buffers: [&wgpu::Buffer; 4],
+        pipelines: impl Fn(usize) -> Vec<&'a wgpu::ComputePipeline>,
+    ) {
+        let [pixels, sums, laplacians, histograms] = buffers;
+        use wgpu::util::DeviceExt;
+
+        let bind_groups: Vec<wgpu::BindGroup> = placements
+            .iter()
+            .zip(means)
+            .map(|(placement, mean)| {
+                let params = self
+                    .device
+                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("params"),
+                        contents: &placement.params(*mean),
+                        usage: wgpu::BufferUsages::UNIFORM,
+                    });
+
+                self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout: &self.layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: pixels.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: params.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: sums.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: laplacians.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: histograms.as_entire_binding(),
+                        },
+                    ],
+                })
+            })
+            .collect();
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            for (index, (placement, bind_group)) in placements.iter().zip(&bind_groups).enumerate()
+            {
+                for pipeline in pipelines(index) {
+                    pass.set_pipeline(pipeline);
+                    pass.set_bind_group(0, bind_group, &[]);
+                    pass.dispatch_workgroups(placement.groups, 1, 1);
+                }
+            }
+        }
+        self.queue.submit([encoder.finish()]);
+    }
+
+    /// Copy given buffer back from the GPU.
+    fn read(&self, buffer: &wgpu::Buffer) -> Option<Vec<u8>> {
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size: buffer.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, buffer.size());
+        self.queue.submit([encoder.finish()]);
+
+        let (mapped, receiver) = std::sync::mpsc::channel();
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            mapped.send(result).ok();
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely()).ok()?;
+        receiver.recv().ok()?.ok()?;
+
+        let data = slice.get_mapped_range().ok()?.to_vec();
+        Some(data)
+    }
+
+    fn read_u32(&self, buffer: &wgpu::Buffer) -> Option<Vec<u32>> {
+        let words = self
+            .read(buffer)?
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Some(words)
+    }
+
+    fn read_f32(&self, buffer: &wgpu::Buffer) -> Option<Vec<f32>> {
+        let words = self
+            .read(buffer)?
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Some(words)
+    }
+}
+
+/// Total of the partial sums of each channel written by workgroups of an image.
+#[cfg(feature = "gpu")]
+fn group_sums(partials: &[u32], placement: &Placement) -> [u64; 4] {
+    let start = placement.output as usize * 4;
+    let end = start + placement.groups as usize * 4;
+
+    partials[start..end]
+        .chunks_exact(4)
+        .fold([0u64; 4], |mut total, sums| {
+            for (total, sum) in total.iter_mut().zip(sums) {
+                *total += *sum as u64;
+            }
+            total
+        })
+}
+
+/// Variance of the laplacian from the partial sums written by workgroups
+/// of an image, as `blur_score` computes it.
+#[cfg(feature = "gpu")]
+fn laplacian_variance(partials: &[f32], placement: &Placement) -> f64 {
+    let (width, height) = (placement.width, placement.count / placement.width);
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let start = placement.output as usize * 2;
+    let end = start + placement.groups as usize * 2;
+    let (sum, squares) = partials[start..end]
+        .chunks_exact(2)
+        .fold((0f64, 0f64), |(sum, squares), partial| {
+            (sum + partial[0] as f64, squares + partial[1] as f64)
+        });
+
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+
+    squares / count - mean * mean
+}
+
+/// Entropy of the luminance histogram counted for an image, as
+/// `entropy_score` computes it.
+#[cfg(feature = "gpu")]
+fn luma_entropy(histograms: &[u32], placement: &Placement) -> f64 {
+    let start = placement.index as usize * 256;
+    let mut histogram = [0u64; 256];
+    for (count, counted) in histogram.iter_mut().zip(&histograms[start..start + 256]) {
+        *count = *counted as u64;
+    }
+
+    crate::stats::histogram_entropy(&histogram)
+}
+
+/// GPU support isn't compiled in, tell so when it's asked for.
+#[cfg(not(feature = "gpu"))]
+pub struct Gpu;
+
+#[cfg(not(feature = "gpu"))]
+impl Gpu {
+    pub fn new() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "can't use the GPU, cleanax was built without the gpu feature",
+        ))
+    }
+
+    pub fn statistics(
+        &self,
+        _image: &DynamicImage,
+        _blur: bool,
+        _entropy: bool,
+    ) -> Option<GpuStatistics> {
+        None
+    }
+}
@@ -0,0 +1,138 @@
+// Per workgroup partial sums of pixel statistics, reduced on the CPU.
+// Pixels are RGBA8 packed in a u32, a workgroup covers GROUP_PIXELS of them.
+
+struct Params {
+    // First pixel of the image in the batch
+    offset: u32,
+    count: u32,
+    width: u32,
+    // First workgroup of the image in the partial sums
+    output: u32,
+    mean: vec4<u32>,
+    // Index of the image in the batch, and of its histogram
+    index: u32,
+}
+
+const WORKGROUP_SIZE: u32 = 256u;
+const PIXELS_PER_INVOCATION: u32 = 64u;
+
+@group(0) @binding(0) var<storage, read> pixels: array<u32>;
+@group(0) @binding(1) var<uniform> params: Params;
+@group(0) @binding(2) var<storage, read_write> sums: array<vec4<u32>>;
+@group(0) @binding(3) var<storage, read_write> laplacians: array<vec2<f32>>;
+@group(0) @binding(4) var<storage, read_write> histograms: array<atomic<u32>>;
+
+var<workgroup> partial_sums: array<vec4<u32>, WORKGROUP_SIZE>;
+var<workgroup> partial_laplacians: array<vec2<f32>, WORKGROUP_SIZE>;
+var<workgroup> partial_histogram: array<atomic<u32>, 256>;
+
+fn channels(pixel: u32) -> vec4<u32> {
+    return vec4<u32>(pixel & 0xffu, (pixel >> 8u) & 0xffu, (pixel >> 16u) & 0xffu, pixel >> 24u);
+}
+
+// Same as the image crate, truncating a weighted sum of the channels
+fn luma(x: u32, y: u32) -> f32 {
+    let value = vec3<f32>(channels(pixels[params.offset + y * params.width + x]).rgb);
+    return floor(dot(value, vec3<f32>(0.2126, 0.7152, 0.0722)));
+}
+
+// Index of the n-th pixel visited by an invocation, consecutive
+// invocations reading consecutive pixels
+fn pixel_index(group: u32, local: u32, n: u32) -> u32 {
+    return (group * PIXELS_PER_INVOCATION + n) * WORKGROUP_SIZE + local;
+}
+
+fn reduce_sums(group: u32, local: u32, value: vec4<u32>) {
+    partial_sums[local] = value;
+    workgroupBarrier();
+
+    for (var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride = stride / 2u) {
+        if local < stride {
+            partial_sums[local] += partial_sums[local + stride];
+        }
+        workgroupBarrier();
+    }
+
+    if local == 0u {
+        sums[params.output + group] = partial_sums[0];
+    }
+}
+
+@compute @workgroup_size(256)
+fn channel_sums(@builtin(workgroup_id) group: vec3<u32>, @builtin(local_invocation_index) local: u32) {
+    var sum = vec4<u32>(0u);
+    for (var n = 0u; n < PIXELS_PER_INVOCATION; n++) {
+        let index = pixel_index(group.x, local, n);
+        if index < params.count {
+            sum += channels(pixels[params.offset + index]);
+        }
+    }
+
+    reduce_sums(group.x, local, sum);
+}
+
+// Squared deviations from the truncated mean
+@compute @workgroup_size(256)
+fn deviations(@builtin(workgroup_id) group: vec3<u32>, @builtin(local_invocation_index) local: u32) {
+    var sum = vec4<u32>(0u);
+    for (var n = 0u; n < PIXELS_PER_INVOCATION; n++) {
+        let index = pixel_index(group.x, local, n);
+        if index < params.count {
+            let value = channels(pixels[params.offset + index]);
+            let deviation = max(value, params.mean) - min(value, params.mean);
+            sum += deviation * deviation;
+        }
+    }
+
+    reduce_sums(group.x, local, sum);
+}
+
+// Sum and sum of squares of the laplacian of luminance, border excluded
+@compute @workgroup_size(256)
+fn laplacian(@builtin(workgroup_id) group: vec3<u32>, @builtin(local_invocation_index) local: u32) {
+    let height = params.count / params.width;
+
+    var sum = vec2<f32>(0.0);
+    for (var n = 0u; n < PIXELS_PER_INVOCATION; n++) {
+        let index = pixel_index(group.x, local, n);
+        let x = index % params.width;
+        let y = index / params.width;
+        if index < params.count && x > 0u && y > 0u && x < params.width - 1u && y < height - 1u {
+            let value = luma(x - 1u, y) + luma(x + 1u, y) + luma(x, y - 1u) + luma(x, y + 1u) - 4.0 * luma(x, y);
+            sum += vec2<f32>(value, value * value);
+        }
+    }
+
+    partial_laplacians[local] = sum;
+    workgroupBarrier();
+
+    for (var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride = stride / 2u) {
+        if local < stride {
+            partial_laplacians[local] += partial_laplacians[local + stride];
+        }
+        workgroupBarrier();
+    }
+
+    if local == 0u {
+        laplacians[params.output + group.x] = partial_laplacians[0];
+    }
+}
+
+// 256 bins histogram of luminance, counted in the workgroup first
+@compute @workgroup_size(256)
+fn histogram(@builtin(workgroup_id) group: vec3<u32>, @builtin(local_invocation_index) local: u32) {
+    atomicStore(&partial_histogram[local], 0u);
+    workgroupBarrier();
+
+    for (var n = 0u; n < PIXELS_PER_INVOCATION; n++) {
+        let index = pixel_index(group.x, local, n);
+        if index < params.count {
+            let level = u32(luma(index % params.width, index / params.width));
+            atomicAdd(&partial_histogram[level], 1u);
+        }
+    }
+    workgroupBarrier();
+
+    // One bin per invocation, WORKGROUP_SIZE being 256
+    atomicAdd(&histograms[params.index * 256u + local], atomicLoad(&partial_histogram[local]));
+}
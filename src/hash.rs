@@ -0,0 +1,64 @@
+use image::{imageops::FilterType, DynamicImage};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Compute a 64 bits perceptual hash (dHash) of given image.
+/// Image is shrunk to 9x8 grayscale pixels, each bit tells whether
+/// a pixel is brighter than its right neighbour. Hashes of similar
+/// images are close in terms of hamming distance.
+pub fn perceptual_hash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    dhash(|x, y| small.get_pixel(x, y)[0])
+}
+
+/// Hash of the RGB pixels of given image at full resolution, along with
+/// its dimensions. Files holding the same pixels encoded differently, as
+/// a PNG saved again as a lossless WebP, get the same hash.
+pub fn pixel_hash(image: &DynamicImage) -> u64 {
+    let rgb = image.to_rgb8();
+    let mut hasher = Xxh3::new();
+    hasher.update(&rgb.width().to_le_bytes());
+    hasher.update(&rgb.height().to_le_bytes());
+    hasher.update(rgb.as_raw());
+    hasher.digest()
+}
+
+/// Perceptual hashes of given image in its 7 other orientations: mirrored
+/// both ways, rotated by 180 degrees, rotated by 90 degrees both ways and
+/// transposed along both diagonals. Thumbnails are turned rather than
+/// the image, the same way hashing shrinks it.
+pub fn oriented_hashes(image: &DynamicImage) -> Vec<u64> {
+    let wide = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let tall = image.resize_exact(8, 9, FilterType::Triangle).to_luma8();
+    let wide = |x, y| wide.get_pixel(x, y)[0];
+    let tall = |x, y| tall.get_pixel(x, y)[0];
+
+    vec![
+        dhash(|x, y| wide(8 - x, y)),
+        dhash(|x, y| wide(x, 7 - y)),
+        dhash(|x, y| wide(8 - x, 7 - y)),
+        dhash(|x, y| tall(y, x)),
+        dhash(|x, y| tall(y, 8 - x)),
+        dhash(|x, y| tall(7 - y, x)),
+        dhash(|x, y| tall(7 - y, 8 - x)),
+    ]
+}
+
+/// dHash of a 9x8 thumbnail, given its pixel at each position.
+fn dhash(pixel: impl Fn(u32, u32) -> u8) -> u64 {
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if pixel(x, y) > pixel(x + 1, y) {
+                hash |= 1;
+            }
+        }
+    }
+
+    hash
+}
+
+/// Number of differing bits between two hashes.
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
@@ -0,0 +1,135 @@
+use crate::cache::{
+    read_option, read_str, read_u32, read_u64, write_option, write_str, write_u32, write_u64,
+};
+use crate::index::resolve;
+use crate::paths;
+use crate::report::Report;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+/// First bytes of a hash database, followed by the format version.
+const MAGIC: &[u8; 4] = b"CLNH";
+const VERSION: u32 = 2;
+
+/// Content and perceptual hashes of every image ever scanned, so that
+/// images added to a dataset are checked against all of them for duplicates.
+pub struct HashDb {
+    path: String,
+    /// Reports only holding hashes, keyed by resolved path.
+    entries: HashMap<String, Report>,
+}
+
+impl HashDb {
+    /// Load database at given path, starting empty if it doesn't exist.
+    /// Entries of files deleted since are dropped, copies of them are
+    /// no duplicates anymore.
+    pub fn open(path: &str) -> io::Result<Self> {
+        let mut entries = match File::open(path) {
+            Ok(file) => read_entries(&mut BufReader::new(file))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+        entries.retain(|path, _| paths::decode(path).exists());
+
+        Ok(HashDb {
+            path: path.to_string(),
+            entries,
+        })
+    }
+
+    /// Hashes of images scanned before, except those of given reports
+    /// whose files were scanned again.
+    pub fn known(&self, reports: &[Report]) -> Vec<Report> {
+        let scanned: HashSet<String> = reports.iter().map(|report| resolve(&report.path)).collect();
+
+        self.entries
+            .iter()
+            .filter(|(path, _)| !scanned.contains(*path))
+            .map(|(_, entry)| entry.clone())
+            .collect()
+    }
+
+    /// Add hashes of given reports of kept files, replacing those of the
+    /// same files. Flagged files are left out, they're about to be deleted.
+    pub fn update(&mut self, reports: &[Report]) {
+        for report in reports {
+            let path = resolve(&report.path);
+            if report.is_flagged()
+                || (report.hash.is_none()
+                    && report.content_hash.is_none()
+                    && report.pixel_hash.is_none())
+            {
+                self.entries.remove(&path);
+                continue;
+            }
+
+            let mut entry = Report::new(&path);
+            entry.hash = report.hash;
+            entry.content_hash = report.content_hash;
+            entry.pixel_hash = report.pixel_hash;
+            self.entries.insert(path, entry);
+        }
+    }
+
+    /// Write the database, through a temporary file so that
+    /// an interrupted write doesn't lose it.
+    pub fn save(&self) -> io::Result<()> {
+        let temporary = format!("{}.tmp", self.path);
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+
+        writer.write_all(MAGIC)?;
+        write_u32(&mut writer, VERSION)?;
+        write_u64(&mut writer, self.entries.len() as u64)?;
+        for entry in self.entries.values() {
+            write_str(&mut writer, &entry.path)?;
+            write_option(&mut writer, entry.content_hash, |writer, hash| {
+                write_u64(writer, hash)
+            })?;
+            write_option(&mut writer, entry.hash, |writer, hash| {
+                write_u64(writer, hash)
+            })?;
+            write_option(&mut writer, entry.pixel_hash, |writer, hash| {
+                write_u64(writer, hash)
+            })?;
+        }
+
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(temporary, &self.path)
+    }
+}
+
+fn read_entries(reader: &mut impl Read) -> io::Result<HashMap<String, Report>> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+
+    // Hashes can't be computed again, don't overwrite what isn't ours
+    let version = if &magic == MAGIC {
+        read_u32(reader)?
+    } else {
+        0
+    };
+    if !(1..=VERSION).contains(&version) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a cleanax hash database",
+        ));
+    }
+
+    let count = read_u64(reader)?;
+    let mut entries = HashMap::new();
+    for _ in 0..count {
+        let mut entry = Report::new(&read_str(reader)?);
+        entry.content_hash = read_option(reader, read_u64)?;
+        entry.hash = read_option(reader, read_u64)?;
+        // Databases written before pixel hashes don't have them
+        if version >= 2 {
+            entry.pixel_hash = read_option(reader, read_u64)?;
+        }
+        entries.insert(entry.path.clone(), entry);
+    }
+
+    Ok(entries)
+}
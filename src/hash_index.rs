@@ -0,0 +1,196 @@
+use crate::hash;
+use pyo3::prelude::*;
+use pyo3::PySequenceProtocol;
+
+/// Node of the BK-tree, children are keyed by their distance to it.
+struct Node {
+    hash: u64,
+    children: Vec<(u32, usize)>,
+}
+
+/// Perceptual hashes searchable by hamming distance, as a BK-tree.
+/// Finding hashes close to another one only visits subtrees
+/// the triangle inequality can't rule out, instead of every hash.
+/// Hashes are identified by the order they were inserted in.
+#[pyclass]
+pub struct HashIndex {
+    /// Nodes in insertion order, the first one is the root.
+    nodes: Vec<Node>,
+}
+
+impl HashIndex {
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Visit hashes within `max_distance` of given one, until
+    /// `visit` returns false. Return whether it always returned true.
+    fn search(
+        &self,
+        hash: u64,
+        max_distance: u32,
+        mut visit: impl FnMut(usize, u32) -> bool,
+    ) -> bool {
+        if self.nodes.is_empty() {
+            return true;
+        }
+
+        let mut pending = vec![0];
+        while let Some(id) = pending.pop() {
+            let node = &self.nodes[id];
+            let distance = hash::distance(hash, node.hash);
+            if distance <= max_distance && !visit(id, distance) {
+                return false;
+            }
+
+            // Hashes within reach are in subtrees at a distance from the
+            // node that differs from ours by at most max_distance
+            for (child_distance, child) in &node.children {
+                if child_distance + max_distance >= distance
+                    && *child_distance <= distance + max_distance
+                {
+                    pending.push(*child);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Check if a hash within `max_distance` of given one was inserted.
+    pub fn contains_within(&self, hash: u64, max_distance: u32) -> bool {
+        !self.search(hash, max_distance, |_, _| false)
+    }
+}
+
+#[pymethods]
+impl HashIndex {
+    /// Build index from given hashes, as found in profiles.
+    #[new]
+    #[args(hashes = "Vec::new()")]
+    pub fn new(hashes: Vec<u64>) -> Self {
+        let mut index = HashIndex { nodes: Vec::new() };
+        for hash in hashes {
+            index.insert(hash);
+        }
+        index
+    }
+
+    /// Add given hash, returning its identifier.
+    pub fn insert(&mut self, hash: u64) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            hash,
+            children: Vec::new(),
+        });
+        if id == 0 {
+            return id;
+        }
+
+        // Go down the tree to the first node without a child at our distance
+        let mut parent = 0;
+        loop {
+            let distance = hash::distance(hash, self.nodes[parent].hash);
+            let children = &mut self.nodes[parent].children;
+            match children.iter().find(|(other, _)| *other == distance) {
+                Some((_, child)) => parent = *child,
+                None => {
+                    children.push((distance, id));
+                    return id;
+                }
+            }
+        }
+    }
+
+    /// Identifiers and distances of hashes within `max_distance`
+    /// of given one, closest first.
+    pub fn query(&self, hash: u64, max_distance: u32) -> Vec<(usize, u32)> {
+        let mut found = Vec::new();
+        self.search(hash, max_distance, |id, distance| {
+            found.push((id, distance));
+            true
+        });
+
+        found.sort_unstable_by_key(|(id, distance)| (*distance, *id));
+        found
+    }
+
+    /// Hash of given identifier.
+    fn hash(&self, id: usize) -> Option<u64> {
+        self.nodes.get(id).map(|node| node.hash)
+    }
+}
+
+#[pyproto]
+impl PySequenceProtocol for HashIndex {
+    fn __len__(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Identifiers and distances of hashes within `max_distance`, closest
+    /// first, the way a linear scan finds them.
+    fn linear(hashes: &[u64], hash: u64, max_distance: u32) -> Vec<(usize, u32)> {
+        let mut found: Vec<_> = hashes
+            .iter()
+            .map(|other| hash::distance(hash, *other))
+            .enumerate()
+            .filter(|(_, distance)| *distance <= max_distance)
+            .collect();
+        found.sort_unstable_by_key(|(id, distance)| (*distance, *id));
+        found
+    }
+
+    #[test]
+    fn queries_find_what_a_linear_scan_finds() {
+        // Xorshift, hashes packed around a few centers like near-duplicates
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let centers: Vec<u64> = (0..8).map(|_| next()).collect();
+        let hashes: Vec<u64> = (0..500)
+            .map(|i| centers[i % centers.len()] ^ (next() & next() & next()))
+            .collect();
+
+        let index = HashIndex::new(hashes.clone());
+        assert_eq!(index.len(), hashes.len());
+        for hash in centers.iter().copied().chain((0..20).map(|_| next())) {
+            for max_distance in [0, 3, 10, 24] {
+                assert_eq!(
+                    index.query(hash, max_distance),
+                    linear(&hashes, hash, max_distance)
+                );
+                assert_eq!(
+                    index.contains_within(hash, max_distance),
+                    !linear(&hashes, hash, max_distance).is_empty()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn identifiers_follow_insertion_order() {
+        let mut index = HashIndex::new(Vec::new());
+        assert!(index.is_empty());
+        assert!(index.query(0, 64).is_empty());
+
+        assert_eq!(index.insert(0b1111), 0);
+        assert_eq!(index.insert(0b1111), 1);
+        assert_eq!(index.insert(0b0111), 2);
+        assert_eq!(index.query(0b1111, 1), vec![(0, 0), (1, 0), (2, 1)]);
+        assert_eq!(index.hash(2), Some(0b0111));
+        assert_eq!(index.hash(3), None);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::config::SymlinkPolicy;
+use crate::paths;
+use crate::report::Report;
+use crate::summary;
+use crate::walk::{Modified, Walk};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::{self, Path, PathBuf};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// First line of a hash manifest, followed by the folder of the dataset.
+const ROOT_PREFIX: &str = "# cleanax hash manifest of ";
+
+/// Content hashes of the files of a cleaned dataset, to tell later if
+/// some were added, deleted or modified since. A manifest holds the
+/// folder of the dataset on its first line, then a line
+/// `{hash}  {path}` for every file, paths being relative to the folder
+/// of the manifest unless they lie out of it.
+pub struct HashManifest {
+    root: PathBuf,
+    hashes: BTreeMap<PathBuf, u64>,
+}
+
+/// Changes of a dataset since its hash manifest was written.
+#[derive(Default)]
+pub struct Changes {
+    /// Files of the dataset folder missing from the manifest.
+    pub added: Vec<String>,
+    /// Files of the manifest missing from the dataset.
+    pub deleted: Vec<String>,
+    /// Files whose content changed, rotten bits included.
+    pub modified: Vec<String>,
+}
+
+impl HashManifest {
+    /// Manifest of the files given reports kept, once renamed. Files of
+    /// archives and buckets are left out, they can't be hashed on their own.
+    pub fn of_kept(reports: &[Report]) -> io::Result<Self> {
+        let files: Vec<PathBuf> = reports
+            .iter()
+            .filter(|report| !report.is_flagged())
+            .map(|report| paths::decode(report.renamed.as_ref().unwrap_or(&report.path)))
+            .filter(|path| path.is_file())
+            .collect();
+        let folders: Vec<PathBuf> = files
+            .iter()
+            .map(|file| file.parent().unwrap_or_else(|| Path::new("")).to_path_buf())
+            .collect();
+
+        Self::of_files(paths::common_root(&folders), files)
+    }
+
+    /// Manifest of every file under given folder, as a cleaned copy,
+    /// but for the manifest itself.
+    pub fn of_folder(root: &str, manifest: &str) -> io::Result<Self> {
+        let files = list_files(&paths::decode(root), manifest)?;
+        Self::of_files(paths::decode(root), files)
+    }
+
+    fn of_files(root: PathBuf, files: Vec<PathBuf>) -> io::Result<Self> {
+        let hashes = files
+            .into_par_iter()
+            .map(|file| Ok((hash_file(&file)?, file)))
+            .collect::<io::Result<Vec<_>>>()?
+            .into_iter()
+            .map(|(hash, file)| (file, hash))
+            .collect();
+
+        Ok(HashManifest { root, hashes })
+    }
+
+    /// Write manifest to given file, replacing it at once.
+    pub fn write(&self, manifest: &str) -> io::Result<()> {
+        let folder = manifest_folder(&path::absolute(paths::decode(manifest))?);
+        let mut content = format!("{}{}\n", ROOT_PREFIX, relative_to(&self.root, &folder)?);
+        for (file, hash) in &self.hashes {
+            content.push_str(&format!("{:016x}  {}\n", hash, relative_to(file, &folder)?));
+        }
+
+        summary::write_atomic(manifest, &content)
+    }
+
+    /// Read manifest from given file.
+    pub fn read(manifest: &str) -> io::Result<Self> {
+        let folder = manifest_folder(&paths::decode(manifest));
+        let invalid = |message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", manifest, message),
+            )
+        };
+
+        let mut lines = BufReader::new(fs::File::open(paths::decode(manifest))?).split(b'\n');
+        let root = lines.next().transpose()?.unwrap_or_default();
+        let root = paths::from_bytes(&root);
+        let root = root
+            .strip_prefix(ROOT_PREFIX)
+            .ok_or_else(|| invalid("not a cleanax hash manifest"))?;
+
+        let mut hashes = BTreeMap::new();
+        for line in lines {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let line = paths::from_bytes(&line);
+            let (hash, file) = line
+                .split_once("  ")
+                .ok_or_else(|| invalid("lines must be `{hash}  {path}`"))?;
+            let hash = u64::from_str_radix(hash, 16).map_err(|_| invalid("bad hash"))?;
+            hashes.insert(folder.join(paths::decode(file)), hash);
+        }
+
+        Ok(HashManifest {
+            root: folder.join(paths::decode(root)),
+            hashes,
+        })
+    }
+
+    /// Hash files of the dataset again and compare them with the manifest.
+    /// The manifest itself isn't counted as an addition if it lies in the
+    /// dataset folder.
+    pub fn verify(&self, manifest: &str) -> io::Result<Changes> {
+        let present: BTreeSet<PathBuf> = list_files(&self.root, manifest)?.into_iter().collect();
+
+        let modified: Vec<String> = self
+            .hashes
+            .par_iter()
+            .filter(|(file, _)| file.is_file())
+            .map(|(file, hash)| Ok((hash_file(file)? != *hash).then(|| paths::encode(file))))
+            .collect::<io::Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        let mut changes = Changes {
+            modified,
+            ..Changes::default()
+        };
+        for file in &present {
+            if !self.hashes.contains_key(file) {
+                changes.added.push(paths::encode(file));
+            }
+        }
+        for file in self.hashes.keys() {
+            if !file.is_file() {
+                changes.deleted.push(paths::encode(file));
+            }
+        }
+
+        Ok(changes)
+    }
+}
+
+fn hash_file(path: &Path) -> io::Result<u64> {
+    Ok(xxh3_64(&fs::read(path)?))
+}
+
+/// Every file under given folder but given manifest, symbolic links
+/// left as they are.
+fn list_files(root: &Path, manifest: &str) -> io::Result<Vec<PathBuf>> {
+    // Root is empty for paths relative to the current folder
+    let current = root.as_os_str().is_empty();
+    let walk = Walk::new(
+        &paths::encode(if current { Path::new(".") } else { root }),
+        true,
+        SymlinkPolicy::Keep,
+        Modified::default(),
+    )?;
+    let manifest = fs::canonicalize(paths::decode(manifest)).ok();
+
+    let mut files = Vec::new();
+    for path in walk {
+        let mut path = path?;
+        if current {
+            path = path.strip_prefix(".").unwrap_or(&path).to_path_buf();
+        }
+        if path.is_file() && fs::canonicalize(&path).ok() != manifest {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+/// Folder paths of given manifest are relative to.
+fn manifest_folder(manifest: &Path) -> PathBuf {
+    manifest
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .to_path_buf()
+}
+
+/// Given path relative to given absolute folder if it lies in it,
+/// absolute otherwise. Scanned paths are relative to the current folder,
+/// which may not be the one of the manifest.
+fn relative_to(path: &Path, folder: &Path) -> io::Result<String> {
+    let path = path::absolute(path)?;
+    Ok(match path.strip_prefix(folder) {
+        Ok(relative) => paths::encode(relative),
+        Err(_) => paths::encode(&path),
+    })
+}
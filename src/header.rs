@@ -0,0 +1,135 @@
+use crate::dicom;
+use crate::heic;
+use crate::paths;
+use crate::raw;
+use crate::video;
+use image::error::{DecodingError, ImageFormatHint};
+use image::{io::Reader as ImageReader, ImageError, ImageFormat, ImageResult};
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+
+/// Bytes read to guess the format of a file, enough for every magic number,
+/// DICOM ones coming after a 128 bytes preamble.
+const MAGIC_SIZE: u64 = 132;
+
+/// Extensions of HEIC/HEIF files, which the image crate doesn't know about.
+const HEIC_EXTENSIONS: &[&str] = &["heic", "heif"];
+
+/// Read given file whole, unless cheap checks of its header already tell
+/// it's not an image. Files that are empty, of an unknown format or whose
+/// header doesn't hold valid dimensions are rejected without being read.
+pub fn read_checked(path: &str) -> io::Result<ImageResult<Vec<u8>>> {
+    let mut file = File::open(paths::decode(path))?;
+    let size = file.metadata()?.len();
+
+    if let Err(err) = check_header(path, size, &mut file) {
+        return Ok(Err(err));
+    }
+
+    file.seek(SeekFrom::Start(0))?;
+    let mut data = Vec::with_capacity(size as usize);
+    file.read_to_end(&mut data)?;
+
+    Ok(Ok(data))
+}
+
+/// Check given file from its header alone, without reading it whole.
+/// Return whether it's a video, which has no image header to check.
+pub fn verify_header(path: &str) -> io::Result<ImageResult<bool>> {
+    let mut file = File::open(paths::decode(path))?;
+    let size = file.metadata()?.len();
+
+    let mut magic = Vec::new();
+    file.by_ref().take(MAGIC_SIZE).read_to_end(&mut magic)?;
+    if video::is_video(&magic) {
+        return Ok(Ok(true));
+    }
+
+    file.seek(SeekFrom::Start(0))?;
+    Ok(check_header(path, size, &mut file).map(|()| false))
+}
+
+/// Check the header of given file content, as `verify_header` does.
+pub fn verify_data(name: &str, data: &[u8]) -> ImageResult<()> {
+    check_header(name, data.len() as u64, &mut Cursor::new(data))
+}
+
+/// Dimensions of the image held in given file content, from its header.
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Extension given file should have according to its magic bytes, when
+/// its own extension names another image format. Files without extension,
+/// or whose extension or content isn't of a known image format, are left
+/// alone, as are camera RAW files which look like TIFF ones.
+pub fn fixed_extension(name: &str, data: &[u8]) -> Option<&'static str> {
+    if raw::is_raw(name) {
+        return None;
+    }
+
+    let path = paths::decode(name);
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let extensions = match heic::is_heic(data) {
+        true => HEIC_EXTENSIONS,
+        false => image::guess_format(data).ok()?.extensions_str(),
+    };
+
+    let known = ImageFormat::from_extension(&extension).is_some()
+        || HEIC_EXTENSIONS.contains(&extension.as_str());
+    match known && !extensions.contains(&extension.as_str()) {
+        true => extensions.first().copied(),
+        false => None,
+    }
+}
+
+/// Check size, magic bytes and dimensions of given file,
+/// only reading bytes its header is made of.
+fn check_header<R: Read + Seek>(name: &str, size: u64, file: &mut R) -> ImageResult<()> {
+    if size == 0 {
+        return Err(ImageError::IoError(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "empty file",
+        )));
+    }
+
+    let mut magic = Vec::new();
+    file.by_ref().take(MAGIC_SIZE).read_to_end(&mut magic)?;
+
+    // Decoded by their own libraries, which read them whole
+    if raw::is_raw(name)
+        || heic::is_heic(&magic)
+        || dicom::is_dicom(name, &magic)
+        || video::is_video(&magic)
+    {
+        return Ok(());
+    }
+
+    // Same guess as decoding, magic bytes then extension
+    let format = match image::guess_format(&magic) {
+        Ok(format) => format,
+        Err(_) => ImageFormat::from_path(name)
+            .map_err(|_| ImageError::Unsupported(ImageFormatHint::Unknown.into()))?,
+    };
+
+    // The image crate only handles a subset of WebP
+    if format == ImageFormat::WebP {
+        return Ok(());
+    }
+
+    file.seek(SeekFrom::Start(0))?;
+    let (width, height) =
+        ImageReader::with_format(BufReader::new(file), format).into_dimensions()?;
+    if width == 0 || height == 0 {
+        return Err(ImageError::Decoding(DecodingError::new(
+            ImageFormatHint::Exact(format),
+            "image has no pixels",
+        )));
+    }
+
+    Ok(())
+}
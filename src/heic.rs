@@ -0,0 +1,76 @@
+use image::error::{ImageFormatHint, UnsupportedError, UnsupportedErrorKind};
+use image::{DynamicImage, ImageError, ImageResult};
+
+/// Brands of the `ftyp` box identifying HEIF images.
+const BRANDS: [&[u8; 4]; 6] = [b"heic", b"heix", b"hevc", b"hevx", b"mif1", b"msf1"];
+
+/// Check magic bytes of given file for a HEIC/HEIF image.
+pub fn is_heic(data: &[u8]) -> bool {
+    data.len() >= 12 && &data[4..8] == b"ftyp" && BRANDS.iter().any(|b| &data[8..12] == *b)
+}
+
+fn heic_unsupported() -> ImageError {
+    let format = ImageFormatHint::Name("HEIF".to_string());
+
+    ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+        format.clone(),
+        UnsupportedErrorKind::Format(format),
+    ))
+}
+
+/// Decode primary image of given HEIC/HEIF file.
+#[cfg(feature = "heic")]
+pub fn load_heic(data: &[u8]) -> ImageResult<DynamicImage> {
+    use image::error::DecodingError;
+    use image::{ImageBuffer, Rgb, Rgba};
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let heif_error = |err: libheif_rs::HeifError| {
+        ImageError::Decoding(DecodingError::new(
+            ImageFormatHint::Name("HEIF".to_string()),
+            err.to_string(),
+        ))
+    };
+
+    let context = HeifContext::read_from_bytes(data).map_err(heif_error)?;
+    let handle = context.primary_image_handle().map_err(heif_error)?;
+    let has_alpha = handle.has_alpha_channel();
+    let chroma = if has_alpha {
+        RgbChroma::Rgba
+    } else {
+        RgbChroma::Rgb
+    };
+
+    let image = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(chroma), None)
+        .map_err(heif_error)?;
+    let plane = image.planes().interleaved.ok_or_else(heic_unsupported)?;
+
+    // Rows may be padded, keep visible pixels only
+    let channels = if has_alpha { 4 } else { 3 };
+    let row = plane.width as usize * channels;
+    let raw: Vec<u8> = plane
+        .data
+        .chunks(plane.stride)
+        .take(plane.height as usize)
+        .flat_map(|line| &line[..row])
+        .copied()
+        .collect();
+
+    let image = if has_alpha {
+        ImageBuffer::<Rgba<u8>, _>::from_raw(plane.width, plane.height, raw)
+            .map(DynamicImage::ImageRgba8)
+    } else {
+        ImageBuffer::<Rgb<u8>, _>::from_raw(plane.width, plane.height, raw)
+            .map(DynamicImage::ImageRgb8)
+    };
+
+    image.ok_or_else(heic_unsupported)
+}
+
+/// HEIC/HEIF decoding isn't compiled in, tell so instead of
+/// reporting the file as garbage.
+#[cfg(not(feature = "heic"))]
+pub fn load_heic(_data: &[u8]) -> ImageResult<DynamicImage> {
+    Err(heic_unsupported())
+}
@@ -0,0 +1,49 @@
+use std::io::{self, Read};
+use std::time::Duration;
+use ureq::{Agent, AgentBuilder};
+
+/// Download images over HTTP(S), refusing bodies larger than a limit.
+pub struct Downloader {
+    agent: Agent,
+    max_size: u64,
+}
+
+impl Downloader {
+    /// `timeout` applies to the whole request, from connection to
+    /// the end of the body.
+    pub fn new(timeout: Duration, max_size: u64) -> Self {
+        Downloader {
+            agent: AgentBuilder::new().timeout(timeout).build(),
+            max_size,
+        }
+    }
+
+    /// Download content of given URL.
+    pub fn download(&self, url: &str) -> io::Result<Vec<u8>> {
+        let response = self.agent.get(url).call().map_err(io::Error::other)?;
+
+        // Don't bother downloading a body announced as too large
+        let length = response
+            .header("Content-Length")
+            .and_then(|length| length.parse::<u64>().ok());
+        if let Some(length) = length.filter(|length| *length > self.max_size) {
+            return Err(io::Error::other(format!(
+                "body of {} bytes exceeds max_download_size",
+                length
+            )));
+        }
+
+        let mut data = Vec::with_capacity(length.unwrap_or(0) as usize);
+        response
+            .into_reader()
+            .take(self.max_size + 1)
+            .read_to_end(&mut data)?;
+
+        // Length may be missing or lying
+        if data.len() as u64 > self.max_size {
+            return Err(io::Error::other("body exceeds max_download_size"));
+        }
+
+        Ok(data)
+    }
+}
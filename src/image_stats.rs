@@ -0,0 +1,72 @@
+use crate::stats::{channel_names, get_image_histograms, get_moments};
+use image::{DynamicImage, GenericImageView};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Statistics of every channel of an image, in buffer order.
+/// Minimum, maximum, mean and standard deviation are on the scale of its
+/// values (0-255, or 0-65535 for 16 bits images), histograms have
+/// 256 bins whatever the depth.
+#[pyclass]
+pub struct ImageStats {
+    #[pyo3(get)]
+    width: u32,
+    #[pyo3(get)]
+    height: u32,
+    /// Name of each channel ("r", "g", "b", "a", "l").
+    #[pyo3(get)]
+    channels: Vec<&'static str>,
+    #[pyo3(get)]
+    min: Vec<f64>,
+    #[pyo3(get)]
+    max: Vec<f64>,
+    #[pyo3(get)]
+    mean: Vec<f64>,
+    #[pyo3(get)]
+    std: Vec<f64>,
+    #[pyo3(get)]
+    histograms: Vec<Vec<u64>>,
+}
+
+impl ImageStats {
+    pub fn of(image: &DynamicImage) -> Self {
+        let (width, height) = image.dimensions();
+        let moments = get_moments(image);
+
+        ImageStats {
+            width,
+            height,
+            channels: channel_names(image).to_vec(),
+            min: moments.iter().map(|moments| moments.min()).collect(),
+            max: moments.iter().map(|moments| moments.max()).collect(),
+            mean: moments.iter().map(|moments| moments.mean()).collect(),
+            std: moments
+                .iter()
+                .map(|moments| moments.variance().sqrt())
+                .collect(),
+            histograms: get_image_histograms(image)
+                .iter()
+                .map(|histogram| histogram.to_vec())
+                .collect(),
+        }
+    }
+}
+
+#[pymethods]
+impl ImageStats {
+    /// Convert statistics to a dictionary keyed by channel name.
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        for (index, channel) in self.channels.iter().enumerate() {
+            let stats = PyDict::new(py);
+            stats.set_item("min", self.min[index])?;
+            stats.set_item("max", self.max[index])?;
+            stats.set_item("mean", self.mean[index])?;
+            stats.set_item("std", self.std[index])?;
+            stats.set_item("histogram", &self.histograms[index])?;
+            dict.set_item(channel, stats)?;
+        }
+
+        Ok(dict)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::paths::{self, FsPath};
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+use pyo3::PySequenceProtocol;
+use std::collections::HashSet;
+use std::fs;
+
+/// Resolve given path, so that relative and absolute forms of a path
+/// match. Paths that don't exist anymore are kept as is.
+pub fn resolve(path: &str) -> String {
+    fs::canonicalize(paths::decode(path))
+        .map(|path| paths::encode(&path))
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// Set of flagged files, to skip them at load time
+/// rather than deleting them.
+#[pyclass]
+pub struct CleanaxIndex {
+    flagged: HashSet<String>,
+}
+
+impl CleanaxIndex {
+    pub fn from_paths(paths: &[String]) -> Self {
+        CleanaxIndex {
+            flagged: paths.iter().map(|path| resolve(path)).collect(),
+        }
+    }
+}
+
+#[pymethods]
+impl CleanaxIndex {
+    /// Build index from flagged paths, as returned by `clean`.
+    #[new]
+    fn new(flagged: Vec<FsPath>) -> Self {
+        let flagged: Vec<String> = flagged.into_iter().map(|path| path.0).collect();
+        CleanaxIndex::from_paths(&flagged)
+    }
+
+    /// Tell for each given path whether it's flagged.
+    fn mask(&self, paths: Vec<FsPath>) -> Vec<bool> {
+        paths
+            .iter()
+            .map(|path| self.flagged.contains(&resolve(&path.0)))
+            .collect()
+    }
+
+    /// Wrap given torch dataset in a `Subset` skipping flagged samples.
+    /// Paths of samples are taken from `dataset.samples`, as torchvision
+    /// `ImageFolder` and `DatasetFolder` hold them, unless given.
+    #[args(paths = "None")]
+    fn filter(
+        &self,
+        py: Python,
+        dataset: PyObject,
+        paths: Option<Vec<FsPath>>,
+    ) -> PyResult<PyObject> {
+        let paths = match paths {
+            Some(paths) => paths,
+            None => dataset
+                .getattr(py, "samples")?
+                .as_ref(py)
+                .iter()?
+                .map(|sample| sample?.get_item(0)?.extract())
+                .collect::<PyResult<Vec<FsPath>>>()?,
+        };
+
+        let kept: Vec<usize> = self
+            .mask(paths)
+            .iter()
+            .enumerate()
+            .filter(|(_, flagged)| !**flagged)
+            .map(|(index, _)| index)
+            .collect();
+
+        let subset = py
+            .import("torch.utils.data")?
+            .call1("Subset", (dataset, PyList::new(py, kept)))?;
+
+        Ok(subset.into())
+    }
+}
+
+#[pyproto]
+impl PySequenceProtocol for CleanaxIndex {
+    fn __len__(&self) -> usize {
+        self.flagged.len()
+    }
+
+    fn __contains__(&self, path: FsPath) -> bool {
+        self.flagged.contains(&resolve(&path.0))
+    }
+}
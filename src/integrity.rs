@@ -0,0 +1,96 @@
+use crate::exif::Exif;
+use flate2::Crc;
+use std::convert::TryInto;
+
+/// Problems decoders put up with in given file: broken checksums, missing
+/// end markers, trailing bytes or unreadable metadata. Such a file may
+/// decode, yet be damaged or tampered with.
+pub fn warnings(data: &[u8]) -> Vec<String> {
+    let mut warnings = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_warnings(data)
+    } else if data.starts_with(&[0xff, 0xd8]) {
+        jpeg_warnings(data)
+    } else if data.starts_with(b"GIF8") {
+        gif_warnings(data)
+    } else {
+        Vec::new()
+    };
+
+    if Exif::read(data).broken {
+        warnings.push("EXIF metadata can't be parsed".to_string());
+    }
+
+    warnings
+}
+
+/// Walk chunks of a PNG file, checking their CRC and that it ends right
+/// after its IEND chunk.
+fn png_warnings(data: &[u8]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut at = 8;
+
+    loop {
+        let chunk = data
+            .get(at..at + 4)
+            .map(|length| u32::from_be_bytes(length.try_into().unwrap()) as usize)
+            .and_then(|length| Some((data.get(at + 4..at + 8 + length)?, length)));
+        let (chunk, length) = match chunk {
+            Some(chunk) => chunk,
+            None => {
+                warnings.push("PNG ends without an IEND chunk".to_string());
+                return warnings;
+            }
+        };
+        let kind = String::from_utf8_lossy(&chunk[..4]).into_owned();
+
+        match data.get(at + 8 + length..at + 12 + length) {
+            Some(crc) => {
+                let mut expected = Crc::new();
+                expected.update(chunk);
+                if expected.sum() != u32::from_be_bytes(crc.try_into().unwrap()) {
+                    warnings.push(format!("PNG chunk {} has a bad CRC", kind));
+                }
+            }
+            None => {
+                warnings.push("PNG ends without an IEND chunk".to_string());
+                return warnings;
+            }
+        }
+
+        at += 12 + length;
+        if kind == "IEND" {
+            break;
+        }
+    }
+
+    if at < data.len() {
+        warnings.push(format!("{} bytes after PNG IEND chunk", data.len() - at));
+    }
+    warnings
+}
+
+/// Check that a JPEG file ends with its EOI marker. Bytes after the last
+/// one are reported, but for padding.
+fn jpeg_warnings(data: &[u8]) -> Vec<String> {
+    // Embedded thumbnails end with their own EOI marker
+    let end = data
+        .windows(2)
+        .rposition(|marker| marker == [0xff, 0xd9])
+        .map(|at| at + 2);
+
+    match end {
+        None => vec!["JPEG ends without an EOI marker".to_string()],
+        Some(end) if data[end..].iter().any(|byte| *byte != 0) => {
+            vec![format!("{} bytes after JPEG EOI marker", data.len() - end)]
+        }
+        Some(_) => Vec::new(),
+    }
+}
+
+/// Check that a GIF file ends with its trailer.
+fn gif_warnings(data: &[u8]) -> Vec<String> {
+    match data.last() {
+        Some(0x3b) => Vec::new(),
+        _ => vec!["GIF ends without a trailer".to_string()],
+    }
+}
@@ -0,0 +1,217 @@
+use crate::paths;
+use serde_json::{json, Value};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path;
+use std::sync::Mutex;
+
+/// What was done to a file.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Delete,
+    Trash,
+    Rename,
+}
+
+impl Action {
+    fn as_str(self) -> &'static str {
+        match self {
+            Action::Delete => "delete",
+            Action::Trash => "trash",
+            Action::Rename => "rename",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Action> {
+        match value {
+            "delete" => Some(Action::Delete),
+            "trash" => Some(Action::Trash),
+            "rename" => Some(Action::Rename),
+            _ => None,
+        }
+    }
+}
+
+struct Entry {
+    action: Action,
+    path: String,
+    /// New path of a renamed file.
+    to: Option<String>,
+}
+
+impl Entry {
+    fn to_json(&self) -> Value {
+        match &self.to {
+            Some(to) => json!({"action": self.action.as_str(), "path": self.path, "to": to}),
+            None => json!({"action": self.action.as_str(), "path": self.path}),
+        }
+    }
+
+    fn from_json(value: &Value) -> Option<Entry> {
+        Some(Entry {
+            action: Action::parse(value.get("action")?.as_str()?)?,
+            path: value.get("path")?.as_str()?.to_string(),
+            to: value.get("to").and_then(Value::as_str).map(str::to_string),
+        })
+    }
+}
+
+/// Destructive actions taken on files, appended to a file as JSON lines
+/// right when they happen, so that they can be undone later on.
+/// Does nothing without a file.
+pub struct Journal {
+    file: Option<Mutex<File>>,
+}
+
+impl Journal {
+    pub fn open(path: Option<&str>) -> io::Result<Self> {
+        let file = match path {
+            Some(path) => Some(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => None,
+        };
+
+        Ok(Journal { file })
+    }
+
+    /// Record an action on given file, and where it went if renamed.
+    /// Paths are made absolute, the journal being undone from anywhere.
+    pub fn record(&self, action: Action, path: &str, to: Option<&str>) -> io::Result<()> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+
+        let entry = Entry {
+            action,
+            path: absolute(path)?,
+            to: to.map(absolute).transpose()?,
+        };
+
+        let mut file = file.lock().unwrap();
+        writeln!(file, "{}", entry.to_json())?;
+        file.sync_data()
+    }
+}
+
+fn absolute(path: &str) -> io::Result<String> {
+    Ok(paths::encode(&path::absolute(paths::decode(path))?))
+}
+
+/// Undo actions of given journal, latest first: renamed files get their
+/// name back, trashed ones are restored from the system trash when it
+/// can be listed. Return restored files, and those that can't be, deleted
+/// ones among them. The journal is left with the latter only.
+pub fn undo(journal: &str) -> io::Result<(Vec<String>, Vec<String>)> {
+    let mut entries = Vec::new();
+    for line in fs::read_to_string(journal)?.lines() {
+        // A line being written when the process died is cut short
+        let entry = serde_json::from_str(line)
+            .ok()
+            .and_then(|value| Entry::from_json(&value));
+        entries.extend(entry);
+    }
+
+    let trashed: Vec<&str> = entries
+        .iter()
+        .filter(|entry| entry.action == Action::Trash)
+        .map(|entry| entry.path.as_str())
+        .collect();
+    let mut untrashed = restore_trashed(&trashed)?;
+
+    let mut restored = Vec::new();
+    let mut lost = Vec::new();
+    for entry in entries.iter().rev() {
+        let undone = match entry.action {
+            Action::Delete => false,
+            Action::Trash => match untrashed.iter().position(|path| path == &entry.path) {
+                Some(index) => {
+                    untrashed.swap_remove(index);
+                    true
+                }
+                None => false,
+            },
+            Action::Rename => match &entry.to {
+                Some(to) => rename_back(to, &entry.path),
+                None => false,
+            },
+        };
+
+        if undone {
+            restored.push(entry.path.clone());
+        } else {
+            lost.push(entry);
+        }
+    }
+
+    let mut file = File::create(journal)?;
+    for entry in lost.iter().rev() {
+        writeln!(file, "{}", entry.to_json())?;
+    }
+
+    Ok((
+        restored,
+        lost.into_iter().map(|entry| entry.path.clone()).collect(),
+    ))
+}
+
+/// Give a renamed file its former name back, unless it's gone
+/// or another file took that name.
+fn rename_back(to: &str, path: &str) -> bool {
+    let (to, path) = (paths::decode(to), paths::decode(path));
+    to.exists() && !path.exists() && fs::rename(to, path).is_ok()
+}
+
+/// Restore given files from the system trash, the last trashed one when
+/// several held the same path. Return restored files.
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn restore_trashed(files: &[&str]) -> io::Result<Vec<String>> {
+    use trash::os_limited;
+
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let items = os_limited::list().map_err(io::Error::other)?;
+
+    let mut restored = Vec::new();
+    for file in files {
+        let path = paths::decode(file);
+        let item = items
+            .iter()
+            .filter(|item| item.original_path() == path)
+            .max_by_key(|item| item.time_deleted);
+
+        // Files put back in place since are left in the trash
+        if let Some(item) = item {
+            if !path.exists() && os_limited::restore_all([item.clone()]).is_ok() {
+                restored.push(file.to_string());
+            }
+        }
+    }
+
+    Ok(restored)
+}
+
+/// The system trash can't be listed on this platform.
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+fn restore_trashed(_files: &[&str]) -> io::Result<Vec<String>> {
+    Ok(Vec::new())
+}
@@ -0,0 +1,296 @@
+use crate::captions;
+use crate::journal::{Action, Journal};
+use crate::paths;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{self, Component, Path, PathBuf};
+
+/// Removed files, as image references of a label file may point at them.
+pub struct Removed {
+    /// Absolute paths of removed files.
+    paths: HashSet<PathBuf>,
+    /// Every trailing part of removed paths (`a/b/c.jpg` gives `c.jpg`,
+    /// `b/c.jpg` and `a/b/c.jpg`), along with how many files end with it.
+    suffixes: HashMap<String, usize>,
+    /// Folder of the label file, references are relative to it first.
+    folder: PathBuf,
+}
+
+impl Removed {
+    /// Removed files, referred to by given label file.
+    pub fn new(removed: &[String], label_file: &str) -> io::Result<Self> {
+        let mut paths = HashSet::new();
+        let mut suffixes = HashMap::new();
+
+        for path in removed {
+            let components: Vec<_> = Path::new(path)
+                .components()
+                .filter(|component| !matches!(component, Component::RootDir | Component::Prefix(_)))
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect();
+            for start in 0..components.len() {
+                *suffixes.entry(components[start..].join("/")).or_insert(0) += 1;
+            }
+
+            paths.insert(normalize(&path::absolute(paths::decode(path))?));
+        }
+
+        let folder = path::absolute(paths::decode(label_file))?
+            .parent()
+            .map_or_else(PathBuf::new, Path::to_path_buf);
+
+        Ok(Removed {
+            paths,
+            suffixes,
+            folder,
+        })
+    }
+
+    /// Check if given image reference, as written in the label file,
+    /// points at one of the removed files. References are resolved from
+    /// the folder of the label file, and only matched against the end of
+    /// removed paths when that leads nowhere, images living in a folder
+    /// label files don't tell. The end of a path must then be the one of
+    /// a single removed file, and a file name alone must not be `ambiguous`
+    /// in the label file.
+    pub fn contains(&self, reference: &str, ambiguous: &HashSet<&str>) -> bool {
+        let reference = reference.trim_start_matches("./");
+        if reference.is_empty() {
+            return false;
+        }
+
+        let resolved = normalize(&self.folder.join(paths::decode(reference)));
+        if self.paths.contains(&resolved) {
+            return true;
+        }
+        // Another file, which is kept
+        if resolved.exists() {
+            return false;
+        }
+
+        match self.suffixes.get(reference) {
+            Some(count) if reference.contains('/') => *count == 1,
+            Some(count) => *count == 1 && !ambiguous.contains(reference),
+            None => false,
+        }
+    }
+}
+
+/// Bare file names given references hold more than once, telling
+/// several images apart by their folder in another column, if at all.
+pub fn ambiguous_names<'a>(references: impl IntoIterator<Item = &'a str>) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut ambiguous = HashSet::new();
+    for reference in references {
+        let reference = reference.trim_start_matches("./");
+        if !reference.contains('/') && !seen.insert(reference) {
+            ambiguous.insert(reference);
+        }
+    }
+
+    ambiguous
+}
+
+/// Given absolute path without `.` and `..` components, without
+/// resolving symbolic links as removed files may be gone.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Candidate YOLO label files of given image: a `.txt` file of the same
+/// stem next to it, and in the `labels` folder mirroring its `images` one
+/// (`images/train/a.jpg` is labelled by `labels/train/a.txt`).
+fn yolo_labels(image: &str) -> Vec<PathBuf> {
+    let image = paths::decode(image);
+    let mut labels = vec![image.with_extension("txt")];
+
+    let components: Vec<Component> = image.components().collect();
+    if let Some(index) = components
+        .iter()
+        .rposition(|component| component.as_os_str() == "images")
+    {
+        let mut label: PathBuf = components[..index].iter().collect();
+        label.push("labels");
+        label.extend(&components[index + 1..]);
+        labels.push(label.with_extension("txt"));
+    }
+
+    labels
+}
+
+/// Delete given file, or move it to the system trash if asked.
+fn delete(path: &Path, trash: bool) -> io::Result<()> {
+    if !trash {
+        return fs::remove_file(path);
+    }
+
+    // Trash reports missing files as any other failure
+    if !path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} doesn't exist", path.display()),
+        ));
+    }
+    trash::delete(path).map_err(io::Error::other)
+}
+
+/// Delete given images, and their YOLO label files and captions if asked.
+/// Files go to the system trash instead with `trash`, and deletions
+/// are recorded to given journal. Return every deleted file.
+pub fn remove_files(
+    images: &[String],
+    with_labels: bool,
+    with_captions: bool,
+    trash: bool,
+    journal: &Journal,
+) -> io::Result<Vec<String>> {
+    let action = match trash {
+        true => Action::Trash,
+        false => Action::Delete,
+    };
+    let mut deleted = Vec::new();
+
+    for image in images {
+        delete(&paths::decode(image), trash)?;
+        journal.record(action, image, None)?;
+        deleted.push(image.clone());
+
+        let mut companions = Vec::new();
+        if with_labels {
+            companions.extend(yolo_labels(image));
+        }
+        if with_captions {
+            companions.push(captions::caption_path(image));
+        }
+
+        for companion in companions {
+            // Both label layouts are tried, only one of them is used
+            match delete(&companion, trash) {
+                Ok(()) => {
+                    let companion = paths::encode(&companion);
+                    journal.record(action, &companion, None)?;
+                    deleted.push(companion);
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Drop rows of given CSV table whose `column` points at a removed file,
+/// and write the result to output. Return the number of dropped rows.
+pub fn sync_csv(table: &str, removed: &[String], column: &str, output: &str) -> io::Result<usize> {
+    let removed = Removed::new(removed, table)?;
+    let mut reader = csv::Reader::from_path(table)?;
+    let mut writer = csv::Writer::from_path(output)?;
+
+    let headers = reader.headers()?.clone();
+    let index = headers
+        .iter()
+        .position(|header| header == column)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("table has no column '{}'", column),
+            )
+        })?;
+    writer.write_record(&headers)?;
+
+    let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+    let ambiguous = ambiguous_names(records.iter().map(|record| record.get(index).unwrap_or("")));
+
+    let mut dropped = 0;
+    for record in &records {
+        if removed.contains(record.get(index).unwrap_or(""), &ambiguous) {
+            dropped += 1;
+        } else {
+            writer.write_record(record)?;
+        }
+    }
+    writer.flush()?;
+
+    Ok(dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::scratch;
+
+    fn removed_files(paths: &[&str]) -> Removed {
+        let paths: Vec<String> = paths.iter().map(|path| path.to_string()).collect();
+        Removed::new(&paths, "/nowhere/labels/train.csv").unwrap()
+    }
+
+    #[test]
+    fn references_resolve_from_the_label_file() {
+        let removed = removed_files(&["/nowhere/labels/images/a.jpg"]);
+        let none = HashSet::new();
+
+        assert!(removed.contains("images/a.jpg", &none));
+        assert!(removed.contains("./images/a.jpg", &none));
+        assert!(removed.contains("images/../images/a.jpg", &none));
+        assert!(!removed.contains("images/b.jpg", &none));
+        assert!(!removed.contains("", &none));
+    }
+
+    #[test]
+    fn path_suffixes_match_a_single_removed_file() {
+        let removed = removed_files(&["/data/1/images/a.jpg", "/data/1/images/b.jpg"]);
+        assert!(removed.contains("images/a.jpg", &HashSet::new()));
+        assert!(removed.contains("1/images/b.jpg", &HashSet::new()));
+        assert!(!removed.contains("other/a.jpg", &HashSet::new()));
+
+        let removed = removed_files(&["/data/1/images/a.jpg", "/data/2/images/a.jpg"]);
+        assert!(!removed.contains("images/a.jpg", &HashSet::new()));
+        assert!(removed.contains("2/images/a.jpg", &HashSet::new()));
+    }
+
+    #[test]
+    fn file_names_match_when_unambiguous() {
+        let removed = removed_files(&["/data/1/a.jpg", "/data/1/b.jpg", "/data/2/b.jpg"]);
+        assert!(removed.contains("a.jpg", &HashSet::new()));
+        // Two removed files have this name
+        assert!(!removed.contains("b.jpg", &HashSet::new()));
+
+        // The label file names two images this way
+        let ambiguous = ambiguous_names(vec!["a.jpg", "./a.jpg", "c/a.jpg", "b.jpg"]);
+        assert_eq!(ambiguous, HashSet::from(["a.jpg"]));
+        assert!(!removed.contains("a.jpg", &ambiguous));
+    }
+
+    #[test]
+    fn kept_files_are_not_matched_by_suffix() {
+        let folder = scratch("labels");
+        fs::create_dir(folder.join("images")).unwrap();
+        fs::write(folder.join("images/a.jpg"), b"").unwrap();
+        fs::write(folder.join("b.jpg"), b"").unwrap();
+
+        let removed = Removed::new(
+            &["/data/images/a.jpg".to_string(), "/data/b.jpg".to_string()],
+            folder.join("train.csv").to_str().unwrap(),
+        )
+        .unwrap();
+        assert!(!removed.contains("images/a.jpg", &HashSet::new()));
+        assert!(!removed.contains("b.jpg", &HashSet::new()));
+        // Nothing next to the label file goes by this name
+        assert!(removed.contains("a.jpg", &HashSet::new()));
+
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
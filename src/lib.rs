@@ -1,244 +1,903 @@
-use image::{io::Reader as ImageReader, DynamicImage};
-use indicatif::ProgressBar;
+use log::debug;
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
-use pyo3::types::PyList;
+use pyo3::types::{PyDict, PyList};
 use pyo3::wrap_pyfunction;
 use rayon::prelude::*;
+
+mod animation;
+mod archive;
+mod budget;
+mod cache;
+mod captions;
+mod checkpoint;
+mod checks;
+mod classes;
+mod cleaner;
+mod cloud;
+mod coco;
+mod config;
+mod config_file;
+mod dicom;
+mod duplicates;
+mod exif;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod gpu;
+mod hash;
+mod hash_db;
+mod hash_index;
+mod hash_manifest;
+mod header;
+mod heic;
+mod http;
+mod image_stats;
+mod index;
+mod integrity;
+mod journal;
+mod labels;
+mod manifest;
+mod model;
+mod normalization;
+mod outliers;
+mod output;
+mod pages;
+mod paths;
+mod patterns;
+mod profile;
+mod progress;
+mod raw;
+mod report;
+mod review;
+mod sampling;
+mod scanner;
+mod stats;
+mod stream;
+mod summary;
+mod tags;
+#[cfg(test)]
+mod testing;
+mod throttle;
+mod timings;
+mod video;
+mod walk;
+mod webdataset;
+mod webp;
+
+use cleaner::Cleaner;
+use config::{Config, SortKey};
+use duplicates::Cluster;
+use hash_db::HashDb;
+use hash_index::HashIndex;
+use hash_manifest::HashManifest;
+use image_stats::ImageStats;
+use index::CleanaxIndex;
+use journal::Journal;
+use normalization::Normalization;
+use paths::{FsPath, Roots};
+use patterns::Patterns;
+use report::{Reason, Report};
+use review::{Decision, Decisions};
+use sampling::Audit;
+use scanner::Scanner;
+use std::collections::BTreeSet;
+use std::fs;
 use std::io;
-use std::{fs::read_dir, iter::Sum};
-
-#[derive(Debug)]
-struct Pixel {
-    r: u32,
-    g: u32,
-    b: u32,
-    a: u32,
-}
-
-impl Sum for Pixel {
-    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-        let mut sum: Pixel = Pixel {
-            r: 0,
-            g: 0,
-            b: 0,
-            a: 0,
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+use stream::ReportStream;
+use summary::{ScanSummary, Summary};
+
+/// Reports waiting to be read by python before the scan pauses.
+const STREAM_CAPACITY: usize = 1024;
+
+/// Build scanner configured by keyword arguments and run it, returning
+/// reports along with a summary of the scan.
+/// The GIL is released meanwhile, scanning threads take it to log.
+fn run_with(
+    py: Python,
+    kwargs: Option<&PyDict>,
+    scan: impl FnOnce(&Scanner) -> io::Result<Vec<Report>> + Send,
+) -> PyResult<(Vec<Report>, Summary)> {
+    let config = Config::from_kwargs(kwargs)?;
+    let scanner = Scanner::new(config.clone())?;
+    let (reports, _, summary) = py.allow_threads(|| run_scanner(scanner, &config, scan))?;
+    Ok((reports, summary))
+}
+
+/// Run given scan, writing the cache, a profile of reports and a summary
+/// of the scan if asked. The checkpoint of a scan that went through is
+/// removed. Return reports along with clusters of duplicates, if looked for,
+/// and a summary of the scan.
+fn run_scanner(
+    scanner: Scanner,
+    config: &Config,
+    scan: impl FnOnce(&Scanner) -> io::Result<Vec<Report>>,
+) -> io::Result<(Vec<Report>, Vec<Cluster>, Summary)> {
+    let started = Instant::now();
+    let bytes_read = scanner.bytes_read();
+    let cached = scanner.cache_hits();
+    let timings = scanner.timings();
+    let mut reports = scan(&scanner)?;
+    debug!("checked {} files", reports.len());
+
+    let (clusters, hash_db) = judge(&mut reports, config)?;
+
+    // An aborted scan leaves the cache and checkpoint as they were
+    let flagged = reports.iter().filter(|report| report.is_flagged()).count();
+    config.check_flagged(flagged, Some(reports.len()))?;
+    scanner.finish()?;
+
+    // Only once verdicts are final, and the scan wasn't aborted
+    if let Some(mut hash_db) = hash_db {
+        hash_db.update(&reports);
+        hash_db.save()?;
+    }
+    scanner.rename_fixed(&mut reports)?;
+
+    if let Some(profile) = &config.profile {
+        profile::write_profile(profile, &reports)?;
+    }
+
+    if let Some(target) = config.tag {
+        tags::write_tags(&reports, target)?;
+    }
+
+    if let Some(path) = &config.hash_manifest {
+        let manifest = match &config.copy_clean_to {
+            Some(output) => HashManifest::of_folder(output, path)?,
+            None => HashManifest::of_kept(&reports)?,
         };
-        for pixel in iter {
-            sum.r += pixel.r;
-            sum.g += pixel.g;
-            sum.b += pixel.b;
-            sum.a += pixel.a;
-        }
+        manifest.write(path)?;
+    }
 
-        sum
+    let summary = Summary::new(
+        &reports,
+        bytes_read.load(Ordering::Relaxed),
+        cached.load(Ordering::Relaxed),
+        started.elapsed(),
+        timings.as_deref(),
+    );
+    if let Some(path) = &config.run_summary {
+        summary::write_atomic(path, &summary.to_json().to_string())?;
+    }
+    if let Some(path) = &config.prometheus_textfile {
+        summary::write_atomic(path, &summary.to_prometheus())?;
     }
+
+    Ok((reports, clusters, summary))
 }
 
-impl Pixel {
-    fn div(&self, rhs: u32) -> Self {
-        Pixel {
-            r: self.r / rhs,
-            g: self.g / rhs,
-            b: self.b / rhs,
-            a: self.a / rhs,
-        }
+/// Flag files by comparing reports of the whole scan with each other:
+/// duplicates, bursts and outliers, and settle verdicts.
+/// Return clusters of duplicates, if looked for, and the hash database
+/// they were looked for in, for hashes of kept files to be added to it.
+fn judge(reports: &mut [Report], config: &Config) -> io::Result<(Vec<Cluster>, Option<HashDb>)> {
+    // Threads hand reports out in whatever order they checked files,
+    // sorting them also settles which duplicate comes first
+    if config.sort == Some(SortKey::Path) {
+        reports.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    let (clusters, hash_db) = if config.duplicates {
+        find_duplicates(reports, config)?
+    } else if config.hardlinks {
+        let clusters = duplicates::flag_hardlinks(reports, config.keep_duplicates);
+        (clusters, None)
+    } else {
+        (Vec::new(), None)
+    };
+
+    if config.bursts {
+        duplicates::flag_bursts(reports, config);
+    }
+
+    // Images already flagged, duplicates included, don't weigh on the
+    // color distribution
+    if config.outliers {
+        outliers::flag_outliers(reports, config.max_outlier_score);
     }
 
-    fn is_zero(&self, threshold: u32) -> bool {
-        self.r < threshold && self.g < threshold && self.b < threshold && self.a < threshold
+    // Duplicates may not be deleted, as other reasons
+    for report in reports.iter_mut() {
+        config.settle(report);
     }
+
+    Ok((clusters, hash_db))
 }
 
-fn get_image_statistics_with_alpha<T: Sync + num::traits::PrimInt>(
-    image: &Vec<T>,
-) -> (Pixel, Pixel) {
-    let sum: Pixel = image
-        .par_iter()
-        .chunks(3)
-        .map(|pixel| {
-            let r = *pixel[0];
-            let g = *pixel[1];
-            let b = *pixel[2];
-            let a = *pixel[3];
+/// Flag duplicates among reports, and of images scanned before
+/// if a hash database is given, returned along with clusters.
+fn find_duplicates(
+    reports: &mut [Report],
+    config: &Config,
+) -> io::Result<(Vec<Cluster>, Option<HashDb>)> {
+    let hash_db = match &config.hash_db {
+        Some(path) => Some(HashDb::open(path)?),
+        None => None,
+    };
+
+    let known = match &hash_db {
+        Some(hash_db) => hash_db.known(reports),
+        None => Vec::new(),
+    };
+    let clusters = duplicates::flag_duplicates(
+        reports,
+        &known,
+        config.duplicate_distance,
+        config.duplicate_match,
+        config.keep_duplicates,
+    );
+
+    Ok((clusters, hash_db))
+}
 
-            Pixel {
-                r: r.to_u32().unwrap(),
-                g: g.to_u32().unwrap(),
-                b: b.to_u32().unwrap(),
-                a: a.to_u32().unwrap(),
-            }
-        })
-        .sum();
-
-    let mean = sum.div(image.len() as u32 / 4);
-
-    let variance: Pixel = image
-        .par_iter()
-        .chunks(3)
-        .map(|pixel| {
-            let r = *pixel[0];
-            let g = *pixel[1];
-            let b = *pixel[2];
-            let a = *pixel[3];
-
-            Pixel {
-                r: r.to_u32().unwrap() - mean.r,
-                g: g.to_u32().unwrap() - mean.g,
-                b: b.to_u32().unwrap() - mean.b,
-                a: a.to_u32().unwrap() - mean.a,
-            }
-        })
-        .sum();
+/// Run checks configured by keyword arguments over root folder
+/// in the background, streaming reports as files get checked.
+fn stream(
+    root_folders: Vec<String>,
+    kwargs: Option<&PyDict>,
+    flagged_only: bool,
+) -> PyResult<ReportStream> {
+    let config = Config::from_kwargs(kwargs)?;
+    if config.duplicates || config.hardlinks || config.bursts || config.outliers {
+        return Err(PyValueError::new_err(
+            "duplicates, bursts and outliers are only found once every file was checked, \
+             use clean or scan",
+        ));
+    }
+    let mut scanner = Scanner::new(config.clone())?;
 
-    let variance = variance.div(image.len() as u32 / 4);
+    // Bounded, so that reports don't pile up if python reads them slowly
+    let (sender, receiver) = mpsc::sync_channel(STREAM_CAPACITY);
+    scanner.stream_to(sender);
 
-    (mean, variance)
+    let scan = thread::spawn(move || {
+        run_scanner(scanner, &config, |scanner| scanner.scan(&root_folders)).map(|_| ())
+    });
+
+    Ok(ReportStream::new(receiver, scan, flagged_only))
 }
 
-/// Compute mean and standard deviation from colors of given image.
-fn get_image_statistics<T: Sync + num::traits::PrimInt>(image: &Vec<T>) -> (Pixel, Pixel) {
-    let sum: Pixel = image
-        .par_iter()
-        .chunks(3)
-        .map(|pixel| {
-            let r = *pixel[0];
-            let g = *pixel[1];
-            let b = *pixel[2];
-            let a = T::zero();
+/// Run checks configured by keyword arguments over root folders,
+/// also returning a summary of the scan.
+fn run_summarized(
+    py: Python,
+    root_folders: &[String],
+    kwargs: Option<&PyDict>,
+) -> PyResult<(Vec<Report>, Summary)> {
+    run_with(py, kwargs, |scanner| scanner.scan(root_folders))
+}
 
-            Pixel {
-                r: r.to_u32().unwrap(),
-                g: g.to_u32().unwrap(),
-                b: b.to_u32().unwrap(),
-                a: a.to_u32().unwrap(),
-            }
-        })
-        .sum();
-
-    let mean = sum.div(image.len() as u32 / 3);
-
-    let variance: Pixel = image
-        .par_iter()
-        .chunks(3)
-        .map(|pixel| {
-            let r = *pixel[0];
-            let g = *pixel[1];
-            let b = *pixel[2];
-            let a = T::zero();
-
-            Pixel {
-                r: (r.to_u32().unwrap().checked_sub(mean.r).unwrap_or(0)).pow(2),
-                g: (g.to_u32().unwrap().checked_sub(mean.g).unwrap_or(0)).pow(2),
-                b: (b.to_u32().unwrap().checked_sub(mean.b).unwrap_or(0)).pow(2),
-                a: (a.to_u32().unwrap().checked_sub(mean.a).unwrap_or(0)).pow(2),
-            }
-        })
-        .sum();
+/// Run checks configured by keyword arguments over root folders.
+fn run(py: Python, root_folders: &[String], kwargs: Option<&PyDict>) -> PyResult<Vec<Report>> {
+    Ok(run_summarized(py, root_folders, kwargs)?.0)
+}
+
+/// Run checks configured by keyword arguments over downloaded URLs.
+fn run_urls(py: Python, urls: &[String], kwargs: Option<&PyDict>) -> PyResult<Vec<Report>> {
+    Ok(run_with(py, kwargs, |scanner| scanner.scan_urls(urls))?.0)
+}
 
-    let variance = variance.div(image.len() as u32 / 3);
+/// Run checks configured by keyword arguments over files listed in a manifest.
+fn run_manifest(py: Python, manifest: &str, kwargs: Option<&PyDict>) -> PyResult<Vec<Report>> {
+    Ok(run_with(py, kwargs, |scanner| scanner.scan_manifest(manifest))?.0)
+}
 
-    (mean, variance)
+/// Paths of flagged reports.
+fn flagged(reports: Vec<Report>) -> Vec<String> {
+    reports
+        .into_iter()
+        .filter(|report| report.is_flagged())
+        .map(|report| report.path)
+        .collect()
 }
 
-/// Check if given image has more than `quantity` percent of given color.
-/// Return true if specified color proportion is greater than `quantity`.
-/// A the time being, only RGB and RGBA images are supported. That means
-/// an image with another channel format will be marked as deleted.
-fn check_solid_color(image: &DynamicImage) -> bool {
-    // Extract pixels
-    match image {
-        DynamicImage::ImageRgb8(pixels) => {
-            let buffer = pixels.as_raw();
-            let q = get_image_statistics(buffer);
+/// Convert reports to a python list of dictionaries.
+fn to_list<'py>(py: Python<'py>, reports: Vec<Report>) -> PyResult<&'py PyList> {
+    let list = PyList::empty(py);
+
+    for report in reports {
+        list.append(report.to_dict(py)?)?;
+    }
+
+    Ok(list)
+}
+
+/// Select images to delete from root folder.
+/// And return deleted images, or a pandas DataFrame holding a row
+/// for every image and a column for every metric if asked, or a dictionary
+/// of numpy arrays holding the same columns. With `with_summary`, a
+/// `ScanSummary` of the scan comes along, as `(result, summary)`.
+#[pyfunction(
+    root_folder,
+    "*",
+    as_dataframe = "false",
+    as_arrays = "false",
+    with_summary = "false",
+    kwargs = "**"
+)]
+fn clean(
+    py: Python,
+    root_folder: Roots,
+    as_dataframe: bool,
+    as_arrays: bool,
+    with_summary: bool,
+    kwargs: Option<&PyDict>,
+) -> PyResult<PyObject> {
+    Cleaner::from_kwargs(py, kwargs)?.clean(
+        py,
+        root_folder,
+        as_dataframe,
+        as_arrays,
+        with_summary,
+        None,
+    )
+}
+
+/// Select images to delete from root folder.
+/// And yield each of them as soon as it's flagged, while the scan goes on.
+/// Also exported as `clean_async`: await the result for every flagged
+/// image, or iterate over it with `async for`, without blocking the loop.
+#[pyfunction(root_folder, kwargs = "**")]
+fn iter_clean(root_folder: Roots, kwargs: Option<&PyDict>) -> PyResult<ReportStream> {
+    stream(root_folder.0, kwargs, true)
+}
 
-            q.1.is_zero(20)
+/// Select images to delete from root folder.
+/// And return an index of them, to skip them without deleting them.
+#[pyfunction(root_folder, kwargs = "**")]
+fn index(py: Python, root_folder: Roots, kwargs: Option<&PyDict>) -> PyResult<CleanaxIndex> {
+    Ok(CleanaxIndex::from_paths(&flagged(run(
+        py,
+        &root_folder.0,
+        kwargs,
+    )?)))
+}
+
+/// Compute mean and standard deviation of the red, green and blue channels
+/// over every pixel of images of root folder that pass the checks,
+/// on a 0-1 scale as `torchvision.transforms.Normalize` takes them.
+#[pyfunction(root_folder, kwargs = "**")]
+fn normalization<'py>(
+    py: Python<'py>,
+    root_folder: Roots,
+    kwargs: Option<&PyDict>,
+) -> PyResult<&'py PyDict> {
+    let mut config = Config::from_kwargs(kwargs)?;
+    config.normalization = true;
+
+    let scanner = Scanner::new(config.clone())?;
+    let (reports, _, _) =
+        py.allow_threads(|| run_scanner(scanner, &config, |scanner| scanner.scan(&root_folder.0)))?;
+
+    Normalization::of_kept(&reports).to_dict(py)
+}
+
+/// Check files of root folder under two sets of options, `a` and `b`,
+/// overriding keyword arguments, reading each file once. Return the
+/// count of files, of those flagged under each set, and files whose
+/// verdict changes: `"only_a"` flagged under `a` only, `"only_b"` under
+/// `b` only. Nothing is written, not even the cache.
+#[pyfunction(root_folder, a, b, kwargs = "**")]
+fn compare<'py>(
+    py: Python<'py>,
+    root_folder: Roots,
+    a: &PyDict,
+    b: &PyDict,
+    kwargs: Option<&PyDict>,
+) -> PyResult<&'py PyDict> {
+    let config = |options: &PyDict| -> PyResult<Config> {
+        let merged = PyDict::new(py);
+        for source in kwargs.into_iter().chain(Some(options)) {
+            for (key, value) in source {
+                merged.set_item(key, value)?;
+            }
         }
-        DynamicImage::ImageRgba8(pixels) => {
-            let buffer = pixels.as_raw();
-            let q = get_image_statistics_with_alpha(buffer);
 
-            q.1.is_zero(20)
+        let mut config = Config::from_kwargs(Some(merged))?;
+        config.cache = None;
+        config.checkpoint = None;
+        config.journal = None;
+        config.hash_db = None;
+        Ok(config)
+    };
+    let (config_a, config_b) = (config(a)?, config(b)?);
+    let scanner_a = Scanner::new(config_a.clone())?;
+    let scanner_b = Scanner::new(config_b.clone())?;
+
+    let (reports_a, reports_b) = py.allow_threads(|| -> io::Result<_> {
+        let (mut reports_a, mut reports_b): (Vec<Report>, Vec<Report>) = scanner_a
+            .scan_both(&scanner_b, &root_folder.0)?
+            .into_iter()
+            .unzip();
+        judge(&mut reports_a, &config_a)?;
+        judge(&mut reports_b, &config_b)?;
+        Ok((reports_a, reports_b))
+    })?;
+
+    let files = reports_a.len();
+    let flagged_a: BTreeSet<String> = flagged(reports_a).into_iter().collect();
+    let flagged_b: BTreeSet<String> = flagged(reports_b).into_iter().collect();
+    let only = |flagged: &BTreeSet<String>, other: &BTreeSet<String>| -> Vec<FsPath> {
+        flagged.difference(other).cloned().map(FsPath).collect()
+    };
+
+    let dict = PyDict::new(py);
+    dict.set_item("files", files)?;
+    dict.set_item("flagged_a", flagged_a.len())?;
+    dict.set_item("flagged_b", flagged_b.len())?;
+    dict.set_item("only_a", only(&flagged_a, &flagged_b))?;
+    dict.set_item("only_b", only(&flagged_b, &flagged_a))?;
+    Ok(dict)
+}
+
+/// Check a random sample of the files of root folder, picked by `sample`
+/// or `sample_n`, and estimate how many files of the whole folder would
+/// be flagged, and found each reason for, with 95% confidence intervals.
+#[pyfunction(root_folder, kwargs = "**")]
+fn audit<'py>(
+    py: Python<'py>,
+    root_folder: Roots,
+    kwargs: Option<&PyDict>,
+) -> PyResult<&'py PyDict> {
+    let config = Config::from_kwargs(kwargs)?;
+
+    let scanner = Scanner::new(config.clone())?;
+    let listed = scanner.listed();
+    let (reports, _, _) =
+        py.allow_threads(|| run_scanner(scanner, &config, |scanner| scanner.scan(&root_folder.0)))?;
+
+    Audit::new(&reports, listed.load(Ordering::Relaxed)).to_dict(py)
+}
+
+/// Check images of every class of an ImageFolder tree, its subfolders.
+/// Return counts of images of each class before and after cleaning,
+/// flagging classes losing more than `max_class_loss` of their images.
+/// Classes left without images raise a warning.
+#[pyfunction(root_folder, "*", max_class_loss = "0.5", kwargs = "**")]
+fn class_report<'py>(
+    py: Python<'py>,
+    root_folder: FsPath,
+    max_class_loss: f64,
+    kwargs: Option<&PyDict>,
+) -> PyResult<&'py PyDict> {
+    let mut config = Config::from_kwargs(kwargs)?;
+    config.recursive = true;
+
+    let scanner = Scanner::new(config.clone())?;
+    let (reports, _, _) = py.allow_threads(|| {
+        run_scanner(scanner, &config, |scanner| {
+            scanner.scan(std::slice::from_ref(&root_folder.0))
+        })
+    })?;
+
+    let user_warning = py.import("builtins")?.getattr("UserWarning")?;
+    let dict = PyDict::new(py);
+    for (class, counts) in classes::count_classes(&root_folder.0, &reports)? {
+        if counts.is_empty() {
+            let message = format!("class '{}' has no images left", class);
+            PyErr::warn(py, user_warning, &message, 1)?;
         }
-        DynamicImage::ImageRgb16(pixels) => {
-            let buffer = pixels.as_raw();
-            let q = get_image_statistics(buffer);
+        dict.set_item(
+            paths::to_py(py, &class),
+            counts.to_dict(py, max_class_loss)?,
+        )?;
+    }
+
+    Ok(dict)
+}
 
-            q.1.is_zero(20)
+/// Find images of root folder that are copies of each other or look alike.
+/// Return a group of paths for each set of duplicates, the kept one first,
+/// telling whether their content is exactly the same.
+#[pyfunction(root_folder, kwargs = "**")]
+fn duplicates<'py>(
+    py: Python<'py>,
+    root_folder: Roots,
+    kwargs: Option<&PyDict>,
+) -> PyResult<&'py PyList> {
+    let mut config = Config::from_kwargs(kwargs)?;
+    config.duplicates = true;
+
+    let scanner = Scanner::new(config.clone())?;
+    let (_, clusters, _) =
+        py.allow_threads(|| run_scanner(scanner, &config, |scanner| scanner.scan(&root_folder.0)))?;
+
+    let list = PyList::empty(py);
+    for cluster in clusters {
+        list.append(cluster.to_dict(py)?)?;
+    }
+
+    Ok(list)
+}
+
+/// Compute mean and standard deviation of every channel of given image,
+/// on the scale of its values (0-255, or 0-65535 for 16 bits images).
+#[pyfunction]
+fn image_stats<'py>(py: Python<'py>, path: FsPath) -> PyResult<&'py PyDict> {
+    let path = path.0;
+    let data = fs::read(paths::decode(&path))?;
+    let image = py
+        .allow_threads(|| scanner::load_image(&path, &data))
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+    let dict = PyDict::new(py);
+    for (name, moments) in stats::channel_names(&image)
+        .iter()
+        .zip(py.allow_threads(|| stats::get_moments(&image)))
+    {
+        dict.set_item(name, (moments.mean(), moments.variance().sqrt()))?;
+    }
+
+    Ok(dict)
+}
+
+/// Compute statistics of every channel of given image: minimum, maximum,
+/// mean, standard deviation and histogram.
+#[pyfunction]
+fn get_stats(py: Python, path: FsPath) -> PyResult<ImageStats> {
+    let path = path.0;
+    let data = fs::read(paths::decode(&path))?;
+    py.allow_threads(|| {
+        let image = scanner::load_image(&path, &data)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(ImageStats::of(&image))
+    })
+}
+
+/// Run checks over in-memory files, given as an iterable of (key, bytes)
+/// pairs such as a batch of a Hugging Face dataset with its indices.
+/// Return a report for each key, nothing is read from disk.
+#[pyfunction(items, kwargs = "**")]
+fn check_bytes<'py>(
+    py: Python<'py>,
+    items: &PyAny,
+    kwargs: Option<&PyDict>,
+) -> PyResult<&'py PyDict> {
+    let config = Config::from_kwargs(kwargs)?;
+    let scanner = Scanner::new(config.clone())?;
+
+    let mut keys = Vec::new();
+    let mut contents = Vec::new();
+    for item in items.iter()? {
+        let (key, data): (&PyAny, &[u8]) = item?.extract()?;
+        contents.push((key.str()?.to_string(), data.to_vec()));
+        keys.push(key);
+    }
+
+    // Checks don't need the interpreter
+    let reports: Vec<Report> = py.allow_threads(|| {
+        contents
+            .par_iter()
+            .map(|(name, data)| {
+                let mut report = scanner.check_bytes(name, data);
+                config.settle(&mut report);
+                report
+            })
+            .collect()
+    });
+
+    let dict = PyDict::new(py);
+    for (key, report) in keys.into_iter().zip(reports) {
+        dict.set_item(key, report.to_dict(py)?)?;
+    }
+
+    Ok(dict)
+}
+
+/// Run checks over every file of root folder.
+/// Return a report for each file, listing reasons it got flagged for.
+#[pyfunction(root_folder, kwargs = "**")]
+fn scan<'py>(
+    py: Python<'py>,
+    root_folder: Roots,
+    kwargs: Option<&PyDict>,
+) -> PyResult<&'py PyList> {
+    Cleaner::from_kwargs(py, kwargs)?.scan(py, root_folder, None)
+}
+
+/// Run checks over every file of root folder.
+/// Yield a report for each file as soon as it's checked.
+/// Also exported as `scan_async`, to be awaited or iterated over
+/// with `async for`.
+#[pyfunction(root_folder, kwargs = "**")]
+fn iter_scan(root_folder: Roots, kwargs: Option<&PyDict>) -> PyResult<ReportStream> {
+    stream(root_folder.0, kwargs, false)
+}
+
+/// Select images to delete among files listed in a manifest,
+/// one path per line or a CSV column. Pass "-" to read it from stdin.
+#[pyfunction(manifest, kwargs = "**")]
+fn clean_manifest<'py>(
+    py: Python<'py>,
+    manifest: FsPath,
+    kwargs: Option<&PyDict>,
+) -> PyResult<&'py PyList> {
+    let flagged = flagged(run_manifest(py, &manifest.0, kwargs)?);
+    Ok(PyList::new(py, flagged.into_iter().map(FsPath)))
+}
+
+/// Run checks over every file listed in a manifest.
+/// Return a report for each file, missing ones are flagged as "missing".
+#[pyfunction(manifest, kwargs = "**")]
+fn scan_manifest<'py>(
+    py: Python<'py>,
+    manifest: FsPath,
+    kwargs: Option<&PyDict>,
+) -> PyResult<&'py PyList> {
+    to_list(py, run_manifest(py, &manifest.0, kwargs)?)
+}
+
+/// Download every given URL, without keeping them on disk.
+/// And return URLs that would be deleted.
+#[pyfunction(urls, kwargs = "**")]
+fn clean_urls<'py>(
+    py: Python<'py>,
+    urls: Vec<String>,
+    kwargs: Option<&PyDict>,
+) -> PyResult<&'py PyList> {
+    Ok(PyList::new(py, flagged(run_urls(py, &urls, kwargs)?)))
+}
+
+/// Download every given URL and run checks over it.
+/// Return a report for each URL, failed downloads are flagged as "network".
+#[pyfunction(urls, kwargs = "**")]
+fn scan_urls<'py>(
+    py: Python<'py>,
+    urls: Vec<String>,
+    kwargs: Option<&PyDict>,
+) -> PyResult<&'py PyList> {
+    to_list(py, run_urls(py, &urls, kwargs)?)
+}
+
+/// Remove images matching removed files from COCO annotations,
+/// along with their annotations, and write cleaned annotations to output.
+/// Return the number of removed images and annotations.
+#[pyfunction]
+fn sync_coco(
+    annotations: String,
+    removed: Vec<String>,
+    output: String,
+) -> PyResult<(usize, usize)> {
+    Ok(coco::sync_coco(&annotations, &removed, &output)?)
+}
+
+/// Delete given files, and their YOLO label files and `{stem}.txt`
+/// captions if asked. Protected files, given by path or glob pattern, are left alone.
+/// With `use_trash`, files go to the system trash instead.
+/// Deletions are recorded to `journal` if given.
+/// Return every deleted file.
+#[pyfunction(
+    paths,
+    yolo_labels = "false",
+    captions = "false",
+    protect = "Vec::new()",
+    use_trash = "false",
+    journal = "None"
+)]
+fn remove(
+    paths: Vec<FsPath>,
+    yolo_labels: bool,
+    captions: bool,
+    protect: Vec<String>,
+    use_trash: bool,
+    journal: Option<FsPath>,
+) -> PyResult<Vec<FsPath>> {
+    let journal = Journal::open(journal.as_ref().map(|path| path.0.as_str()))?;
+    let protect = Patterns::new("protect", &protect)?;
+    let paths: Vec<String> = paths
+        .into_iter()
+        .map(|path| path.0)
+        .filter(|path| !protect.contains(path))
+        .collect();
+
+    let deleted = labels::remove_files(&paths, yolo_labels, captions, use_trash, &journal)?;
+    Ok(deleted.into_iter().map(FsPath).collect())
+}
+
+/// Step through flagged images of root folder, asking whether to delete
+/// each of them. Decisions are recorded to `decisions`, a JSON file,
+/// which skips files already decided on when reviewing again, and
+/// with `replay`, deletes files recorded as such without scanning nor asking.
+/// `preview` renders images in the terminal, `viewer` opens them with
+/// the system viewer. With `use_trash`, files go to the system trash
+/// instead, and deletions are recorded to `journal` if given. With
+/// `captions`, captions are deleted along with their image.
+/// Return every deleted file.
+#[pyfunction(
+    root_folder,
+    "*",
+    decisions = "None",
+    replay = "false",
+    preview = "false",
+    viewer = "false",
+    preview_width = "60",
+    yolo_labels = "false",
+    use_trash = "false",
+    kwargs = "**"
+)]
+#[allow(clippy::too_many_arguments)]
+fn review(
+    py: Python,
+    root_folder: Roots,
+    decisions: Option<FsPath>,
+    replay: bool,
+    preview: bool,
+    viewer: bool,
+    preview_width: u32,
+    yolo_labels: bool,
+    use_trash: bool,
+    kwargs: Option<&PyDict>,
+) -> PyResult<Vec<FsPath>> {
+    let config = Config::from_kwargs(kwargs)?;
+    let journal = Journal::open(config.journal.as_deref())?;
+    let mut decisions = Decisions::load(decisions.map(|path| path.0))?;
+    if replay {
+        let deleted = labels::remove_files(
+            &decisions.deleted(),
+            yolo_labels,
+            config.captions,
+            use_trash,
+            &journal,
+        )?;
+        return Ok(deleted.into_iter().map(FsPath).collect());
+    }
+
+    let builtins = py.import("builtins")?;
+    let print = |text: String| builtins.call1("print", (text,)).map(|_| ());
+
+    let reports: Vec<Report> = run(py, &root_folder.0, kwargs)?
+        .into_iter()
+        .filter(|report| report.is_flagged() && decisions.get(&report.path).is_none())
+        .collect();
+
+    let mut deleted = Vec::new();
+    for (i, report) in reports.iter().enumerate() {
+        let reasons: Vec<_> = report
+            .reasons
+            .iter()
+            .map(|reason| reason.as_str())
+            .collect();
+        print(format!(
+            "[{}/{}] {}: {}",
+            i + 1,
+            reports.len(),
+            report.path,
+            reasons.join(", ")
+        ))?;
+
+        if preview {
+            if let Some(text) = review::preview(&report.path, preview_width) {
+                print(text)?;
+            }
+        }
+        if viewer {
+            review::open_viewer(&report.path)?;
         }
-        DynamicImage::ImageRgba16(pixels) => {
-            let buffer = pixels.as_raw();
-            let q = get_image_statistics_with_alpha(buffer);
 
-            q.1.is_zero(20)
+        let decision = loop {
+            let answer: String = builtins
+                .call1("input", ("delete? [y]es, [n]o, [q]uit: ",))?
+                .extract()?;
+            match answer.trim().to_lowercase().as_str() {
+                "y" | "yes" => break Some(Decision::Delete),
+                "n" | "no" => break Some(Decision::Keep),
+                "q" | "quit" => break None,
+                _ => continue,
+            }
+        };
+
+        match decision {
+            Some(decision) => decisions.set(&report.path, decision)?,
+            None => break,
         }
-        DynamicImage::ImageBgr8(_) => false,
-        DynamicImage::ImageBgra8(_) => false,
-        _ => {
-            println!("Unsupported image format!");
-            true
+        if decision == Some(Decision::Delete) {
+            deleted.extend(labels::remove_files(
+                std::slice::from_ref(&report.path),
+                yolo_labels,
+                config.captions,
+                use_trash,
+                &journal,
+            )?);
         }
     }
+
+    Ok(deleted.into_iter().map(FsPath).collect())
 }
 
-/// Load image from disk.
-fn load_image(path: &str) -> Option<DynamicImage> {
-    let img = ImageReader::open(path);
+/// Undo actions recorded to given journal: renamed files get their name
+/// back, trashed ones are restored from the system trash where it can be
+/// listed. Return restored files, and files that can't be restored,
+/// deleted ones among them, which are left in the journal.
+#[pyfunction]
+fn undo(journal: FsPath) -> PyResult<(Vec<FsPath>, Vec<FsPath>)> {
+    let (restored, lost) = journal::undo(&journal.0)?;
+    Ok((
+        restored.into_iter().map(FsPath).collect(),
+        lost.into_iter().map(FsPath).collect(),
+    ))
+}
 
-    match img {
-        Ok(buffer) => match buffer.decode() {
-            Ok(dynamic_image) => Some(dynamic_image),
-            Err(_) => None,
-        },
-        Err(_) => None,
+/// Hash files of the dataset given hash manifest was written for again,
+/// and return files `"added"` to its folder, `"deleted"` and `"modified"`
+/// since, bit rot included.
+#[pyfunction]
+fn verify_manifest<'py>(py: Python<'py>, manifest: FsPath) -> PyResult<&'py PyDict> {
+    let changes = py.allow_threads(|| HashManifest::read(&manifest.0)?.verify(&manifest.0))?;
+
+    let dict = PyDict::new(py);
+    for (key, files) in [
+        ("added", changes.added),
+        ("deleted", changes.deleted),
+        ("modified", changes.modified),
+    ] {
+        dict.set_item(key, files.into_iter().map(FsPath).collect::<Vec<_>>())?;
     }
+    Ok(dict)
 }
 
-/// Select images to delete from root folder.
-/// And return deleted images.
+/// Drop rows of a CSV table whose column points at a removed file,
+/// and write the cleaned table to output. Return the number of dropped rows.
 #[pyfunction]
-fn clean(py: Python, root_folder: String) -> PyResult<&PyList> {
-    // List files in root folder
-    let images = read_dir(root_folder)
-        .expect("Couldn't read dir.")
-        .map(|res| res.map(|e| e.path()))
-        .collect::<Result<Vec<_>, io::Error>>()?;
-
-    let pb = ProgressBar::new(images.len() as u64);
-
-    let to_delete: Vec<String> = images
-        .par_iter()
-        .filter_map(|img| {
-            let image = load_image(img.to_str().unwrap());
-
-            pb.inc(1);
-
-            match image {
-                // If file fails to be loaded as an image
-                // Delete it (return its name)
-                None => Some(img.to_str().unwrap().to_string()),
-                // Otherwise pursue other checks
-                Some(image) => {
-                    // Take most present color and standard deviation
-                    // A very low standard deviation means a solid color image
-
-                    if check_solid_color(&image) {
-                        Some(img.to_str().unwrap().to_string())
-                    } else {
-                        None
-                    }
-                }
-            }
-        })
-        .collect();
-
-    Ok(PyList::new(py, to_delete))
+fn sync_csv(
+    table: String,
+    removed: Vec<String>,
+    column: String,
+    output: String,
+) -> PyResult<usize> {
+    Ok(labels::sync_csv(&table, &removed, &column, &output)?)
 }
 
 #[pymodule]
 fn cleanax(_py: Python, m: &PyModule) -> PyResult<()> {
-    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    // Records go to python's logging module, under the "cleanax" logger
+    pyo3_log::init();
 
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add(
+        "REASONS",
+        Reason::ALL
+            .iter()
+            .map(|reason| reason.as_str())
+            .collect::<Vec<_>>(),
+    )?;
+
+    m.add_class::<Cleaner>()?;
+    m.add_class::<CleanaxIndex>()?;
+    m.add_class::<ReportStream>()?;
+    m.add_class::<HashIndex>()?;
+    m.add_class::<ImageStats>()?;
+    m.add_class::<ScanSummary>()?;
+
+    m.add_wrapped(wrap_pyfunction!(check_bytes))?;
+    m.add_wrapped(wrap_pyfunction!(image_stats))?;
+    m.add_wrapped(wrap_pyfunction!(get_stats))?;
     m.add_wrapped(wrap_pyfunction!(clean))?;
+    m.add_wrapped(wrap_pyfunction!(index))?;
+    m.add_wrapped(wrap_pyfunction!(duplicates))?;
+    m.add_wrapped(wrap_pyfunction!(normalization))?;
+    m.add_wrapped(wrap_pyfunction!(audit))?;
+    m.add_wrapped(wrap_pyfunction!(compare))?;
+    m.add_wrapped(wrap_pyfunction!(class_report))?;
+    m.add_wrapped(wrap_pyfunction!(scan))?;
+    m.add_wrapped(wrap_pyfunction!(iter_clean))?;
+    m.add_wrapped(wrap_pyfunction!(iter_scan))?;
+    // Streams are iterated over and awaited alike
+    m.add("clean_async", m.getattr("iter_clean")?)?;
+    m.add("scan_async", m.getattr("iter_scan")?)?;
+    m.add_wrapped(wrap_pyfunction!(clean_manifest))?;
+    m.add_wrapped(wrap_pyfunction!(scan_manifest))?;
+    m.add_wrapped(wrap_pyfunction!(clean_urls))?;
+    m.add_wrapped(wrap_pyfunction!(scan_urls))?;
+    m.add_wrapped(wrap_pyfunction!(remove))?;
+    m.add_wrapped(wrap_pyfunction!(review))?;
+    m.add_wrapped(wrap_pyfunction!(undo))?;
+    m.add_wrapped(wrap_pyfunction!(verify_manifest))?;
+    m.add_wrapped(wrap_pyfunction!(sync_coco))?;
+    m.add_wrapped(wrap_pyfunction!(sync_csv))?;
 
     Ok(())
 }
@@ -0,0 +1,69 @@
+use crate::paths;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Read paths listed in given manifest, "-" reading it from stdin.
+/// A manifest holds one path per line, or a CSV table whose `column`
+/// holds paths. Relative paths are relative to the manifest folder.
+pub fn read_manifest(manifest: &str, column: Option<&str>) -> io::Result<Vec<String>> {
+    let manifest_path = paths::decode(manifest);
+    let (reader, folder): (Box<dyn Read>, &Path) = if manifest == "-" {
+        (Box::new(io::stdin()), Path::new(""))
+    } else {
+        (
+            Box::new(File::open(&manifest_path)?),
+            manifest_path.parent().unwrap_or_else(|| Path::new("")),
+        )
+    };
+
+    let paths = match column {
+        Some(column) => read_column(reader, column)?,
+        None => read_lines(reader)?,
+    };
+
+    Ok(paths
+        .iter()
+        .map(|path| paths::encode(&folder.join(path)))
+        .collect())
+}
+
+fn read_lines(reader: impl Read) -> io::Result<Vec<String>> {
+    let mut paths = Vec::new();
+
+    // Paths may not be valid UTF-8, as file names copied from other systems
+    for line in BufReader::new(reader).split(b'\n') {
+        let line = line?;
+        let line = line.trim_ascii();
+        if !line.is_empty() {
+            paths.push(paths::from_bytes(line));
+        }
+    }
+
+    Ok(paths)
+}
+
+fn read_column(reader: impl Read, column: &str) -> io::Result<Vec<String>> {
+    let mut reader = csv::Reader::from_reader(reader);
+
+    let index = reader
+        .byte_headers()?
+        .iter()
+        .position(|header| header == column.as_bytes())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("manifest has no column '{}'", column),
+            )
+        })?;
+
+    let mut paths = Vec::new();
+    for record in reader.byte_records() {
+        match record?.get(index) {
+            Some(path) if !path.is_empty() => paths.push(paths::from_bytes(path)),
+            _ => {}
+        }
+    }
+
+    Ok(paths)
+}
@@ -0,0 +1,209 @@
+use crate::config::Config;
+use image::DynamicImage;
+use std::io;
+
+/// Input of the model for an image: its pixels resized to the input size
+/// of the model and normalized, in the layout the model expects.
+#[cfg(feature = "onnx")]
+fn input(image: &DynamicImage, config: &Config) -> Vec<f32> {
+    use crate::config::ModelLayout;
+    use image::imageops::FilterType;
+
+    let size = config.model_input_size;
+    let rgb = image
+        .resize_exact(size, size, FilterType::Triangle)
+        .to_rgb8();
+    let value = |pixel: &[u8], channel: usize| {
+        (pixel[channel] as f32 / 255.0 - config.model_mean[channel]) / config.model_std[channel]
+    };
+
+    match config.model_layout {
+        ModelLayout::Nchw => (0..3)
+            .flat_map(|channel| {
+                rgb.as_raw()
+                    .chunks_exact(3)
+                    .map(move |pixel| value(pixel, channel))
+            })
+            .collect(),
+        ModelLayout::Nhwc => rgb
+            .as_raw()
+            .chunks_exact(3)
+            .flat_map(|pixel| (0..3).map(move |channel| value(pixel, channel)))
+            .collect(),
+    }
+}
+
+/// Image waiting for its score.
+#[cfg(feature = "onnx")]
+struct Job {
+    input: Vec<f32>,
+    result: std::sync::mpsc::SyncSender<Result<f32, String>>,
+}
+
+/// A user supplied ONNX classifier, scoring images on whatever it was
+/// trained for (NSFW, aesthetics, documents, ...). Images sent by scanning
+/// threads are gathered in batches by a thread owning the session, and
+/// run through the model together.
+#[cfg(feature = "onnx")]
+pub struct Model {
+    config: Config,
+    jobs: std::sync::mpsc::Sender<Job>,
+}
+
+#[cfg(feature = "onnx")]
+impl Model {
+    /// Load the model of given options, along with ONNX Runtime: the library
+    /// `model_runtime` names, `ORT_DYLIB_PATH` or the one of the system.
+    pub fn open(config: &Config) -> io::Result<Self> {
+        use ort::session::Session;
+
+        let path = config.model.as_deref().unwrap_or_default();
+        let runtime = match &config.model_runtime {
+            Some(runtime) => runtime.clone(),
+            None => std::env::var("ORT_DYLIB_PATH").unwrap_or_else(|_| runtime_name().to_string()),
+        };
+        ort::init_from(runtime)
+            .map_err(io::Error::other)?
+            .with_name("cleanax")
+            .commit();
+
+        let session = Session::builder()
+            .and_then(|mut builder| builder.commit_from_file(path))
+            .map_err(|err| io::Error::other(format!("can't load model {}: {}", path, err)))?;
+
+        let (jobs, receiver) = std::sync::mpsc::channel();
+        let server = config.clone();
+        std::thread::spawn(move || serve(session, &server, receiver));
+
+        Ok(Model {
+            config: config.clone(),
+            jobs,
+        })
+    }
+
+    /// Score of given image for the class `model_output` stands for.
+    pub fn score(&self, image: &DynamicImage) -> io::Result<f32> {
+        let (result, receiver) = std::sync::mpsc::sync_channel(1);
+        let job = Job {
+            input: input(image, &self.config),
+            result,
+        };
+
+        self.jobs
+            .send(job)
+            .map_err(|_| io::Error::other("model stopped"))?;
+        receiver
+            .recv()
+            .map_err(|_| io::Error::other("model stopped"))?
+            .map_err(io::Error::other)
+    }
+}
+
+/// Name ONNX Runtime goes by on this platform.
+#[cfg(feature = "onnx")]
+fn runtime_name() -> &'static str {
+    if cfg!(windows) {
+        "onnxruntime.dll"
+    } else if cfg!(target_os = "macos") {
+        "libonnxruntime.dylib"
+    } else {
+        "libonnxruntime.so"
+    }
+}
+
+/// Run jobs until every scanner using the model is gone. Jobs waiting
+/// together are gathered, up to `model_batch_size` of them.
+#[cfg(feature = "onnx")]
+fn serve(
+    mut session: ort::session::Session,
+    config: &Config,
+    jobs: std::sync::mpsc::Receiver<Job>,
+) {
+    while let Ok(first) = jobs.recv() {
+        let mut batch = vec![first];
+        while batch.len() < config.model_batch_size {
+            match jobs.try_recv() {
+                Ok(job) => batch.push(job),
+                Err(_) => break,
+            }
+        }
+
+        match run(&mut session, config, &batch) {
+            Ok(scores) => {
+                for (job, score) in batch.iter().zip(scores) {
+                    // The scanning thread may be gone
+                    job.result.send(Ok(score)).ok();
+                }
+            }
+            Err(err) => {
+                for job in &batch {
+                    job.result.send(Err(err.clone())).ok();
+                }
+            }
+        }
+    }
+}
+
+/// Scores of a batch of images, the output of the model holding
+/// a row of scores for each of them.
+#[cfg(feature = "onnx")]
+fn run(
+    session: &mut ort::session::Session,
+    config: &Config,
+    batch: &[Job],
+) -> Result<Vec<f32>, String> {
+    use crate::config::ModelLayout;
+    use ort::value::Tensor;
+
+    let size = config.model_input_size as i64;
+    let shape = match config.model_layout {
+        ModelLayout::Nchw => [batch.len() as i64, 3, size, size],
+        ModelLayout::Nhwc => [batch.len() as i64, size, size, 3],
+    };
+    let input: Vec<f32> = batch
+        .iter()
+        .flat_map(|job| job.input.iter().copied())
+        .collect();
+
+    let tensor = Tensor::from_array((shape, input)).map_err(|err| err.to_string())?;
+    let outputs = session
+        .run(ort::inputs![tensor])
+        .map_err(|err| err.to_string())?;
+    let (_, scores) = outputs[0]
+        .try_extract_tensor::<f32>()
+        .map_err(|err| err.to_string())?;
+
+    let classes = scores.len() / batch.len();
+    if classes * batch.len() != scores.len() || config.model_output >= classes {
+        return Err(format!(
+            "model gives {} scores per image, no score {} among them",
+            classes, config.model_output
+        ));
+    }
+
+    Ok(scores
+        .chunks_exact(classes)
+        .map(|row| row[config.model_output])
+        .collect())
+}
+
+/// ONNX support isn't compiled in, tell so when a model is given.
+#[cfg(not(feature = "onnx"))]
+pub struct Model;
+
+#[cfg(not(feature = "onnx"))]
+impl Model {
+    pub fn open(_config: &Config) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "can't run a model, cleanax was built without the onnx feature",
+        ))
+    }
+
+    pub fn score(&self, _image: &DynamicImage) -> io::Result<f32> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "can't run a model, cleanax was built without the onnx feature",
+        ))
+    }
+}
@@ -0,0 +1,93 @@
+use crate::report::Report;
+use crate::stats::{channel_names, get_moments, Moments};
+use image::DynamicImage;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Metrics holding the number of pixels of an image, then the mean
+/// and standard deviation of its red, green and blue channels.
+const PIXELS: &str = "pixels";
+const MEANS: [&str; 3] = ["norm_mean_r", "norm_mean_g", "norm_mean_b"];
+const STDS: [&str; 3] = ["norm_std_r", "norm_std_g", "norm_std_b"];
+
+/// Largest value of a channel of given image.
+fn value_range(image: &DynamicImage) -> f64 {
+    match image {
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_) => 65535.0,
+        _ => 255.0,
+    }
+}
+
+/// Record mean and standard deviation of the red, green and blue channels
+/// of given image, on a 0-1 scale. Grayscale images count as equal channels.
+pub fn record(image: &DynamicImage, report: &mut Report) {
+    let range = value_range(image);
+    let moments = get_moments(image);
+
+    for (name, moments) in channel_names(image).iter().zip(&moments) {
+        let channels = match *name {
+            "r" => 0..1,
+            "g" => 1..2,
+            "b" => 2..3,
+            "l" => 0..3,
+            _ => continue,
+        };
+        for channel in channels {
+            report.metric(MEANS[channel], moments.mean() / range);
+            report.metric(STDS[channel], moments.variance().sqrt() / range);
+        }
+        report.metric(PIXELS, moments.count());
+    }
+}
+
+/// Mean and standard deviation of every pixel of a dataset,
+/// as `torchvision.transforms.Normalize` takes them.
+pub struct Normalization {
+    mean: [f64; 3],
+    std: [f64; 3],
+    images: usize,
+    pixels: f64,
+}
+
+impl Normalization {
+    /// Combine statistics recorded for images that weren't flagged.
+    pub fn of_kept(reports: &[Report]) -> Self {
+        let mut channels = [Moments::default(); 3];
+        let mut images = 0;
+
+        for report in reports.iter().filter(|report| !report.is_flagged()) {
+            let pixels = match report.metrics.get(PIXELS) {
+                Some(pixels) => *pixels,
+                None => continue,
+            };
+
+            for (channel, moments) in channels.iter_mut().enumerate() {
+                let mean = report.metrics[MEANS[channel]];
+                let std = report.metrics[STDS[channel]];
+                *moments = moments.merge(Moments::from_parts(pixels, mean, std * std));
+            }
+            images += 1;
+        }
+
+        Normalization {
+            mean: channels.map(|moments| moments.mean()),
+            std: channels.map(|moments| moments.variance().sqrt()),
+            images,
+            pixels: channels[0].count(),
+        }
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+
+        dict.set_item("mean", self.mean.to_vec())?;
+        dict.set_item("std", self.std.to_vec())?;
+        dict.set_item("images", self.images)?;
+        dict.set_item("pixels", self.pixels as u64)?;
+
+        Ok(dict)
+    }
+}
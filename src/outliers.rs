@@ -0,0 +1,72 @@
+use crate::checks::COLOR_FEATURES;
+use crate::report::{Reason, Report};
+
+/// Images a dataset must hold for its color distribution to tell outliers.
+const MIN_SAMPLES: usize = 20;
+
+/// Median absolute deviation under which a feature is considered constant
+/// over the dataset, on a 0-255 scale. Keeps scores from blowing up.
+const MIN_DEVIATION: f64 = 1.0;
+
+/// Scale of the median absolute deviation of normally distributed values,
+/// relative to their standard deviation.
+const MAD_SCALE: f64 = 0.6745;
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    match values.len() % 2 {
+        0 => (values[middle - 1] + values[middle]) / 2.0,
+        _ => values[middle],
+    }
+}
+
+/// Color features of given report, if its image was described.
+fn features(report: &Report) -> Option<Vec<f64>> {
+    COLOR_FEATURES
+        .iter()
+        .map(|name| report.metrics.get(name).copied())
+        .collect()
+}
+
+/// Flag images whose colors are far from those of the rest of the dataset,
+/// as wrong-domain images are (an x-ray among pet pictures). Each color
+/// feature is compared with its median over images not flagged yet, in
+/// robust standard deviations, and the distance over every feature is
+/// reported as `outlier_score`. Datasets too small are left alone.
+pub fn flag_outliers(reports: &mut [Report], max_score: f64) {
+    let samples: Vec<(usize, Vec<f64>)> = reports
+        .iter()
+        .enumerate()
+        .filter(|(_, report)| !report.is_flagged())
+        .filter_map(|(index, report)| Some((index, features(report)?)))
+        .collect();
+    if samples.len() < MIN_SAMPLES {
+        return;
+    }
+
+    let (centers, deviations): (Vec<f64>, Vec<f64>) = (0..COLOR_FEATURES.len())
+        .map(|feature| {
+            let mut values: Vec<f64> = samples.iter().map(|(_, values)| values[feature]).collect();
+            let center = median(&mut values);
+            let mut deviations: Vec<f64> =
+                values.iter().map(|value| (value - center).abs()).collect();
+            (center, median(&mut deviations).max(MIN_DEVIATION))
+        })
+        .unzip();
+
+    for (index, values) in samples {
+        let score = values
+            .iter()
+            .zip(centers.iter().zip(&deviations))
+            .map(|(value, (center, deviation))| (MAD_SCALE * (value - center) / deviation).powi(2))
+            .sum::<f64>()
+            .sqrt();
+
+        let report = &mut reports[index];
+        report.metric("outlier_score", score);
+        if score > max_score {
+            report.flag(Reason::Outlier);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use crate::checks::{channel_layout, check_grayscale};
+use crate::config::{Config, OutputFormat};
+use crate::paths;
+use image::{imageops::FilterType, DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Component, Path, PathBuf};
+
+/// Create given output folder, refusing the folder being scanned.
+pub fn prepare(output: &str, root: &Path, option: &str) -> io::Result<()> {
+    fs::create_dir_all(output)?;
+
+    // Root is empty for paths relative to the current folder
+    let root = if root.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        root
+    };
+
+    // Output would be scanned, or overwritten while being read
+    if fs::canonicalize(output)? == fs::canonicalize(root)? {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} must be different from root folder", option),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Where a kept file goes in the cleaned copy, mirroring its place
+/// under root. Files outside of root land at the top of output.
+pub fn destination(output: &str, root: &Path, path: &str) -> PathBuf {
+    let path = paths::decode(path);
+    let relative = path
+        .strip_prefix(root)
+        .ok()
+        .filter(|relative| {
+            relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        })
+        .unwrap_or_else(|| Path::new(path.file_name().unwrap()));
+
+    Path::new(output).join(relative)
+}
+
+/// Copy, or hard link, given file to its destination.
+pub fn copy_file(path: &str, destination: &Path, hard_link: bool) -> io::Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if hard_link {
+        // Linking doesn't overwrite, unlike copying
+        if destination.exists() {
+            fs::remove_file(destination)?;
+        }
+        fs::hard_link(paths::decode(path), destination)
+    } else {
+        fs::copy(paths::decode(path), destination).map(|_| ())
+    }
+}
+
+/// Path of given file with another extension.
+pub fn with_extension(path: &str, extension: &str) -> String {
+    paths::encode(&paths::decode(path).with_extension(extension))
+}
+
+/// Rename given file, unless a file already holds the new name.
+/// Return its new path if it was renamed.
+pub fn rename(path: &str, renamed: &str) -> io::Result<Option<String>> {
+    let renamed = paths::decode(renamed);
+    if renamed.exists() {
+        return Ok(None);
+    }
+
+    fs::rename(paths::decode(path), &renamed)?;
+    Ok(Some(paths::encode(&renamed)))
+}
+
+/// Encode given image to its destination, format following extension.
+pub fn write_image(image: &DynamicImage, destination: &Path) -> io::Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    image.save(destination).map_err(io::Error::other)
+}
+
+/// Turn given image upright according to its EXIF orientation, as
+/// re-encoding it drops metadata telling viewers how to display it.
+fn orient(image: &DynamicImage, orientation: Option<u16>) -> DynamicImage {
+    match orientation {
+        Some(2) => image.fliph(),
+        Some(3) => image.rotate180(),
+        Some(4) => image.flipv(),
+        Some(5) => image.rotate90().fliph(),
+        Some(6) => image.rotate90(),
+        Some(7) => image.rotate270().fliph(),
+        Some(8) => image.rotate270(),
+        _ => image.clone(),
+    }
+}
+
+/// Bring given image to the canonical form asked by configuration:
+/// upright, shrunk to `max_dimension`, 8 bits sRGB, grayscale if its
+/// channels are equal and `grayscale_to_luma` is set.
+fn normalize(image: &DynamicImage, orientation: Option<u16>, config: &Config) -> DynamicImage {
+    let image = orient(image, orientation);
+    let image = match config.max_dimension {
+        Some(max) if image.width() > max || image.height() > max => {
+            image.resize(max, max, FilterType::Lanczos3)
+        }
+        _ => image,
+    };
+
+    // JPEG can't hold transparency
+    let alpha = image.color().has_alpha() && config.output_format != Some(OutputFormat::Jpeg);
+
+    if config.grayscale_to_luma && check_grayscale(&image, config.grayscale_tolerance) {
+        if alpha {
+            DynamicImage::ImageLumaA8(image.to_luma_alpha8())
+        } else {
+            DynamicImage::ImageLuma8(image.to_luma8())
+        }
+    } else if alpha {
+        DynamicImage::ImageRgba8(image.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    }
+}
+
+/// Convert given image to the channels and bit depth honoring the contract
+/// of the dataset. Extra channels are dropped, alpha first, and missing
+/// ones are filled: grayscale values are repeated, alpha is opaque.
+pub fn conform(image: DynamicImage, config: &Config) -> DynamicImage {
+    let layout = channel_layout(&image);
+    let (channels, bit_depth) = config.contract_layout(layout);
+    if (channels, bit_depth) == layout {
+        return image;
+    }
+
+    match (channels, bit_depth > 8) {
+        (1, false) => DynamicImage::ImageLuma8(image.to_luma8()),
+        (1, true) => DynamicImage::ImageLuma16(image.to_luma16()),
+        (2, false) => DynamicImage::ImageLumaA8(image.to_luma_alpha8()),
+        (2, true) => DynamicImage::ImageLumaA16(image.to_luma_alpha16()),
+        (3, false) => DynamicImage::ImageRgb8(image.to_rgb8()),
+        (3, true) => DynamicImage::ImageRgb16(image.to_rgb16()),
+        (_, false) => DynamicImage::ImageRgba8(image.to_rgba8()),
+        (_, true) => DynamicImage::ImageRgba16(image.to_rgba16()),
+    }
+}
+
+/// Re-encode given image to its destination after normalizing it,
+/// which leaves every metadata behind. Format is `output_format`,
+/// or follows extension if unset.
+pub fn write_normalized(
+    image: &DynamicImage,
+    orientation: Option<u16>,
+    destination: &Path,
+    config: &Config,
+) -> io::Result<()> {
+    let image = normalize(image, orientation, config);
+    let image = match config.fixes_contract() {
+        true => conform(image, config),
+        false => image,
+    };
+
+    let (destination, format) = match config.output_format {
+        Some(format) => (
+            destination.with_extension(format.extension()),
+            match format {
+                OutputFormat::Jpeg => ImageOutputFormat::Jpeg(config.jpeg_quality),
+                OutputFormat::Png => ImageOutputFormat::Png,
+            },
+        ),
+        None => (
+            destination.to_path_buf(),
+            ImageFormat::from_path(destination)
+                .map_err(io::Error::other)?
+                .into(),
+        ),
+    };
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = BufWriter::new(File::create(&destination)?);
+    image.write_to(&mut file, format).map_err(io::Error::other)
+}
@@ -0,0 +1,115 @@
+use image::error::{DecodingError, ImageFormatHint};
+use image::{DynamicImage, ImageBuffer, ImageError, ImageFormat, ImageResult};
+use image::{Luma, LumaA, Rgb, Rgba};
+use std::io::Cursor;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::ColorType;
+
+fn tiff_error(err: tiff::TiffError) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Exact(ImageFormat::Tiff),
+        err,
+    ))
+}
+
+fn unsupported_page() -> ImageError {
+    ImageError::Decoding(DecodingError::from_format_hint(ImageFormatHint::Exact(
+        ImageFormat::Tiff,
+    )))
+}
+
+/// Iterator over pages of a TIFF file, decoded one at a time.
+pub struct Pages<'a> {
+    decoder: Decoder<Cursor<&'a [u8]>>,
+    started: bool,
+    failed: bool,
+}
+
+/// Open given TIFF file for page by page decoding.
+/// Return `None` if file isn't a TIFF.
+pub fn open_pages(data: &[u8]) -> ImageResult<Option<Pages<'_>>> {
+    if image::guess_format(data).ok() != Some(ImageFormat::Tiff) {
+        return Ok(None);
+    }
+
+    let decoder = Decoder::new(Cursor::new(data)).map_err(tiff_error)?;
+
+    Ok(Some(Pages {
+        decoder,
+        started: false,
+        failed: false,
+    }))
+}
+
+impl Pages<'_> {
+    /// Decode current page of the document.
+    fn read_page(&mut self) -> ImageResult<DynamicImage> {
+        let (width, height) = self.decoder.dimensions().map_err(tiff_error)?;
+        let color = self.decoder.colortype().map_err(tiff_error)?;
+        let buffer = self.decoder.read_image().map_err(tiff_error)?;
+
+        let image = match (color, buffer) {
+            (ColorType::Gray(8), DecodingResult::U8(raw)) => {
+                ImageBuffer::<Luma<u8>, _>::from_raw(width, height, raw)
+                    .map(DynamicImage::ImageLuma8)
+            }
+            (ColorType::GrayA(8), DecodingResult::U8(raw)) => {
+                ImageBuffer::<LumaA<u8>, _>::from_raw(width, height, raw)
+                    .map(DynamicImage::ImageLumaA8)
+            }
+            (ColorType::RGB(8), DecodingResult::U8(raw)) => {
+                ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, raw).map(DynamicImage::ImageRgb8)
+            }
+            (ColorType::RGBA(8), DecodingResult::U8(raw)) => {
+                ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, raw)
+                    .map(DynamicImage::ImageRgba8)
+            }
+            (ColorType::Gray(16), DecodingResult::U16(raw)) => {
+                ImageBuffer::<Luma<u16>, _>::from_raw(width, height, raw)
+                    .map(DynamicImage::ImageLuma16)
+            }
+            (ColorType::GrayA(16), DecodingResult::U16(raw)) => {
+                ImageBuffer::<LumaA<u16>, _>::from_raw(width, height, raw)
+                    .map(DynamicImage::ImageLumaA16)
+            }
+            (ColorType::RGB(16), DecodingResult::U16(raw)) => {
+                ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, raw)
+                    .map(DynamicImage::ImageRgb16)
+            }
+            (ColorType::RGBA(16), DecodingResult::U16(raw)) => {
+                ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, raw)
+                    .map(DynamicImage::ImageRgba16)
+            }
+            _ => None,
+        };
+
+        image.ok_or_else(unsupported_page)
+    }
+}
+
+impl Iterator for Pages<'_> {
+    type Item = ImageResult<DynamicImage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Pages following a broken one can't be located
+        if self.failed {
+            return None;
+        }
+
+        if self.started {
+            if !self.decoder.more_images() {
+                return None;
+            }
+            if let Err(err) = self.decoder.next_image() {
+                self.failed = true;
+                return Some(Err(tiff_error(err)));
+            }
+        }
+        self.started = true;
+
+        let page = self.read_page();
+        self.failed = page.is_err();
+
+        Some(page)
+    }
+}
@@ -0,0 +1,202 @@
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyString};
+use pyo3::PyNativeType;
+use std::path::{Path, PathBuf};
+
+/// Character standing for the first undecodable byte of a file name.
+/// Paths are carried as strings, file names that aren't valid UTF-8
+/// holding each of their undecodable bytes (always 0x80 or above) as one
+/// of the last private use characters, as python does with lone surrogates.
+#[cfg(unix)]
+const ESCAPE: u32 = 0x10FF00;
+
+/// Byte given character stands for, if it's an escaped one.
+#[cfg(unix)]
+fn escaped_byte(c: char) -> Option<u8> {
+    match c as u32 {
+        code if code >= ESCAPE + 0x80 => Some((code - ESCAPE) as u8),
+        _ => None,
+    }
+}
+
+/// Convert a path to a string, escaping bytes that aren't valid UTF-8.
+#[cfg(unix)]
+pub fn encode(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    from_bytes(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+pub fn from_bytes(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Convert bytes of a path, as written in a file, to a string.
+#[cfg(unix)]
+pub fn from_bytes(mut bytes: &[u8]) -> String {
+    use std::str;
+
+    let mut encoded = String::with_capacity(bytes.len());
+    loop {
+        match str::from_utf8(bytes) {
+            Ok(valid) => {
+                encoded.push_str(valid);
+                return encoded;
+            }
+            Err(err) => {
+                let (valid, rest) = bytes.split_at(err.valid_up_to());
+                encoded.push_str(str::from_utf8(valid).unwrap());
+
+                let invalid = err.error_len().unwrap_or(rest.len());
+                for byte in &rest[..invalid] {
+                    encoded.push(char::from_u32(ESCAPE + *byte as u32).unwrap());
+                }
+                bytes = &rest[invalid..];
+            }
+        }
+    }
+}
+
+/// Paths that aren't valid unicode are rare outside of unix.
+#[cfg(not(unix))]
+pub fn encode(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// Bytes of a path converted to a string, its escaped bytes restored.
+#[cfg(unix)]
+fn to_bytes(path: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut buffer = [0u8; 4];
+    for c in path.chars() {
+        match escaped_byte(c) {
+            Some(byte) => bytes.push(byte),
+            None => bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes()),
+        }
+    }
+    bytes
+}
+
+/// Convert a string back to the path it was converted from.
+#[cfg(unix)]
+pub fn decode(path: &str) -> PathBuf {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    if !is_escaped(path) {
+        return PathBuf::from(path);
+    }
+    PathBuf::from(OsString::from_vec(to_bytes(path)))
+}
+
+#[cfg(not(unix))]
+pub fn decode(path: &str) -> PathBuf {
+    PathBuf::from(path)
+}
+
+/// Check if given path holds bytes that aren't valid UTF-8.
+#[cfg(unix)]
+fn is_escaped(path: &str) -> bool {
+    path.chars().any(|c| escaped_byte(c).is_some())
+}
+
+#[cfg(not(unix))]
+fn is_escaped(_path: &str) -> bool {
+    false
+}
+
+/// Convert a path to a python string, undecodable bytes becoming lone
+/// surrogates as `os.fsdecode` does, so that it opens the same file.
+pub fn to_py(py: Python, path: &str) -> PyObject {
+    #[cfg(unix)]
+    if is_escaped(path) {
+        let bytes = PyBytes::new(py, &to_bytes(path));
+        // Decoding with surrogateescape never fails
+        return bytes
+            .call_method1("decode", ("utf-8", "surrogateescape"))
+            .unwrap()
+            .into();
+    }
+
+    PyString::new(py, path).into()
+}
+
+/// Path given by python as a string, bytes or `os.PathLike` object,
+/// converted to a string that opens the same file.
+pub struct FsPath(pub String);
+
+impl<'source> FromPyObject<'source> for FsPath {
+    #[cfg(unix)]
+    fn extract(value: &'source PyAny) -> PyResult<Self> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        // Most paths are valid strings, don't go through os for them
+        if let Ok(path) = value.downcast::<PyString>() {
+            if let Ok(path) = path.to_str() {
+                return Ok(FsPath(path.to_string()));
+            }
+        }
+
+        let os = value.py().import("os")?;
+        let bytes: &PyBytes = os.call1("fsencode", (value,))?.downcast()?;
+        Ok(FsPath(encode(Path::new(OsStr::from_bytes(
+            bytes.as_bytes(),
+        )))))
+    }
+
+    #[cfg(not(unix))]
+    fn extract(value: &'source PyAny) -> PyResult<Self> {
+        let os = value.py().import("os")?;
+        Ok(FsPath(os.call1("fspath", (value,))?.extract()?))
+    }
+}
+
+/// Deepest folder holding every given folder, empty if they have
+/// nothing in common or are relative to the current folder.
+pub fn common_root(folders: &[PathBuf]) -> PathBuf {
+    let mut root: Vec<_> = match folders.first() {
+        Some(folder) => folder.components().collect(),
+        None => return PathBuf::new(),
+    };
+
+    for folder in &folders[1..] {
+        let shared = root
+            .iter()
+            .zip(folder.components())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        root.truncate(shared);
+    }
+
+    root.into_iter().collect()
+}
+
+/// Root folders to scan together, given by python as a single path
+/// or a list of them.
+pub struct Roots(pub Vec<String>);
+
+impl<'source> FromPyObject<'source> for Roots {
+    fn extract(value: &'source PyAny) -> PyResult<Self> {
+        // Strings are sequences too, try them as a single path first
+        if let Ok(path) = FsPath::extract(value) {
+            return Ok(Roots(vec![path.0]));
+        }
+
+        let paths: Vec<FsPath> = value.extract()?;
+        Ok(Roots(paths.into_iter().map(|path| path.0).collect()))
+    }
+}
+
+impl ToPyObject for FsPath {
+    fn to_object(&self, py: Python) -> PyObject {
+        to_py(py, &self.0)
+    }
+}
+
+impl IntoPy<PyObject> for FsPath {
+    fn into_py(self, py: Python) -> PyObject {
+        to_py(py, &self.0)
+    }
+}
@@ -0,0 +1,47 @@
+use crate::config::OptionError;
+use crate::index::resolve;
+use glob::Pattern;
+use std::path::Path;
+
+/// Files given by path or glob pattern. A pattern matches a path
+/// as given, once resolved, or its file name.
+#[derive(Clone, Debug, Default)]
+pub struct Patterns {
+    patterns: Vec<Pattern>,
+}
+
+impl Patterns {
+    /// Compile patterns given to an option.
+    pub fn new(option: &str, patterns: &[String]) -> Result<Self, OptionError> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern).map_err(|err| {
+                    OptionError::Value(format!("invalid {} pattern '{}': {}", option, pattern, err))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Patterns { patterns })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let resolved = resolve(path);
+
+        self.patterns.iter().any(|pattern| {
+            pattern.matches(path) || pattern.matches(&name) || pattern.matches(&resolved)
+        })
+    }
+}
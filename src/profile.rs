@@ -0,0 +1,68 @@
+#[cfg(feature = "parquet")]
+use crate::report::Columns;
+use crate::report::Report;
+use std::io;
+
+/// Write a row for every given report to a Parquet file: path, verdict,
+/// perceptual hash and a column for each metric. Missing metrics are null.
+#[cfg(feature = "parquet")]
+pub fn write_profile(path: &str, reports: &[Report]) -> io::Result<()> {
+    use arrow_array::{
+        ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, UInt64Array,
+    };
+    use parquet::arrow::ArrowWriter;
+    use std::fs::File;
+    use std::sync::Arc;
+
+    let columns = Columns::new(reports);
+
+    let mut arrays: Vec<(&str, ArrayRef)> = vec![
+        (
+            "path",
+            Arc::new(StringArray::from_iter_values(&columns.path)),
+        ),
+        ("flagged", Arc::new(BooleanArray::from(columns.flagged))),
+        (
+            "reasons",
+            Arc::new(StringArray::from_iter_values(&columns.reasons)),
+        ),
+        (
+            "warnings",
+            Arc::new(StringArray::from_iter_values(&columns.warnings)),
+        ),
+        (
+            "info",
+            Arc::new(StringArray::from_iter_values(&columns.infos)),
+        ),
+        (
+            "suspect",
+            Arc::new(StringArray::from_iter_values(&columns.suspects)),
+        ),
+        ("error", Arc::new(StringArray::from(columns.error))),
+        ("hash", Arc::new(UInt64Array::from(columns.hash))),
+    ];
+
+    for (name, column) in columns.metrics {
+        arrays.push((name, Arc::new(Float64Array::from(column))));
+    }
+
+    let batch = RecordBatch::try_from_iter(arrays).map_err(io::Error::other)?;
+
+    let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), None)
+        .map_err(io::Error::other)?;
+    writer.write(&batch).map_err(io::Error::other)?;
+    writer.close().map_err(io::Error::other)?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet"))]
+pub fn write_profile(path: &str, _reports: &[Report]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "can't write {}, cleanax was built without the parquet feature",
+            path
+        ),
+    ))
+}
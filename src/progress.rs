@@ -0,0 +1,101 @@
+use crate::config::ProgressMode;
+use crate::report::Report;
+use indicatif::{ProgressBar, ProgressDrawTarget};
+use log::info;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Time between two progress lines in log mode.
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Progress of a scan, shown as a bar, logged periodically or not at all.
+pub struct Progress {
+    mode: ProgressMode,
+    bar: ProgressBar,
+    total: AtomicU64,
+    scanned: AtomicU64,
+    flagged: AtomicU64,
+    /// When the last progress line was logged.
+    logged: Mutex<Instant>,
+}
+
+impl Progress {
+    /// Follow a scan of `total` files, which may grow as they're listed.
+    pub fn new(mode: ProgressMode, total: u64) -> Self {
+        let bar = match mode {
+            ProgressMode::Bar => ProgressBar::new(total),
+            _ => ProgressBar::with_draw_target(total, ProgressDrawTarget::hidden()),
+        };
+
+        Progress {
+            mode,
+            bar,
+            total: AtomicU64::new(total),
+            scanned: AtomicU64::new(0),
+            flagged: AtomicU64::new(0),
+            logged: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Count one more file to scan.
+    pub fn inc_length(&self) {
+        self.total.fetch_add(1, Ordering::Relaxed);
+        self.bar.inc_length(1);
+    }
+
+    /// Count a scanned file, given its reports.
+    pub fn inc(&self, reports: &[Report]) {
+        let flagged = reports.iter().filter(|report| report.is_flagged()).count() as u64;
+        self.scanned.fetch_add(1, Ordering::Relaxed);
+        self.flagged.fetch_add(flagged, Ordering::Relaxed);
+        self.bar.inc(1);
+
+        if self.mode != ProgressMode::Log {
+            return;
+        }
+
+        // Other threads got to log this period
+        let mut logged = match self.logged.try_lock() {
+            Ok(logged) => logged,
+            Err(_) => return,
+        };
+        if logged.elapsed() >= LOG_INTERVAL {
+            *logged = Instant::now();
+            self.log();
+        }
+    }
+
+    /// Log the last progress line, or finish the bar.
+    pub fn finish(&self) {
+        match self.mode {
+            ProgressMode::Bar => self.bar.finish(),
+            ProgressMode::Log => self.log(),
+            ProgressMode::None => {}
+        }
+    }
+
+    fn log(&self) {
+        info!(
+            "{}/{} scanned, {} flagged",
+            thousands(self.scanned.load(Ordering::Relaxed)),
+            thousands(self.total.load(Ordering::Relaxed)),
+            thousands(self.flagged.load(Ordering::Relaxed)),
+        );
+    }
+}
+
+/// Format given number with commas between groups of thousands.
+fn thousands(number: u64) -> String {
+    let digits = number.to_string();
+    let mut formatted = String::with_capacity(digits.len() * 4 / 3);
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+
+    formatted
+}
@@ -0,0 +1,124 @@
+use image::codecs::jpeg::JpegDecoder;
+use image::error::{DecodingError, ImageFormatHint};
+use image::{DynamicImage, ImageDecoder, ImageError, ImageResult};
+use std::io::Cursor;
+use std::path::Path;
+
+/// Extensions of camera RAW files.
+const EXTENSIONS: [&str; 11] = [
+    "cr2", "nef", "nrw", "arw", "srf", "dng", "raf", "orf", "rw2", "pef", "srw",
+];
+
+fn raw_error(message: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("RAW".to_string()),
+        message,
+    ))
+}
+
+/// Check extension of given file for a camera RAW file. These are mostly
+/// TIFF based, so magic bytes can't tell them apart from regular TIFF.
+pub fn is_raw(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Decode the largest JPEG preview embedded in given RAW file.
+/// Cameras store such previews next to sensor data, validating them
+/// is much cheaper than decoding sensor data.
+pub fn load_preview(data: &[u8]) -> ImageResult<DynamicImage> {
+    // JPEG streams start with a SOI marker directly followed by another marker
+    let mut largest: Option<(u64, usize)> = None;
+    for (start, _) in data
+        .windows(3)
+        .enumerate()
+        .filter(|(_, window)| *window == [0xFF, 0xD8, 0xFF])
+    {
+        if let Ok(decoder) = JpegDecoder::new(Cursor::new(&data[start..])) {
+            let (width, height) = decoder.dimensions();
+            let area = width as u64 * height as u64;
+
+            if largest.is_none_or(|(largest_area, _)| area > largest_area) {
+                largest = Some((area, start));
+            }
+        }
+    }
+
+    match largest {
+        Some((_, start)) => {
+            DynamicImage::from_decoder(JpegDecoder::new(Cursor::new(&data[start..]))?)
+        }
+        None => Err(raw_error("No embedded preview found")),
+    }
+}
+
+/// Decode sensor data of given RAW file. Bayer cells of 2x2 photosites
+/// are merged into a single RGB pixel, which is plenty for statistics.
+#[cfg(feature = "raw")]
+pub fn load_raw(data: &[u8]) -> ImageResult<DynamicImage> {
+    use image::{ImageBuffer, Rgb};
+    use rawloader::RawImageData;
+    use rayon::prelude::*;
+
+    let raw =
+        rawloader::decode(&mut Cursor::new(data)).map_err(|err| raw_error(err.to_string()))?;
+
+    let data: Vec<f32> = match &raw.data {
+        RawImageData::Integer(data) => data.iter().map(|value| *value as f32).collect(),
+        RawImageData::Float(data) => data.clone(),
+    };
+
+    // Bring a photosite of given color back to 16 bits
+    let normalize = |value: f32, color: usize| {
+        let black = raw.blacklevels[color] as f32;
+        let white = (raw.whitelevels[color] as f32).max(black + 1.0);
+        ((value - black) / (white - black)).clamp(0.0, 1.0) * 65535.0
+    };
+
+    let (pixels, width, height) = if raw.cpp == 3 {
+        let pixels = data
+            .par_chunks(3)
+            .flat_map_iter(|pixel| (0..3).map(move |c| normalize(pixel[c], c) as u16))
+            .collect();
+        (pixels, raw.width, raw.height)
+    } else {
+        let (width, height) = (raw.width / 2, raw.height / 2);
+        let pixels = (0..height)
+            .into_par_iter()
+            .flat_map_iter(|y| (0..width).map(move |x| (x, y)))
+            .flat_map_iter(|(x, y)| {
+                let mut sums = [0f32; 3];
+                let mut counts = [0f32; 3];
+
+                for (row, col) in [(0, 0), (0, 1), (1, 0), (1, 1)].iter() {
+                    let (row, col) = (2 * y + row, 2 * x + col);
+                    let color = raw.cfa.color_at(row, col);
+                    let value = normalize(data[row * raw.width + col], color);
+
+                    // Emerald photosites are close enough to green ones
+                    let channel = if color == 3 { 1 } else { color };
+                    sums[channel] += value;
+                    counts[channel] += 1.0;
+                }
+
+                (0..3).map(move |c| (sums[c] / counts[c].max(1.0)) as u16)
+            })
+            .collect();
+        (pixels, width, height)
+    };
+
+    ImageBuffer::<Rgb<u16>, _>::from_raw(width as u32, height as u32, pixels)
+        .map(DynamicImage::ImageRgb16)
+        .ok_or_else(|| raw_error("Sensor data doesn't match image size"))
+}
+
+/// RAW decoding isn't compiled in.
+#[cfg(not(feature = "raw"))]
+pub fn load_raw(_data: &[u8]) -> ImageResult<DynamicImage> {
+    Err(raw_error(
+        "RAW decoding requires the `raw` feature, use raw_preview=True to check embedded previews",
+    ))
+}
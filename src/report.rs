@@ -0,0 +1,535 @@
+use crate::paths;
+use numpy::PyArray1;
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyDict, PyList};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::mem;
+
+/// Why a file got flagged as noise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// File couldn't be loaded as an image.
+    Undecodable,
+    /// Image is (almost) a single color.
+    SolidColor,
+    /// Image looks like a known placeholder.
+    Placeholder,
+    /// Color image whose channels are all equal.
+    Grayscale,
+    /// Image enlarged from a much smaller source.
+    Upscaled,
+    /// Image holding very few distinct levels per channel.
+    Banding,
+    /// Image crossed by constant rows or columns.
+    DeadLine,
+    /// Animated image, when animations aren't kept.
+    Animated,
+    /// Remote file couldn't be downloaded.
+    Network,
+    /// File listed in a manifest doesn't exist.
+    Missing,
+    /// Image is a copy of another one, or looks just like it.
+    Duplicate,
+    /// Image tinted by one color channel dominating the others.
+    ColorCast,
+    /// Image with many pixels crushed to black or blown to white.
+    Clipping,
+    /// Screenshot, rather than a photo.
+    Screenshot,
+    /// Image carrying a watermark or an overlaid logo.
+    Watermark,
+    /// Blank image but for a small foreground region.
+    NearBlank,
+    /// Image filtered out by its EXIF metadata: capture date,
+    /// camera or GPS coordinates.
+    Metadata,
+    /// File whose extension names another format than its content.
+    WrongExtension,
+    /// Image whose colors are far from those of the rest of the dataset.
+    Outlier,
+    /// Frame of a burst of nearly identical pictures, but the sharpest.
+    Burst,
+    /// File decoded despite problems decoders put up with.
+    DecoderWarning,
+    /// File implausibly small for the dimensions its header tells.
+    Overcompressed,
+    /// Image made of the same tile repeated many times.
+    RepeatedTiles,
+    /// Image whose score by a user supplied model is out of bounds.
+    Model,
+    /// Image without a caption file.
+    MissingCaption,
+    /// Image whose caption is empty or holds only whitespace.
+    EmptyCaption,
+    /// Image whose caption isn't valid UTF-8.
+    CaptionEncoding,
+    /// Caption file without an image.
+    OrphanCaption,
+    /// Video file, when frames of videos aren't sampled.
+    Video,
+    /// Image whose channels or bit depth break the contract of the dataset.
+    ContractViolation,
+    /// Image whose content hugs one edge or corner, the rest being background.
+    EdgeContent,
+    /// File exceeding decoding limits: dimensions, decoded size or time.
+    ResourceLimit,
+    /// Image too blurry, its blur score being under `min_sharpness`.
+    Blurry,
+    /// Image holding too little information, its luminance entropy
+    /// being under `min_entropy`.
+    LowEntropy,
+}
+
+impl Reason {
+    /// Every reason, in the order of their bit in a reason mask.
+    pub const ALL: &'static [Reason] = &[
+        Reason::Undecodable,
+        Reason::SolidColor,
+        Reason::Placeholder,
+        Reason::Grayscale,
+        Reason::Upscaled,
+        Reason::Banding,
+        Reason::DeadLine,
+        Reason::Animated,
+        Reason::Network,
+        Reason::Missing,
+        Reason::Duplicate,
+        Reason::ColorCast,
+        Reason::Clipping,
+        Reason::Screenshot,
+        Reason::Watermark,
+        Reason::NearBlank,
+        Reason::Metadata,
+        Reason::WrongExtension,
+        Reason::Outlier,
+        Reason::Burst,
+        Reason::DecoderWarning,
+        Reason::Overcompressed,
+        Reason::RepeatedTiles,
+        Reason::Model,
+        Reason::MissingCaption,
+        Reason::EmptyCaption,
+        Reason::CaptionEncoding,
+        Reason::OrphanCaption,
+        Reason::Video,
+        Reason::ContractViolation,
+        Reason::EdgeContent,
+        Reason::ResourceLimit,
+        Reason::Blurry,
+        Reason::LowEntropy,
+    ];
+
+    /// Bit standing for the reason in a reason mask.
+    pub fn bit(&self) -> u64 {
+        1 << Reason::ALL
+            .iter()
+            .position(|reason| reason == self)
+            .unwrap()
+    }
+
+    /// Reason of given name, as `as_str` gives it.
+    pub fn parse(name: &str) -> Option<Reason> {
+        Reason::ALL
+            .iter()
+            .find(|reason| reason.as_str() == name)
+            .copied()
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Reason::Undecodable => "undecodable",
+            Reason::SolidColor => "solid_color",
+            Reason::Placeholder => "placeholder",
+            Reason::Grayscale => "grayscale",
+            Reason::Upscaled => "upscaled",
+            Reason::Banding => "banding",
+            Reason::DeadLine => "dead_line",
+            Reason::Animated => "animated",
+            Reason::Network => "network",
+            Reason::Missing => "missing",
+            Reason::Duplicate => "duplicate",
+            Reason::ColorCast => "color_cast",
+            Reason::Clipping => "clipping",
+            Reason::Screenshot => "screenshot",
+            Reason::Watermark => "watermark",
+            Reason::NearBlank => "near_blank",
+            Reason::Metadata => "metadata",
+            Reason::WrongExtension => "wrong_extension",
+            Reason::Outlier => "outlier",
+            Reason::Burst => "burst",
+            Reason::DecoderWarning => "decoder_warning",
+            Reason::Overcompressed => "overcompressed",
+            Reason::RepeatedTiles => "repeated_tiles",
+            Reason::Model => "model",
+            Reason::MissingCaption => "missing_caption",
+            Reason::EmptyCaption => "empty_caption",
+            Reason::CaptionEncoding => "caption_encoding",
+            Reason::OrphanCaption => "orphan_caption",
+            Reason::Video => "video",
+            Reason::ContractViolation => "contract_violation",
+            Reason::EdgeContent => "edge_content",
+            Reason::ResourceLimit => "resource_limit",
+            Reason::Blurry => "blurry",
+            Reason::LowEntropy => "low_entropy",
+        }
+    }
+}
+
+/// What a reason found by checks means for the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// File gets flagged, and deleted.
+    Delete,
+    /// File is kept, the reason being reported as a warning.
+    Warn,
+    /// File is kept, the reason being reported for information.
+    Info,
+    /// File is kept, but should be reviewed.
+    Suspect,
+}
+
+/// Severity of every reason.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Severities {
+    /// In the order of `Reason::ALL`.
+    severities: Vec<Severity>,
+}
+
+impl Default for Severities {
+    /// Every reason gets files deleted, but decoder warnings
+    /// which leave files to be reviewed.
+    fn default() -> Self {
+        let mut severities = Severities {
+            severities: vec![Severity::Delete; Reason::ALL.len()],
+        };
+        severities.set(Reason::DecoderWarning, Severity::Suspect);
+        severities
+    }
+}
+
+impl Severities {
+    pub fn of(&self, reason: Reason) -> Severity {
+        self.severities[reason.bit().trailing_zeros() as usize]
+    }
+
+    pub fn set(&mut self, reason: Reason, severity: Severity) {
+        self.severities[reason.bit().trailing_zeros() as usize] = severity;
+    }
+}
+
+/// Outcome of all checks run over a single file.
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub path: String,
+    /// Reasons the file gets deleted for.
+    pub reasons: Vec<Reason>,
+    /// Reasons of "warn" severity, which don't get the file deleted.
+    pub warnings: Vec<Reason>,
+    /// Reasons of "info" severity.
+    pub infos: Vec<Reason>,
+    /// Reasons of "suspect" severity, for the file to be reviewed.
+    pub suspects: Vec<Reason>,
+    /// Problems decoders put up with while reading the file.
+    pub decoder_warnings: Vec<String>,
+    /// Scores computed by checks, to help tuning their thresholds.
+    pub metrics: BTreeMap<&'static str, f64>,
+    /// Reports of each page of a multipage document.
+    pub pages: Vec<Report>,
+    /// Why the file couldn't be decoded.
+    pub error: Option<String>,
+    /// Perceptual hash, when profiling or looking for duplicates.
+    pub hash: Option<u64>,
+    /// Perceptual hashes of the image flipped and rotated, when duplicates
+    /// are looked for in every orientation.
+    pub oriented_hashes: Vec<u64>,
+    /// Hash of file content, when looking for duplicates.
+    pub content_hash: Option<u64>,
+    /// Hash of decoded pixels, when looking for duplicates encoded
+    /// differently.
+    pub pixel_hash: Option<u64>,
+    /// Group of duplicates the file belongs to.
+    pub cluster: Option<usize>,
+    /// Path the file was renamed to, its extension being fixed.
+    pub renamed: Option<String>,
+    /// SOP Instance UID of a DICOM file.
+    pub sop_instance_uid: Option<String>,
+    /// Caption file going with the image, when captions are checked.
+    pub caption: Option<String>,
+}
+
+impl Report {
+    pub fn new(path: &str) -> Self {
+        Report {
+            path: path.to_string(),
+            reasons: Vec::new(),
+            warnings: Vec::new(),
+            infos: Vec::new(),
+            suspects: Vec::new(),
+            decoder_warnings: Vec::new(),
+            metrics: BTreeMap::new(),
+            pages: Vec::new(),
+            error: None,
+            hash: None,
+            oriented_hashes: Vec::new(),
+            content_hash: None,
+            pixel_hash: None,
+            cluster: None,
+            renamed: None,
+            sop_instance_uid: None,
+            caption: None,
+        }
+    }
+
+    pub fn flag(&mut self, reason: Reason) {
+        self.reasons.push(reason);
+    }
+
+    /// Flag file as undecodable because of given error.
+    pub fn fail(&mut self, error: impl Display) {
+        self.fail_as(Reason::Undecodable, error);
+    }
+
+    /// Flag file for given reason, keeping error that led to it.
+    pub fn fail_as(&mut self, reason: Reason, error: impl Display) {
+        self.flag(reason);
+        self.error = Some(error.to_string());
+    }
+
+    pub fn metric(&mut self, name: &'static str, value: f64) {
+        self.metrics.insert(name, value);
+    }
+
+    /// Move reasons that don't get the file deleted to warnings
+    /// and infos, according to their severity.
+    pub fn apply_severities(&mut self, severities: &Severities) {
+        for reason in mem::take(&mut self.reasons) {
+            match severities.of(reason) {
+                Severity::Delete => self.reasons.push(reason),
+                Severity::Warn => self.warnings.push(reason),
+                Severity::Info => self.infos.push(reason),
+                Severity::Suspect => self.suspects.push(reason),
+            }
+        }
+
+        for page in &mut self.pages {
+            page.apply_severities(severities);
+        }
+    }
+
+    pub fn is_flagged(&self) -> bool {
+        !self.reasons.is_empty()
+    }
+
+    /// Convert report to a python dictionary.
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("path", paths::to_py(py, &self.path))?;
+        dict.set_item("reasons", names(&self.reasons))?;
+        dict.set_item("warnings", names(&self.warnings))?;
+        dict.set_item("info", names(&self.infos))?;
+        dict.set_item("suspect", names(&self.suspects))?;
+        dict.set_item("decoder_warnings", &self.decoder_warnings)?;
+        dict.set_item("metrics", self.metrics.clone().into_py_dict(py))?;
+
+        let pages = PyList::empty(py);
+        for page in &self.pages {
+            pages.append(page.to_dict(py)?)?;
+        }
+        dict.set_item("pages", pages)?;
+        dict.set_item("error", &self.error)?;
+        dict.set_item("hash", self.hash)?;
+        dict.set_item("cluster", self.cluster)?;
+        dict.set_item(
+            "renamed",
+            self.renamed.as_ref().map(|path| paths::to_py(py, path)),
+        )?;
+        dict.set_item("sop_instance_uid", &self.sop_instance_uid)?;
+        dict.set_item(
+            "caption",
+            self.caption.as_ref().map(|path| paths::to_py(py, path)),
+        )?;
+
+        Ok(dict)
+    }
+
+    /// Convert report to JSON, laid out as its python dictionary.
+    /// Paths that aren't valid UTF-8 are converted lossily.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "path": paths::decode(&self.path).to_string_lossy(),
+            "reasons": names(&self.reasons),
+            "warnings": names(&self.warnings),
+            "info": names(&self.infos),
+            "suspect": names(&self.suspects),
+            "decoder_warnings": self.decoder_warnings,
+            "metrics": self.metrics,
+            "pages": self.pages.iter().map(Report::to_json).collect::<Vec<_>>(),
+            "error": self.error,
+            "hash": self.hash,
+            "cluster": self.cluster,
+            "renamed": self.renamed.as_ref().map(|path| paths::decode(path).to_string_lossy().into_owned()),
+            "sop_instance_uid": self.sop_instance_uid,
+            "caption": self.caption.as_ref().map(|path| paths::decode(path).to_string_lossy().into_owned()),
+        })
+    }
+}
+
+/// Names of given reasons.
+fn names(reasons: &[Reason]) -> Vec<&'static str> {
+    reasons.iter().map(Reason::as_str).collect()
+}
+
+/// Mask of bits of given reasons.
+fn mask(reasons: &[Reason]) -> u64 {
+    reasons.iter().map(Reason::bit).fold(0, |a, b| a | b)
+}
+
+/// Reports laid out column by column, one row per report.
+pub struct Columns {
+    pub path: Vec<String>,
+    pub flagged: Vec<bool>,
+    /// Comma separated reasons, warnings, infos and suspects.
+    pub reasons: Vec<String>,
+    pub warnings: Vec<String>,
+    pub infos: Vec<String>,
+    pub suspects: Vec<String>,
+    pub error: Vec<Option<String>>,
+    pub hash: Vec<Option<u64>>,
+    pub cluster: Vec<Option<usize>>,
+    /// Reasons, warnings, infos and suspects as masks of `Reason::bit`.
+    pub reason_mask: Vec<u64>,
+    pub warning_mask: Vec<u64>,
+    pub info_mask: Vec<u64>,
+    pub suspect_mask: Vec<u64>,
+    /// Files don't all go through the same checks, metrics a check
+    /// didn't compute for a file are missing.
+    pub metrics: BTreeMap<&'static str, Vec<Option<f64>>>,
+}
+
+impl Columns {
+    pub fn new(reports: &[Report]) -> Self {
+        let mut metrics = BTreeMap::new();
+        for name in reports.iter().flat_map(|report| report.metrics.keys()) {
+            metrics.entry(*name).or_insert_with(Vec::new);
+        }
+        for (name, column) in metrics.iter_mut() {
+            column.extend(
+                reports
+                    .iter()
+                    .map(|report| report.metrics.get(name).copied()),
+            );
+        }
+
+        Columns {
+            path: reports.iter().map(|report| report.path.clone()).collect(),
+            flagged: reports.iter().map(|report| report.is_flagged()).collect(),
+            reasons: reports
+                .iter()
+                .map(|report| names(&report.reasons).join(","))
+                .collect(),
+            warnings: reports
+                .iter()
+                .map(|report| names(&report.warnings).join(","))
+                .collect(),
+            infos: reports
+                .iter()
+                .map(|report| names(&report.infos).join(","))
+                .collect(),
+            suspects: reports
+                .iter()
+                .map(|report| names(&report.suspects).join(","))
+                .collect(),
+            error: reports.iter().map(|report| report.error.clone()).collect(),
+            hash: reports.iter().map(|report| report.hash).collect(),
+            cluster: reports.iter().map(|report| report.cluster).collect(),
+            reason_mask: reports.iter().map(|report| mask(&report.reasons)).collect(),
+            warning_mask: reports
+                .iter()
+                .map(|report| mask(&report.warnings))
+                .collect(),
+            info_mask: reports.iter().map(|report| mask(&report.infos)).collect(),
+            suspect_mask: reports
+                .iter()
+                .map(|report| mask(&report.suspects))
+                .collect(),
+            metrics,
+        }
+    }
+
+    /// Convert columns to a python dictionary of lists, missing metrics
+    /// being NaN as pandas expects.
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+
+        dict.set_item("path", self.paths(py))?;
+        dict.set_item("flagged", &self.flagged)?;
+        dict.set_item("reasons", &self.reasons)?;
+        dict.set_item("warnings", &self.warnings)?;
+        dict.set_item("info", &self.infos)?;
+        dict.set_item("suspect", &self.suspects)?;
+        dict.set_item("error", &self.error)?;
+        dict.set_item("hash", &self.hash)?;
+        dict.set_item("cluster", &self.cluster)?;
+
+        for (name, column) in &self.metrics {
+            let values: Vec<f64> = column
+                .iter()
+                .map(|value| value.unwrap_or(f64::NAN))
+                .collect();
+            dict.set_item(name, values)?;
+        }
+
+        Ok(dict)
+    }
+}
+
+impl Columns {
+    /// Python strings of paths, which may not be valid UTF-8.
+    fn paths(&self, py: Python) -> Vec<PyObject> {
+        self.path
+            .iter()
+            .map(|path| paths::to_py(py, path))
+            .collect()
+    }
+
+    /// Convert columns to a python dictionary of dense numpy arrays, paths
+    /// and errors excepted. Reasons, warnings, infos and suspects are masks of bits ordered as
+    /// `cleanax.REASONS`, missing hashes are 0, files without duplicates
+    /// are in cluster -1 and missing metrics are NaN.
+    pub fn to_arrays<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        // Raise an ImportError rather than panicking without numpy
+        py.import("numpy")?;
+
+        let dict = PyDict::new(py);
+
+        dict.set_item("path", self.paths(py))?;
+        dict.set_item("flagged", PyArray1::from_slice(py, &self.flagged))?;
+        dict.set_item("reasons", PyArray1::from_slice(py, &self.reason_mask))?;
+        dict.set_item("warnings", PyArray1::from_slice(py, &self.warning_mask))?;
+        dict.set_item("info", PyArray1::from_slice(py, &self.info_mask))?;
+        dict.set_item("suspect", PyArray1::from_slice(py, &self.suspect_mask))?;
+        dict.set_item("error", &self.error)?;
+
+        let hash: Vec<u64> = self.hash.iter().map(|hash| hash.unwrap_or(0)).collect();
+        dict.set_item("hash", PyArray1::from_vec(py, hash))?;
+
+        let cluster: Vec<i64> = self
+            .cluster
+            .iter()
+            .map(|cluster| cluster.map_or(-1, |cluster| cluster as i64))
+            .collect();
+        dict.set_item("cluster", PyArray1::from_vec(py, cluster))?;
+
+        for (name, column) in &self.metrics {
+            let values: Vec<f64> = column
+                .iter()
+                .map(|value| value.unwrap_or(f64::NAN))
+                .collect();
+            dict.set_item(name, PyArray1::from_vec(py, values))?;
+        }
+
+        Ok(dict)
+    }
+}
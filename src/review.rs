@@ -0,0 +1,154 @@
+use crate::paths;
+use crate::scanner::load_image;
+use image::{imageops::FilterType, GenericImageView};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// What the user made of a flagged file.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Delete,
+    Keep,
+}
+
+impl Decision {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Decision::Delete => "delete",
+            Decision::Keep => "keep",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Decision> {
+        match value {
+            "delete" => Some(Decision::Delete),
+            "keep" => Some(Decision::Keep),
+            _ => None,
+        }
+    }
+}
+
+/// Decisions taken during a review, by path, kept in a JSON file
+/// so that a review can be resumed or replayed.
+pub struct Decisions {
+    path: Option<String>,
+    decisions: BTreeMap<String, Decision>,
+}
+
+impl Decisions {
+    /// Read decisions from given file, if any and if it exists.
+    pub fn load(path: Option<String>) -> io::Result<Decisions> {
+        let mut decisions = BTreeMap::new();
+
+        if let Some(path) = path.as_deref().filter(|path| Path::new(path).exists()) {
+            let value: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+            let entries = value.as_object().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} doesn't hold a JSON object", path),
+                )
+            })?;
+
+            for (file, decision) in entries {
+                let decision = decision.as_str().and_then(Decision::parse).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("decision for {} must be 'delete' or 'keep'", file),
+                    )
+                })?;
+                decisions.insert(file.clone(), decision);
+            }
+        }
+
+        Ok(Decisions { path, decisions })
+    }
+
+    pub fn get(&self, path: &str) -> Option<Decision> {
+        self.decisions.get(path).copied()
+    }
+
+    /// Record a decision, writing the file right away so that
+    /// an interrupted review loses nothing.
+    pub fn set(&mut self, path: &str, decision: Decision) -> io::Result<()> {
+        self.decisions.insert(path.to_string(), decision);
+        self.save()
+    }
+
+    /// Paths the user chose to delete.
+    pub fn deleted(&self) -> Vec<String> {
+        self.decisions
+            .iter()
+            .filter(|(_, &decision)| decision == Decision::Delete)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let entries: Map<String, Value> = self
+            .decisions
+            .iter()
+            .map(|(file, decision)| (file.clone(), Value::from(decision.as_str())))
+            .collect();
+        fs::write(path, serde_json::to_string_pretty(&entries)?)
+    }
+}
+
+/// Render given image for a terminal, two pixels per character using
+/// half blocks and 24 bits colors. Return None if it can't be decoded.
+pub fn preview(path: &str, width: u32) -> Option<String> {
+    let data = fs::read(paths::decode(path)).ok()?;
+    let image = load_image(path, &data).ok()?;
+
+    // Characters are about twice as high as wide, two pixels each keep proportions
+    let height = (width * image.height() / image.width().max(1)).max(2);
+    let image = image
+        .resize_exact(width, height + height % 2, FilterType::Triangle)
+        .to_rgb8();
+
+    let mut lines = Vec::new();
+    for y in (0..image.height()).step_by(2) {
+        let mut text = String::new();
+        for x in 0..image.width() {
+            let top = image.get_pixel(x, y);
+            let bottom = image.get_pixel(x, y + 1);
+            text.push_str(&format!(
+                "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}",
+                top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+            ));
+        }
+        text.push_str("\x1b[0m");
+        lines.push(text);
+    }
+
+    Some(lines.join("\n"))
+}
+
+/// Open given file with the system image viewer, without waiting for it.
+pub fn open_viewer(path: &str) -> io::Result<()> {
+    let path = paths::decode(path);
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+
+    command
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
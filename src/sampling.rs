@@ -0,0 +1,158 @@
+use crate::paths;
+use crate::report::{Reason, Report};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::BTreeMap;
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+/// Quantile of the normal distribution bounding 95% confidence intervals.
+const Z: f64 = 1.96;
+
+/// Position of given path in the random order set by seed, between 0 and 1.
+/// The same path always lands at the same place, so that a sample doesn't
+/// depend on the order files are listed in.
+fn draw(path: &str, seed: u64) -> f64 {
+    xxh3_64_with_seed(path.as_bytes(), seed) as f64 / u64::MAX as f64
+}
+
+/// Check if given path is part of a random sample of given fraction.
+pub fn keeps(path: &str, fraction: f64, seed: u64) -> bool {
+    draw(path, seed) < fraction
+}
+
+/// Pick `count` of given paths at random, in the order they're given.
+pub fn pick(paths: Vec<String>, count: usize, seed: u64) -> Vec<String> {
+    if paths.len() <= count {
+        return paths;
+    }
+
+    let draws: Vec<f64> = paths.iter().map(|path| draw(path, seed)).collect();
+    let mut sorted = draws.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let cutoff = sorted[count - 1];
+
+    let mut picked = Vec::with_capacity(count);
+    for (path, draw) in paths.into_iter().zip(draws) {
+        if draw <= cutoff && picked.len() < count {
+            picked.push(path);
+        }
+    }
+    picked
+}
+
+/// Proportion of a sample, with the bounds of its 95% confidence interval
+/// (Wilson score interval, which holds for rare events).
+struct Rate {
+    count: usize,
+    rate: f64,
+    low: f64,
+    high: f64,
+}
+
+impl Rate {
+    fn new(count: usize, sampled: usize) -> Self {
+        if sampled == 0 {
+            return Rate {
+                count,
+                rate: 0.0,
+                low: 0.0,
+                high: 1.0,
+            };
+        }
+
+        let n = sampled as f64;
+        let rate = count as f64 / n;
+        let denominator = 1.0 + Z * Z / n;
+        let center = (rate + Z * Z / (2.0 * n)) / denominator;
+        let margin = Z * (rate * (1.0 - rate) / n + Z * Z / (4.0 * n * n)).sqrt() / denominator;
+
+        Rate {
+            count,
+            rate,
+            low: (center - margin).max(0.0),
+            high: (center + margin).min(1.0),
+        }
+    }
+
+    /// Convert rate to a python dictionary, along with the number of
+    /// files of the whole dataset it stands for.
+    fn to_dict<'py>(&self, py: Python<'py>, total: u64) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("count", self.count)?;
+        dict.set_item("rate", self.rate)?;
+        dict.set_item("low", self.low)?;
+        dict.set_item("high", self.high)?;
+        dict.set_item("estimate", (self.rate * total as f64).round() as u64)?;
+        Ok(dict)
+    }
+}
+
+/// Estimate of how dirty a dataset is, from reports of a sample of it.
+pub struct Audit {
+    /// Files of the dataset, sampled or not.
+    total: u64,
+    sampled: usize,
+    flagged: Rate,
+    /// Files each reason was found for, whatever its severity.
+    reasons: BTreeMap<&'static str, Rate>,
+    /// Paths of sampled files that got flagged.
+    flagged_paths: Vec<String>,
+}
+
+impl Audit {
+    pub fn new(reports: &[Report], total: u64) -> Self {
+        let sampled = reports.len();
+        let mut counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+        for report in reports {
+            let found: Vec<&Reason> = report
+                .reasons
+                .iter()
+                .chain(&report.warnings)
+                .chain(&report.infos)
+                .chain(&report.suspects)
+                .collect();
+            for reason in Reason::ALL.iter().filter(|reason| found.contains(reason)) {
+                *counts.entry(reason.as_str()).or_default() += 1;
+            }
+        }
+
+        let flagged_paths: Vec<String> = reports
+            .iter()
+            .filter(|report| report.is_flagged())
+            .map(|report| report.path.clone())
+            .collect();
+
+        Audit {
+            total,
+            sampled,
+            flagged: Rate::new(flagged_paths.len(), sampled),
+            reasons: counts
+                .into_iter()
+                .map(|(reason, count)| (reason, Rate::new(count, sampled)))
+                .collect(),
+            flagged_paths,
+        }
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("total", self.total)?;
+        dict.set_item("sampled", self.sampled)?;
+        dict.set_item("flagged", self.flagged.to_dict(py, self.total)?)?;
+
+        let reasons = PyDict::new(py);
+        for (reason, rate) in &self.reasons {
+            reasons.set_item(reason, rate.to_dict(py, self.total)?)?;
+        }
+        dict.set_item("reasons", reasons)?;
+
+        let flagged_paths: Vec<PyObject> = self
+            .flagged_paths
+            .iter()
+            .map(|path| paths::to_py(py, path))
+            .collect();
+        dict.set_item("flagged_paths", flagged_paths)?;
+
+        Ok(dict)
+    }
+}
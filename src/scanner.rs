@@ -0,0 +1,1758 @@
+use crate::animation::{load_animation, Animation};
+use crate::archive;
+use crate::budget::{self, MemoryBudget, Reservation};
+use crate::cache::{Cache, Stamp};
+use crate::captions;
+use crate::checkpoint::Checkpoint;
+use crate::checks::{
+    blur_score, channel_layout, channel_moments, check_banding, check_grayscale, check_solid_color,
+    clipping, color_cast, color_features, content_area, content_box, corner_edges, dead_lines,
+    detail_score, distinct_levels, downscale, entropy_score, flat_area, hugs_edge,
+    is_screen_resolution, is_solid_color, periodicity, sharp_edges, tile_repetition,
+};
+use crate::cloud;
+use crate::config::{
+    AnimationPolicy, BurstOrder, Config, DuplicateMatch, GpsPolicy, KeepPolicy, ModelFlag, Verify,
+};
+use crate::dicom;
+use crate::exif::{self, Exif};
+use crate::gpu::Gpu;
+use crate::hash;
+use crate::hash_index::HashIndex;
+use crate::header;
+use crate::heic;
+use crate::http::Downloader;
+use crate::integrity;
+use crate::journal::{Action, Journal};
+use crate::manifest;
+use crate::model::Model;
+use crate::normalization;
+use crate::output;
+use crate::pages::{open_pages, Pages};
+use crate::paths;
+use crate::progress::Progress;
+use crate::raw;
+use crate::report::{Reason, Report};
+use crate::sampling;
+use crate::throttle::Throttle;
+use crate::timings::Timings;
+use crate::video;
+use crate::walk::Walk;
+use crate::webdataset::{self, Sample, ShardWriter};
+use image::{
+    io::Reader as ImageReader, DynamicImage, GenericImageView, ImageError, ImageFormat, ImageResult,
+};
+use log::{debug, warn};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::borrow::Cow;
+use std::fs::{self, read_dir};
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Files read ahead per I/O thread, waiting to be checked.
+const PREFETCH_PER_THREAD: usize = 4;
+
+/// Content of a file read ahead of its checks, if it was.
+type Prefetched = Option<io::Result<ImageResult<Vec<u8>>>>;
+
+fn thread_pool(threads: usize) -> io::Result<ThreadPool> {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(io::Error::other)
+}
+
+/// Run given work on given pool, or the global one.
+fn install<R: Send>(pool: Option<&ThreadPool>, work: impl FnOnce() -> R + Send) -> R {
+    match pool {
+        Some(pool) => pool.install(work),
+        None => work(),
+    }
+}
+
+/// Decode image from file content. Format is guessed from magic bytes,
+/// falling back to the file name extension.
+pub fn load_image(name: &str, data: &[u8]) -> ImageResult<DynamicImage> {
+    // The image crate doesn't know about HEIC/HEIF
+    if heic::is_heic(data) {
+        return heic::load_heic(data);
+    }
+
+    let mut reader = ImageReader::new(Cursor::new(data));
+    if let Ok(format) = ImageFormat::from_path(name) {
+        reader.set_format(format);
+    }
+
+    reader.with_guessed_format()?.decode()
+}
+
+/// List files contained in given folder.
+fn list_files(folder: &str) -> io::Result<Vec<PathBuf>> {
+    read_dir(folder)?
+        .map(|res| res.map(|e| e.path()))
+        .collect::<Result<Vec<_>, io::Error>>()
+}
+
+/// Compute perceptual hashes of reference placeholder images.
+/// Files that can't be loaded as images are ignored.
+fn load_placeholders(folder: &str) -> io::Result<HashIndex> {
+    let hashes = list_files(folder)?
+        .par_iter()
+        .filter_map(|path| {
+            let data = fs::read(path).ok()?;
+            load_image(&path.to_string_lossy(), &data).ok()
+        })
+        .map(|image| hash::perceptual_hash(&image))
+        .collect();
+
+    Ok(HashIndex::new(hashes))
+}
+
+/// Report of a file that couldn't be read.
+fn unreadable(path: &str, err: io::Error) -> Report {
+    debug!("{}: unreadable: {}", path, err);
+    let mut report = Report::new(path);
+    if err.kind() == io::ErrorKind::NotFound {
+        report.fail_as(Reason::Missing, err);
+    } else {
+        report.fail(err);
+    }
+    report
+}
+
+/// Report of a file whose header tells it's not an image.
+fn undecodable(path: &str, err: ImageError) -> Report {
+    debug!("{}: undecodable: {}", path, err);
+    let mut report = Report::new(path);
+    report.fail(err);
+    report
+}
+
+/// Reasons shared by every given report.
+fn common_reasons(reports: &[Report]) -> Vec<Reason> {
+    match reports.split_first() {
+        Some((first, others)) => first
+            .reasons
+            .iter()
+            .filter(|reason| others.iter().all(|other| other.reasons.contains(reason)))
+            .copied()
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Run enabled checks over files of a folder.
+pub struct Scanner {
+    config: Config,
+    /// Perceptual hashes of reference placeholders.
+    placeholders: HashIndex,
+    /// Reports of a previous scan.
+    cache: Option<Cache>,
+    /// Reports of the current scan, written as it goes.
+    checkpoint: Option<Checkpoint>,
+    /// Where reports are sent as soon as they're produced.
+    results: Option<SyncSender<Report>>,
+    /// Device computing pixel statistics, if enabled.
+    gpu: Option<Gpu>,
+    /// Classifier images are run through, if given.
+    model: Option<Model>,
+    /// Bytes of images in memory at once, if limited.
+    budget: Option<MemoryBudget>,
+    /// Rates of reads, if limited.
+    throttle: Option<Throttle>,
+    /// Time spent in each stage of checks, if recorded.
+    timings: Option<Arc<Timings>>,
+    /// Files listed so far, sampled or not.
+    listed: Arc<AtomicU64>,
+    /// Bytes of files checked so far.
+    bytes_read: Arc<AtomicU64>,
+    /// Files whose report was reused from the cache so far.
+    cache_hits: Arc<AtomicU64>,
+    /// Files flagged so far, counted when aborting past `max_flagged`.
+    flagged: AtomicUsize,
+    /// Where renamed files are recorded.
+    journal: Journal,
+}
+
+impl Scanner {
+    pub fn new(config: Config) -> io::Result<Self> {
+        // Hash known placeholders once, before scanning
+        let placeholders = match &config.placeholders {
+            Some(folder) => load_placeholders(folder)?,
+            None => HashIndex::new(Vec::new()),
+        };
+
+        let cache = match &config.cache {
+            Some(path) => Some(Cache::open(path, config.fingerprint(), !config.no_cache)?),
+            None => None,
+        };
+
+        let checkpoint = match &config.checkpoint {
+            Some(path) => Some(Checkpoint::open(
+                path,
+                &config.fingerprint(),
+                config.resume,
+            )?),
+            None => None,
+        };
+
+        let gpu = match config.gpu {
+            true => Some(Gpu::new()?),
+            false => None,
+        };
+
+        let model = match &config.model {
+            Some(_) => Some(Model::open(&config)?),
+            None => None,
+        };
+
+        let budget = config.memory_budget.map(MemoryBudget::new);
+        let timings = config.timings.then(Arc::default);
+        let throttle = match (config.max_read_rate, config.max_reads_per_second) {
+            (None, None) => None,
+            (bytes, reads) => Some(Throttle::new(bytes, reads)),
+        };
+        let journal = Journal::open(config.journal.as_deref())?;
+
+        Ok(Scanner {
+            config,
+            placeholders,
+            cache,
+            checkpoint,
+            results: None,
+            gpu,
+            model,
+            budget,
+            throttle,
+            timings,
+            listed: Arc::new(AtomicU64::new(0)),
+            bytes_read: Arc::new(AtomicU64::new(0)),
+            cache_hits: Arc::new(AtomicU64::new(0)),
+            flagged: AtomicUsize::new(0),
+            journal,
+        })
+    }
+
+    /// Count of files listed by scans, including those left out of
+    /// the sample. It goes on counting once the scanner is consumed.
+    pub fn listed(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.listed)
+    }
+
+    /// Count of bytes of files checked by scans, archives and downloads
+    /// included. It goes on counting once the scanner is consumed.
+    pub fn bytes_read(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.bytes_read)
+    }
+
+    /// Count of files whose report was reused from the cache.
+    /// It goes on counting once the scanner is consumed.
+    pub fn cache_hits(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.cache_hits)
+    }
+
+    /// Keep the random sample of given files configuration asks for.
+    fn sample(&self, paths: Vec<String>) -> Vec<String> {
+        self.listed.fetch_add(paths.len() as u64, Ordering::Relaxed);
+
+        match (self.config.sample, self.config.sample_n) {
+            (Some(_), _) => paths
+                .into_iter()
+                .filter(|path| self.config.samples(path))
+                .collect(),
+            (_, Some(count)) => sampling::pick(paths, count, self.config.sample_seed),
+            _ => paths,
+        }
+    }
+
+    /// Send reports to given channel as files get checked,
+    /// instead of only returning them once the scan is over.
+    pub fn stream_to(&mut self, results: SyncSender<Report>) {
+        self.results = Some(results);
+    }
+
+    /// Send reports of a checked file or URL, if streaming.
+    /// Fail once nobody reads them anymore, stopping the scan.
+    fn emit(&self, reports: &[Report]) -> io::Result<()> {
+        if let Some(results) = &self.results {
+            for report in reports {
+                results.send(report.clone()).map_err(|_| {
+                    io::Error::new(io::ErrorKind::BrokenPipe, "reports are not read anymore")
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Count flagged reports of a checked file or URL, failing once more
+    /// files are flagged than allowed, stopping the scan.
+    fn count_flagged(&self, reports: &[Report]) -> io::Result<()> {
+        if self.config.max_flagged.is_none() {
+            return Ok(());
+        }
+
+        let flagged = reports.iter().filter(|report| report.is_flagged()).count();
+        let flagged = self.flagged.fetch_add(flagged, Ordering::Relaxed) + flagged;
+        self.config.check_flagged(flagged, None)
+    }
+
+    /// Rename kept files whose extension is fixed, once the scan went
+    /// through without being aborted. Files flagged since, duplicates
+    /// included, keep their name, as well as those whose new name is taken.
+    pub fn rename_fixed(&self, reports: &mut [Report]) -> io::Result<()> {
+        if !self.config.fix_extensions || self.config.copy_clean_to.is_some() {
+            return Ok(());
+        }
+
+        for report in reports {
+            let renamed = match report.renamed.take() {
+                Some(renamed) if !report.is_flagged() => renamed,
+                _ => continue,
+            };
+
+            report.renamed = output::rename(&report.path, &renamed)?;
+            if let Some(renamed) = &report.renamed {
+                self.journal
+                    .record(Action::Rename, &report.path, Some(renamed))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write reports of checked files to the cache and remove
+    /// the checkpoint, once a scan went through.
+    pub fn finish(&self) -> io::Result<()> {
+        if let Some(cache) = &self.cache {
+            cache.save()?;
+        }
+        if let Some(checkpoint) = &self.checkpoint {
+            checkpoint.finish()?;
+        }
+
+        Ok(())
+    }
+
+    /// Run a check producing reports of given file or URL, unless
+    /// it went through before the scan got interrupted.
+    fn checkpointed(
+        &self,
+        key: &str,
+        check: impl FnOnce() -> io::Result<Vec<Report>>,
+    ) -> io::Result<Vec<Report>> {
+        // Reports are handed out as soon as they're checked
+        let check = || -> io::Result<Vec<Report>> {
+            Ok(check()?
+                .into_iter()
+                .map(|report| self.settle(report))
+                .collect())
+        };
+
+        let checkpoint = match &self.checkpoint {
+            Some(checkpoint) => checkpoint,
+            None => return check(),
+        };
+
+        if let Some(reports) = checkpoint.get(key) {
+            return Ok(reports);
+        }
+
+        let reports = check()?;
+        checkpoint.record(key, &reports)?;
+
+        Ok(reports)
+    }
+
+    /// Check every file of given folders, as a single scan.
+    /// Outputs mirror their files under the folder holding all of them.
+    pub fn scan(&self, root_folders: &[String]) -> io::Result<Vec<Report>> {
+        let (remote, local): (Vec<&String>, Vec<&String>) = root_folders
+            .iter()
+            .partition(|root_folder| cloud::is_remote(root_folder));
+
+        let mut reports = Vec::new();
+        for root_folder in remote {
+            reports.extend(self.scan_bucket(root_folder)?);
+        }
+        if local.is_empty() {
+            return Ok(reports);
+        }
+
+        let folders: Vec<PathBuf> = local.iter().map(|root| paths::decode(root)).collect();
+        for folder in &folders {
+            self.prepare_outputs(folder)?;
+        }
+        let root = paths::common_root(&folders);
+
+        let mut walks = Vec::new();
+        for root_folder in &local {
+            walks.push(self.walk(root_folder)?);
+        }
+        let listing = walks.into_iter().flatten();
+
+        // Picking a number of files takes the whole listing
+        if self.config.sample_n.is_some() {
+            let paths = listing
+                .map(|path| Ok(paths::encode(&path?)))
+                .collect::<io::Result<Vec<String>>>()?;
+            reports.extend(self.scan_files(&paths, &root)?);
+            return Ok(reports);
+        }
+
+        // Check files as they're listed rather than once the listing is
+        // over, which takes minutes for huge folders on network filesystems
+        let progress = self.progress(0);
+        let paths = listing
+            .map(|path| Ok(paths::encode(&path?)))
+            .filter(|path: &io::Result<String>| {
+                path.as_ref().map_or(true, |path| self.config.selects(path))
+            })
+            .inspect(|_| {
+                self.listed.fetch_add(1, Ordering::Relaxed);
+            })
+            .filter(|path: &io::Result<String>| {
+                path.as_ref().map_or(true, |path| self.config.samples(path))
+            })
+            .inspect(|_| progress.inc_length())
+            .par_bridge();
+
+        reports.extend(self.scan_paths(paths, &progress, &root)?);
+        Ok(reports)
+    }
+
+    /// Check every file of given folders both the way configuration asks
+    /// and the way the one of another scanner does, reading each file once.
+    /// Files are only checked, nothing gets copied or renamed.
+    pub fn scan_both(
+        &self,
+        other: &Scanner,
+        root_folders: &[String],
+    ) -> io::Result<Vec<(Report, Report)>> {
+        let mut paths = Vec::new();
+        for root_folder in root_folders {
+            for path in self.walk(root_folder)? {
+                let path = paths::encode(&path?);
+                if self.config.selects(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        let paths = self.sample(paths);
+
+        let progress = self.progress(paths.len() as u64);
+        let reports = paths
+            .par_iter()
+            .map(|path| {
+                let (report, other_report) = match self.read_checked(path) {
+                    Ok(Ok(data)) => (
+                        self.check_bytes(path, &data),
+                        other.check_bytes(path, &data),
+                    ),
+                    Ok(Err(err)) => {
+                        let report = undecodable(path, err);
+                        (report.clone(), report)
+                    }
+                    Err(err) => {
+                        let report = unreadable(path, err);
+                        (report.clone(), report)
+                    }
+                };
+                let reports = (self.settle(report), other.settle(other_report));
+                progress.inc(std::slice::from_ref(&reports.0));
+                reports
+            })
+            .collect();
+        progress.finish();
+
+        Ok(reports)
+    }
+
+    /// List files of given folder the way configuration asks.
+    fn walk(&self, root_folder: &str) -> io::Result<Walk> {
+        Walk::new(
+            root_folder,
+            self.config.recursive,
+            self.config.follow_symlinks,
+            self.config.modified(),
+        )
+    }
+
+    /// Check every file listed in given manifest, "-" reading it from stdin.
+    pub fn scan_manifest(&self, manifest: &str) -> io::Result<Vec<Report>> {
+        let mut paths = manifest::read_manifest(manifest, self.config.manifest_column.as_deref())?;
+        let modified = self.config.modified();
+        if modified.is_set() {
+            paths.retain(|path| modified.contains(&paths::decode(path)));
+        }
+
+        // Listed paths are relative to the manifest
+        let manifest_path = paths::decode(manifest);
+        let root = match manifest {
+            "-" => Path::new("."),
+            _ => manifest_path.parent().unwrap_or_else(|| Path::new(".")),
+        };
+        self.prepare_outputs(root)?;
+
+        self.scan_files(&paths, root)
+    }
+
+    /// Create enabled output folders.
+    fn prepare_outputs(&self, root: &Path) -> io::Result<()> {
+        if let Some(output) = &self.config.webdataset_output {
+            output::prepare(output, root, "webdataset_output")?;
+        }
+        if let Some(output) = &self.config.copy_clean_to {
+            output::prepare(output, root, "copy_clean_to")?;
+        }
+
+        Ok(())
+    }
+
+    /// Check given files, looking into archives and shards if enabled.
+    fn scan_files(&self, paths: &[String], root: &Path) -> io::Result<Vec<Report>> {
+        let paths: Vec<String> = paths
+            .iter()
+            .filter(|path| self.config.selects(path))
+            .cloned()
+            .collect();
+        let paths = self.sample(paths);
+
+        let progress = self.progress(paths.len() as u64);
+        self.scan_paths(paths.into_par_iter().map(Ok), &progress, root)
+    }
+
+    /// Check files as they come from given iterator, stopping at the first
+    /// error it yields. With `io_threads`, files are read ahead on a pool
+    /// of their own while checks run on the compute one.
+    fn scan_paths(
+        &self,
+        paths: impl ParallelIterator<Item = io::Result<String>>,
+        progress: &Progress,
+        root: &Path,
+    ) -> io::Result<Vec<Report>> {
+        let compute = match self.config.compute_threads {
+            Some(threads) => Some(thread_pool(threads)?),
+            None => None,
+        };
+
+        let threads = match self.config.io_threads {
+            Some(threads) => threads,
+            None => {
+                let paths = paths.map(|path| path.map(|path| (path, None)));
+                return install(compute.as_ref(), || self.check_paths(paths, progress, root));
+            }
+        };
+
+        let io = thread_pool(threads)?;
+        // Bounded, so that read files don't pile up if checks are slower
+        let (sender, receiver) = mpsc::sync_channel(threads * PREFETCH_PER_THREAD);
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                io.install(|| {
+                    paths
+                        .map(|path| {
+                            path.map(|path| {
+                                let data = self.prefetch(&path);
+                                (path, data)
+                            })
+                        })
+                        .try_for_each_with(sender, |sender, read| {
+                            // Checks stopped at an error, stop reading as well
+                            sender.send(read).map_err(|_| ())
+                        })
+                })
+            });
+
+            install(compute.as_ref(), || {
+                self.check_paths(receiver.into_iter().par_bridge(), progress, root)
+            })
+        })
+    }
+
+    /// Read given file ahead of its checks, unless it's an archive or
+    /// shard read some other way, or it won't need to be read at all.
+    fn prefetch(&self, path: &str) -> Prefetched {
+        if (self.config.webdataset && archive::is_tar(path))
+            || (self.config.archives && archive::is_archive(path))
+            || (self.config.captions && captions::is_caption(path))
+            || self.config.verify == Verify::Fast
+            || self
+                .checkpoint
+                .as_ref()
+                .is_some_and(|checkpoint| checkpoint.contains(path))
+        {
+            return None;
+        }
+
+        if let Some(cache) = &self.cache {
+            if let Ok(stamp) = Stamp::of(path) {
+                if self.cached(cache, path, stamp).is_some() {
+                    return None;
+                }
+            }
+        }
+
+        Some(self.read_checked(path))
+    }
+
+    /// Read given file as `header::read_checked` does, within allowed
+    /// read rates.
+    fn read_checked(&self, path: &str) -> io::Result<ImageResult<Vec<u8>>> {
+        let data = self.timed("read", || header::read_checked(path));
+        self.throttle(match &data {
+            Ok(Ok(data)) => data.len(),
+            // Only the header was read
+            _ => 0,
+        });
+        data
+    }
+
+    /// Time spent in each stage of checks, if recorded. It goes on
+    /// counting once the scanner is consumed.
+    pub fn timings(&self) -> Option<Arc<Timings>> {
+        self.timings.clone()
+    }
+
+    /// Run given stage of checks, recording the time it takes if asked.
+    fn timed<T>(&self, stage: &'static str, run: impl FnOnce() -> T) -> T {
+        match &self.timings {
+            Some(timings) => {
+                let started = Instant::now();
+                let result = run();
+                timings.stage(stage, started.elapsed());
+                result
+            }
+            None => run(),
+        }
+    }
+
+    /// Flag given file if its header claims dimensions or a decoded size
+    /// over the allowed ones, before any pixel is decoded. Pages of
+    /// multipage TIFFs are decoded one at a time, only their first one is
+    /// told apart here.
+    fn exceeds_limits(&self, data: &[u8], report: &mut Report) -> bool {
+        let config = &self.config;
+        if config.max_decode_dimension.is_none() && config.max_decoded_bytes.is_none() {
+            return false;
+        }
+        let (width, height) = match header::dimensions(data) {
+            Some(dimensions) => dimensions,
+            None => return false,
+        };
+
+        if let Some(max) = config.max_decode_dimension {
+            if width.max(height) > max {
+                report.fail_as(
+                    Reason::ResourceLimit,
+                    format!(
+                        "{}x{} exceeds max_decode_dimension ({})",
+                        width, height, max
+                    ),
+                );
+                return true;
+            }
+        }
+        // Decoders work on 8 bits RGBA at most for the checks
+        let bytes = width as u64 * height as u64 * 4;
+        if let Some(max) = config.max_decoded_bytes {
+            if bytes > max {
+                report.fail_as(
+                    Reason::ResourceLimit,
+                    format!("{} decoded bytes exceed max_decoded_bytes ({})", bytes, max),
+                );
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Decode given file with given decoder, giving up past
+    /// `decode_timeout`. The decoder then goes on in the background, its
+    /// result dropped, as decoders can't be interrupted. Errors flag the
+    /// file, as resource limits if they're the cause.
+    fn decode<T: Send + 'static>(
+        &self,
+        data: &[u8],
+        report: &mut Report,
+        decode: impl FnOnce(&[u8]) -> ImageResult<T> + Send + 'static,
+    ) -> Option<T> {
+        let result = self.timed("decode", || match self.config.decode_timeout {
+            Some(timeout) => {
+                let data = data.to_vec();
+                let (sender, receiver) = mpsc::sync_channel(1);
+                thread::spawn(move || sender.send(decode(&data)).ok());
+                receiver.recv_timeout(Duration::from_secs_f64(timeout))
+            }
+            None => Ok(decode(data)),
+        });
+
+        match result {
+            Ok(Ok(decoded)) => Some(decoded),
+            Ok(Err(ImageError::Limits(err))) => {
+                report.fail_as(Reason::ResourceLimit, err);
+                None
+            }
+            Ok(Err(err)) => {
+                report.fail(err);
+                None
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                report.fail_as(
+                    Reason::ResourceLimit,
+                    format!(
+                        "decoding took longer than decode_timeout ({}s)",
+                        self.config.decode_timeout.unwrap_or_default()
+                    ),
+                );
+                None
+            }
+            // The decoder panicked on its own thread
+            Err(RecvTimeoutError::Disconnected) => {
+                report.fail("decoder panicked");
+                None
+            }
+        }
+    }
+
+    /// Wait for a read of given bytes to fit in allowed read rates.
+    fn throttle(&self, bytes: usize) {
+        if let Some(throttle) = &self.throttle {
+            throttle.wait(bytes as u64);
+        }
+    }
+
+    /// Check files as they come from given iterator, along with their
+    /// content if it was read ahead.
+    fn check_paths(
+        &self,
+        paths: impl ParallelIterator<Item = io::Result<(String, Prefetched)>>,
+        progress: &Progress,
+        root: &Path,
+    ) -> io::Result<Vec<Report>> {
+        let reports: Vec<Vec<Report>> = paths
+            .map(|path| {
+                let (path, data) = path?;
+                let path = &path;
+                let reports = self.checkpointed(path, || {
+                    if self.config.webdataset && archive::is_tar(path) {
+                        Ok(self.scan_shard(path))
+                    } else if self.config.archives && archive::is_archive(path) {
+                        Ok(self.scan_archive(path))
+                    } else if self.config.captions && captions::is_caption(path) {
+                        Ok(captions::orphan(path).into_iter().collect())
+                    } else {
+                        let started = Instant::now();
+                        let mut report = self.check_file(path, root, data)?;
+                        if let Some(timings) = &self.timings {
+                            timings.file(path, started.elapsed());
+                        }
+                        // Captions change on their own, they aren't cached along with images
+                        if self.config.captions {
+                            captions::check(&mut report);
+                        }
+                        Ok(vec![report])
+                    }
+                })?;
+
+                self.emit(&reports)?;
+                self.count_flagged(&reports)?;
+                progress.inc(&reports);
+
+                Ok(reports)
+            })
+            .collect::<io::Result<_>>()?;
+        progress.finish();
+
+        Ok(reports.into_iter().flatten().collect())
+    }
+
+    /// Run enabled checks over a regular file, reusing its cached report
+    /// if it didn't change since the previous scan. Its content is read
+    /// unless it was read ahead.
+    fn check_file(&self, path: &str, root: &Path, data: Prefetched) -> io::Result<Report> {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return self.check_uncached(path, root, data),
+        };
+
+        let stamp = match Stamp::of(path) {
+            Ok(stamp) => stamp,
+            Err(err) => return Ok(unreadable(path, err)),
+        };
+
+        let report = match self.cached(cache, path, stamp) {
+            Some(report) => {
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                report
+            }
+            None => self.check_uncached(path, root, data)?,
+        };
+        // Reasons of protected files are cleared, they would be lost
+        // once the file isn't protected anymore
+        if !self.config.protect.contains(path) {
+            cache.insert(path, stamp, &report);
+        }
+
+        Ok(report)
+    }
+
+    /// Cached report of given file, if it didn't change since the
+    /// previous scan. Kept files must be read again to be copied or renamed.
+    fn cached(&self, cache: &Cache, path: &str, stamp: Stamp) -> Option<Report> {
+        cache
+            .get(path, stamp)
+            .map(|report| self.settle(report))
+            .filter(|report| {
+                report.is_flagged()
+                    || (self.config.copy_clean_to.is_none() && !self.config.fix_extensions)
+            })
+    }
+
+    /// Run enabled checks over a regular file, copying it to the cleaned
+    /// dataset if it passes them. Its extension is fixed on the way.
+    fn check_uncached(&self, path: &str, root: &Path, data: Prefetched) -> io::Result<Report> {
+        let copied = self.config.copy_clean_to.is_some() || self.config.fix_extensions;
+        // Only the header is read, unless the file is copied or renamed,
+        // or its size weighed against its dimensions
+        if self.config.verify == Verify::Fast
+            && !copied
+            && self.config.min_bytes_per_pixel.is_none()
+        {
+            let mut report = Report::new(path);
+            let verified = self.timed("read", || header::verify_header(path));
+            self.throttle(0);
+            match verified {
+                Ok(Ok(true)) => report.flag(Reason::Video),
+                Ok(Ok(false)) => {}
+                Ok(Err(err)) => report.fail(err),
+                Err(err) => return Ok(self.settle(unreadable(path, err))),
+            }
+            return Ok(self.settle(report));
+        }
+
+        let data = data.unwrap_or_else(|| self.read_checked(path));
+        if !copied {
+            return Ok(self.settle(self.check_read(path, data)));
+        }
+
+        let data = match data {
+            Ok(Ok(data)) => data,
+            Ok(Err(err)) => return Ok(self.settle(undecodable(path, err))),
+            Err(err) => return Ok(self.settle(unreadable(path, err))),
+        };
+
+        let _reservation = self.reserve(&data);
+        let (report, image) = self.check_decoded(path, &data);
+        let mut report = self.settle(report);
+        // Flagged for its caption once checked, an image isn't kept without one
+        if report.is_flagged() || (self.config.captions && captions::problem(path).is_some()) {
+            return Ok(report);
+        }
+
+        let extension = match self.config.fix_extensions {
+            true => header::fixed_extension(path, &data),
+            false => None,
+        };
+        match &self.config.copy_clean_to {
+            Some(output) => {
+                let mut destination = output::destination(output, root, path);
+                if let Some(extension) = extension {
+                    destination.set_extension(extension);
+                }
+                self.copy_clean(path, &data, &destination, &report, image)?;
+                if self.config.captions {
+                    fs::copy(
+                        captions::caption_path(path),
+                        captions::caption_path(&paths::encode(&destination)),
+                    )?;
+                }
+            }
+            // Renamed by `rename_fixed` once the scan went through
+            None => {
+                report.renamed = extension.map(|extension| output::with_extension(path, extension));
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Sort reasons of given report by severity, clearing those
+    /// that would get it deleted if its file is protected.
+    fn settle(&self, mut report: Report) -> Report {
+        self.config.settle(&mut report);
+        report
+    }
+
+    /// Write a kept file to the cleaned dataset, re-encoding its decoded
+    /// image if normalization or metadata stripping is enabled.
+    fn copy_clean(
+        &self,
+        path: &str,
+        data: &[u8],
+        destination: &Path,
+        report: &Report,
+        image: Option<DynamicImage>,
+    ) -> io::Result<()> {
+        let animated = report
+            .metrics
+            .get("frames")
+            .is_some_and(|frames| *frames > 1.0);
+
+        match image {
+            Some(image) if self.config.normalizes() => {
+                let destination = if animated {
+                    destination.with_extension("png")
+                } else {
+                    destination.to_path_buf()
+                };
+                let orientation = Exif::read(data).orientation;
+                output::write_normalized(&image, orientation, &destination, &self.config)
+            }
+            // Only the first frame was checked, only keep that one
+            Some(image) if animated => output::write_image(
+                &output::conform(image, &self.config),
+                &destination.with_extension("png"),
+            ),
+            Some(image)
+                if self.config.fixes_contract()
+                    && self.config.breaks_contract(channel_layout(&image)) =>
+            {
+                output::write_image(&output::conform(image, &self.config), destination)
+            }
+            _ => output::copy_file(path, destination, self.config.hard_link),
+        }
+    }
+
+    /// Check every object directly under given bucket prefix.
+    /// Reports are named after the full object URL.
+    #[cfg(feature = "cloud")]
+    fn scan_bucket(&self, root: &str) -> io::Result<Vec<Report>> {
+        let bucket = cloud::Bucket::open(root)?;
+        let keys = self.sample(bucket.list()?);
+
+        let progress = self.progress(keys.len() as u64);
+
+        let reports: Vec<Vec<Report>> = keys
+            .par_iter()
+            .map(|key| {
+                let url = bucket.url(key);
+                let reports = self.checkpointed(&url, || {
+                    Ok(vec![match self.timed("download", || bucket.read(key)) {
+                        Ok(data) => {
+                            self.throttle(data.len());
+                            self.check_bytes(&url, &data)
+                        }
+                        Err(err) => {
+                            let mut report = Report::new(&url);
+                            report.fail_as(Reason::Network, err);
+                            report
+                        }
+                    }])
+                })?;
+
+                self.emit(&reports)?;
+                self.count_flagged(&reports)?;
+                progress.inc(&reports);
+
+                Ok(reports)
+            })
+            .collect::<io::Result<_>>()?;
+        progress.finish();
+
+        Ok(reports.into_iter().flatten().collect())
+    }
+
+    #[cfg(not(feature = "cloud"))]
+    fn scan_bucket(&self, root: &str) -> io::Result<Vec<Report>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "can't scan {}, cleanax was built without the cloud feature",
+                root
+            ),
+        ))
+    }
+
+    /// Download and check every given URL. Downloads run on their own
+    /// pool, bounding the number of simultaneous connections.
+    pub fn scan_urls(&self, urls: &[String]) -> io::Result<Vec<Report>> {
+        let downloader = Downloader::new(
+            Duration::from_secs_f64(self.config.http_timeout),
+            self.config.max_download_size,
+        );
+        let pool = thread_pool(self.config.http_concurrency)?;
+
+        let progress = self.progress(urls.len() as u64);
+
+        let reports: Vec<Vec<Report>> = pool.install(|| {
+            urls.par_iter()
+                .map(|url| {
+                    let reports = self.checkpointed(url, || {
+                        Ok(vec![
+                            match self.timed("download", || downloader.download(url)) {
+                                Ok(data) => {
+                                    self.throttle(data.len());
+                                    self.check_bytes(url, &data)
+                                }
+                                Err(err) => {
+                                    let mut report = Report::new(url);
+                                    report.fail_as(Reason::Network, err);
+                                    report
+                                }
+                            },
+                        ])
+                    })?;
+
+                    self.emit(&reports)?;
+                    self.count_flagged(&reports)?;
+                    progress.inc(&reports);
+
+                    Ok(reports)
+                })
+                .collect::<io::Result<_>>()
+        })?;
+        progress.finish();
+
+        Ok(reports.into_iter().flatten().collect())
+    }
+
+    /// Check every file stored in given archive, without extracting it
+    /// to disk. Reports are named `archive.zip!path/inside.jpg`.
+    fn scan_archive(&self, path: &str) -> Vec<Report> {
+        archive::read_entries(path)
+            .into_iter()
+            .par_bridge()
+            .map(|entry| {
+                let name = match entry.name {
+                    Some(name) => format!("{}!{}", path, name),
+                    None => path.to_string(),
+                };
+
+                match entry.data {
+                    Ok(data) => {
+                        self.throttle(data.len());
+                        self.check_bytes(&name, &data)
+                    }
+                    Err(err) => {
+                        let mut report = Report::new(&name);
+                        report.fail(err);
+                        report
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Check every sample of given WebDataset shard, writing kept samples
+    /// to a new shard if an output folder is set. Reports are named
+    /// `shard.tar!key`.
+    fn scan_shard(&self, path: &str) -> Vec<Report> {
+        let mut reports = Vec::new();
+
+        if let Err(err) = self.filter_shard(path, &mut reports) {
+            let mut report = Report::new(path);
+            report.fail(err);
+            reports.push(report);
+        }
+
+        reports
+    }
+
+    fn filter_shard(&self, path: &str, reports: &mut Vec<Report>) -> io::Result<()> {
+        let mut writer = match &self.config.webdataset_output {
+            Some(output) => Some(ShardWriter::create(output, path)?),
+            None => None,
+        };
+
+        webdataset::read_samples(path, |samples| {
+            // Read by a single thread, a batch at a time
+            self.throttle(
+                samples
+                    .iter()
+                    .flat_map(|sample| &sample.members)
+                    .map(|member| member.data.len())
+                    .sum(),
+            );
+            let sample_reports: Vec<Report> = samples
+                .par_iter()
+                .map(|sample| self.check_sample(path, sample))
+                .collect();
+
+            if let Some(writer) = &mut writer {
+                for (sample, report) in samples.iter().zip(&sample_reports) {
+                    if !report.is_flagged() {
+                        writer.append(sample)?;
+                    }
+                }
+            }
+
+            reports.extend(sample_reports);
+            Ok(())
+        })?;
+
+        match writer {
+            Some(writer) => writer.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Run enabled checks over every image of a sample. The whole sample
+    /// is flagged as soon as one of its images is.
+    fn check_sample(&self, shard: &str, sample: &Sample) -> Report {
+        let mut report = Report::new(&format!("{}!{}", shard, sample.key));
+
+        for member in sample.members.iter().filter(|member| member.is_image()) {
+            let mut member_report =
+                self.check_bytes(&format!("{}!{}", shard, member.name), &member.data);
+
+            for reason in member_report.reasons {
+                if !report.reasons.contains(&reason) {
+                    report.flag(reason);
+                }
+            }
+
+            // Metrics and error are the ones of the first image
+            if report.metrics.is_empty() {
+                report.metrics.append(&mut member_report.metrics);
+                report.hash = report.hash.or(member_report.hash);
+                report.oriented_hashes = member_report.oriented_hashes;
+                report.pixel_hash = member_report.pixel_hash;
+            }
+            if report.error.is_none() {
+                report.error = member_report.error;
+            }
+        }
+
+        // Kept samples are written before reports are handed out
+        self.settle(report)
+    }
+
+    /// Run enabled checks over a single file, as `header::read_checked`
+    /// read it. Its header is checked first, it's only decoded if it passes.
+    fn check_read(&self, path: &str, data: io::Result<ImageResult<Vec<u8>>>) -> Report {
+        match data {
+            Ok(Ok(data)) => self.check_bytes(path, &data),
+            Ok(Err(err)) => undecodable(path, err),
+            Err(err) => unreadable(path, err),
+        }
+    }
+
+    /// Run enabled checks over content of a file, named after its path.
+    pub fn check_bytes(&self, name: &str, data: &[u8]) -> Report {
+        let _reservation = self.reserve(data);
+        self.check_decoded(name, data).0
+    }
+
+    /// Wait for given file and its decoded image to fit in the memory budget.
+    fn reserve(&self, data: &[u8]) -> Option<Reservation<'_>> {
+        let budget = self.budget.as_ref()?;
+        Some(budget.reserve(budget::decoded_size(data)))
+    }
+
+    /// Follow a scan of `total` files the way configuration asks.
+    fn progress(&self, total: u64) -> Progress {
+        Progress::new(self.config.progress, total)
+    }
+
+    /// Run enabled checks over content of a file, also returning the image
+    /// standing for it in a cleaned dataset: the decoded image, or the first
+    /// frame of an animation when only that one is kept. Animations and
+    /// multipage documents kept whole have none.
+    fn check_decoded(&self, name: &str, data: &[u8]) -> (Report, Option<DynamicImage>) {
+        self.bytes_read
+            .fetch_add(data.len() as u64, Ordering::Relaxed);
+        let mut report = Report::new(name);
+        if self.config.duplicates {
+            report.content_hash = Some(xxh3_64(data));
+        }
+        if self.config.keeps_duplicates_by(KeepPolicy::FileSize) {
+            report.metric("file_size", data.len() as f64);
+        }
+        // Also tells when frames of bursts were taken
+        if self.config.filters_metadata()
+            || (self.config.bursts && self.config.burst_order == BurstOrder::Time)
+        {
+            self.timed("metadata", || self.check_metadata(data, &mut report));
+        }
+        if self.config.decoder_warnings || self.config.verify == Verify::Strict {
+            report.decoder_warnings = self.timed("decoder_warnings", || integrity::warnings(data));
+            if let Some(warning) = report.decoder_warnings.first() {
+                if self.config.verify == Verify::Strict {
+                    report.error = Some(warning.clone());
+                    report.flag(Reason::Undecodable);
+                }
+                if self.config.decoder_warnings {
+                    report.flag(Reason::DecoderWarning);
+                }
+            }
+        }
+        // Fixed rather than flagged, once the file is known to be kept
+        if self.config.wrong_extensions
+            && !self.config.fix_extensions
+            && header::fixed_extension(name, data).is_some()
+        {
+            report.flag(Reason::WrongExtension);
+        }
+
+        // Stubs and heavily recompressed files aren't worth decoding
+        if let Some(min) = self.config.min_bytes_per_pixel {
+            if let Some((width, height)) = header::dimensions(data) {
+                let bytes_per_pixel = data.len() as f64 / (width as f64 * height as f64).max(1.0);
+                report.metric("bytes_per_pixel", bytes_per_pixel);
+                if bytes_per_pixel < min {
+                    report.flag(Reason::Overcompressed);
+                    return (report, None);
+                }
+            }
+        }
+
+        // Frames of a video are only sampled when asked, it's no image otherwise
+        if video::is_video(data) {
+            if self.config.video_frames == 0 || self.config.verify == Verify::Fast {
+                report.flag(Reason::Video);
+                return (report, None);
+            }
+
+            let (owned_name, samples) = (name.to_string(), self.config.video_frames);
+            if let Some(video) = self.decode(data, &mut report, move |data| {
+                video::load_video(&owned_name, data, samples)
+            }) {
+                self.check_video(video, &mut report);
+            }
+            return (report, None);
+        }
+
+        // Decodability is told by the header alone
+        if self.config.verify == Verify::Fast {
+            if let Err(err) = header::verify_data(name, data) {
+                report.fail(err);
+            }
+            return (report, None);
+        }
+
+        // Bombs and absurd dimensions aren't worth the memory and time
+        if self.exceeds_limits(data, &mut report) {
+            return (report, None);
+        }
+
+        if dicom::is_dicom(name, data) {
+            return match self.decode(data, &mut report, dicom::load_dicom) {
+                Some((image, uid)) => {
+                    report.sop_instance_uid = uid;
+                    self.check_image(&image, &mut report);
+                    (report, Some(image))
+                }
+                None => (report, None),
+            };
+        }
+
+        // Camera RAW files are TIFF based, don't mistake them for documents
+        if raw::is_raw(name) {
+            let preview = self.config.raw_preview;
+            let image = self.decode(data, &mut report, move |data| match preview {
+                true => raw::load_preview(data),
+                false => raw::load_raw(data),
+            });
+
+            return match image {
+                Some(image) => {
+                    self.check_image(&image, &mut report);
+                    (report, Some(image))
+                }
+                None => (report, None),
+            };
+        }
+
+        let samples = self.config.animation_samples;
+        // Broken GIF, APNG or WebP are given up on
+        match self.decode(data, &mut report, move |data| load_animation(data, samples)) {
+            Some(Some(animation)) if animation.frame_count > 1 => {
+                let frame = self.check_animation(animation, &mut report);
+                return (report, frame);
+            }
+            Some(Some(mut animation)) => {
+                // A still GIF, no need to decode it a second time
+                let image = animation.frames.remove(0);
+                self.check_image(&image, &mut report);
+                return (report, Some(image));
+            }
+            Some(None) => {}
+            None => return (report, None),
+        }
+
+        match open_pages(data) {
+            Ok(Some(pages)) => {
+                let page = self.check_pages(pages, &mut report);
+                return (report, page);
+            }
+            Ok(None) => {}
+            // Broken TIFF
+            Err(err) => {
+                report.fail(err);
+                return (report, None);
+            }
+        }
+
+        let owned_name = name.to_string();
+        let image = match self.decode(data, &mut report, move |data| load_image(&owned_name, data))
+        {
+            Some(image) => image,
+            // If file fails to be loaded as an image
+            // Delete it
+            None => return (report, None),
+        };
+
+        self.check_image(&image, &mut report);
+        if self.config.screenshots {
+            self.timed("screenshots", || {
+                self.check_screenshot(data, &image, &mut report)
+            });
+        }
+
+        (report, Some(image))
+    }
+
+    /// Apply animation policy to given animation, running checks over
+    /// sampled frames if needed. Return the first frame if it's the only
+    /// one kept.
+    fn check_animation(&self, animation: Animation, report: &mut Report) -> Option<DynamicImage> {
+        report.metric("frames", animation.frame_count as f64);
+        report.metric("duration", animation.duration);
+
+        let frames = match self.config.keep_animations {
+            AnimationPolicy::Flag => {
+                report.flag(Reason::Animated);
+                return None;
+            }
+            // Only the first frame will end up in the cleaned dataset
+            AnimationPolicy::FirstFrame => &animation.frames[..1],
+            AnimationPolicy::Keep => &animation.frames[..],
+        };
+        self.check_frames(frames, report);
+
+        match self.config.keep_animations {
+            AnimationPolicy::FirstFrame => animation.frames.into_iter().next(),
+            _ => None,
+        }
+    }
+
+    /// Run checks over frames sampled from a video, which stand for it.
+    fn check_video(&self, video: Animation, report: &mut Report) {
+        // Unknown to some containers
+        if video.frame_count > 0 {
+            report.metric("frames", video.frame_count as f64);
+        }
+        report.metric("duration", video.duration);
+
+        self.check_frames(&video.frames, report);
+    }
+
+    /// Run checks over frames of an animation or a video. A reason applies
+    /// to the whole of it when it applies to every frame, a single faded
+    /// out frame doesn't make it noise.
+    fn check_frames(&self, frames: &[DynamicImage], report: &mut Report) {
+        let mut frame_reports: Vec<Report> = frames
+            .iter()
+            .map(|frame| {
+                let mut frame_report = Report::new(&report.path);
+                self.check_image(frame, &mut frame_report);
+                frame_report
+            })
+            .collect();
+
+        report.reasons = common_reasons(&frame_reports);
+        // Metrics are the ones of the first frame
+        report.metrics.append(&mut frame_reports[0].metrics);
+        report.hash = frame_reports[0].hash;
+        report.oriented_hashes = std::mem::take(&mut frame_reports[0].oriented_hashes);
+        report.pixel_hash = frame_reports[0].pixel_hash;
+    }
+
+    /// Run checks over every page of a multipage document, each page
+    /// getting its own report. The document is flagged for reasons applying
+    /// to every page, a single blank page doesn't make it noise.
+    /// Return the page of a single page document.
+    fn check_pages(&self, pages: Pages, report: &mut Report) -> Option<DynamicImage> {
+        let mut first_page = None;
+        let mut page_reports: Vec<Report> = pages
+            .map(|page| {
+                let mut page_report = Report::new(&report.path);
+                match page {
+                    Ok(image) => {
+                        self.check_image(&image, &mut page_report);
+                        first_page.get_or_insert(image);
+                    }
+                    Err(err) => page_report.fail(err),
+                }
+                page_report
+            })
+            .collect();
+
+        // A regular single page TIFF
+        if page_reports.len() == 1 {
+            let page_report = page_reports.remove(0);
+            report.reasons = page_report.reasons;
+            report.metrics = page_report.metrics;
+            report.hash = page_report.hash;
+            report.oriented_hashes = page_report.oriented_hashes;
+            report.pixel_hash = page_report.pixel_hash;
+            return first_page;
+        }
+
+        for (index, page_report) in page_reports.iter_mut().enumerate() {
+            page_report.metric("page", index as f64);
+        }
+
+        report.metric("page_count", page_reports.len() as f64);
+        report.reasons = common_reasons(&page_reports);
+        report.pages = page_reports;
+
+        None
+    }
+
+    /// Filter given file on its EXIF metadata: capture date, camera and
+    /// GPS coordinates. When it was taken is reported as a Unix timestamp.
+    fn check_metadata(&self, data: &[u8], report: &mut Report) {
+        let exif = Exif::read(data);
+        report.metric("gps", exif.gps as u8 as f64);
+        if let Some(taken) = &exif.taken {
+            report.metric("taken", exif::timestamp(taken));
+        }
+
+        let too_old = match (&exif.taken, &self.config.taken_after) {
+            (Some(taken), Some(after)) => taken < after,
+            _ => false,
+        };
+        let too_recent = match (&exif.taken, &self.config.taken_before) {
+            (Some(taken), Some(before)) => taken >= before,
+            _ => false,
+        };
+        let camera = exif.camera().to_lowercase();
+        let flagged_camera = exif.has_camera()
+            && self
+                .config
+                .flag_cameras
+                .iter()
+                .any(|name| camera.contains(name.as_str()));
+        let gps = match self.config.flag_gps {
+            Some(GpsPolicy::Present) => exif.gps,
+            Some(GpsPolicy::Absent) => !exif.gps,
+            None => false,
+        };
+
+        if too_old
+            || too_recent
+            || flagged_camera
+            || gps
+            || (self.config.require_timestamp && exif.taken.is_none())
+        {
+            report.flag(Reason::Metadata);
+        }
+    }
+
+    /// Count signals of a screenshot shown by given image: dimensions of
+    /// a screen, no camera named by its metadata, flat backgrounds of user
+    /// interfaces and sharp edges of text. Each one is reported.
+    fn check_screenshot(&self, data: &[u8], image: &DynamicImage, report: &mut Report) {
+        let luma = image.to_luma8();
+        let (width, height) = image.dimensions();
+        let screen_resolution = is_screen_resolution(width, height);
+        let camera = Exif::read(data).has_camera();
+        let flat_area = flat_area(&luma);
+        let sharp_edges = sharp_edges(&luma);
+
+        let signals = [
+            screen_resolution,
+            !camera,
+            flat_area >= self.config.min_flat_area,
+            sharp_edges >= self.config.min_sharp_edges,
+        ]
+        .iter()
+        .filter(|signal| **signal)
+        .count();
+
+        report.metric("screen_resolution", screen_resolution as u8 as f64);
+        report.metric("camera_exif", camera as u8 as f64);
+        report.metric("flat_area", flat_area);
+        report.metric("sharp_edges", sharp_edges);
+        report.metric("screenshot_signals", signals as f64);
+
+        if signals >= self.config.screenshot_signals {
+            report.flag(Reason::Screenshot);
+        }
+    }
+
+    /// Run enabled checks over a decoded image.
+    fn check_image(&self, image: &DynamicImage, report: &mut Report) {
+        let profiling = self.config.profile.is_some();
+        // The sharpest frame of a burst is kept
+        let sharpest = self.config.keeps_duplicates_by(KeepPolicy::Sharpest) || self.config.bursts;
+
+        // The GPU goes through every pixel, of 8 bits color images only
+        let gpu_statistics = self.gpu.as_ref().and_then(|gpu| {
+            self.timed("gpu", || {
+                gpu.statistics(
+                    image,
+                    profiling || sharpest || self.config.blur,
+                    profiling || self.config.entropy,
+                )
+            })
+        });
+
+        // Statistics barely change on a smaller copy, or on sampled
+        // pixels, and cost a lot less
+        let stats_image = match self.config.stats_max_dimension {
+            Some(max_dimension) if self.config.stats_sampling.is_none() => {
+                downscale(image, max_dimension)
+            }
+            _ => Cow::Borrowed(image),
+        };
+
+        // Take most present color and standard deviation
+        // A very low standard deviation means a solid color image
+        let thresholds = self.config.solid_color_thresholds();
+        let solid_color = self.timed("solid_color", || match &gpu_statistics {
+            Some(statistics) => is_solid_color(&statistics.moments.1, &thresholds),
+            None => check_solid_color(&stats_image, self.config.stats_step(), &thresholds),
+        });
+        if solid_color {
+            report.flag(Reason::SolidColor);
+        }
+
+        if profiling || self.config.keeps_duplicates_by(KeepPolicy::Resolution) {
+            let (width, height) = image.dimensions();
+            report.metric("width", width as f64);
+            report.metric("height", height as f64);
+        }
+        if profiling {
+            for (name, value) in self.timed("profile", || channel_moments(&stats_image)) {
+                report.metric(name, value);
+            }
+        }
+        // Compared with the rest of the dataset once every file is checked
+        if self.config.outliers {
+            for (name, value) in &self.timed("outliers", || color_features(&stats_image)) {
+                report.metric(name, *value);
+            }
+        }
+        if profiling || sharpest || self.config.blur {
+            let blur = gpu_statistics
+                .as_ref()
+                .and_then(|statistics| statistics.blur)
+                .unwrap_or_else(|| self.timed("blur", || blur_score(image)));
+            report.metric("blur", blur);
+
+            if self.config.blur && blur < self.config.min_sharpness {
+                report.flag(Reason::Blurry);
+            }
+        }
+        if profiling || self.config.entropy {
+            let entropy = gpu_statistics
+                .as_ref()
+                .and_then(|statistics| statistics.entropy)
+                .unwrap_or_else(|| self.timed("entropy", || entropy_score(&stats_image)));
+            report.metric("entropy", entropy);
+
+            if self.config.entropy && entropy < self.config.min_entropy {
+                report.flag(Reason::LowEntropy);
+            }
+        }
+
+        if self.config.has_contract() {
+            let layout = channel_layout(image);
+            report.metric("channels", layout.0 as f64);
+            report.metric("bit_depth", layout.1 as f64);
+
+            // Converted on the way to the cleaned copy if asked
+            if self.config.breaks_contract(layout) && !self.config.fixes_contract() {
+                report.flag(Reason::ContractViolation);
+            }
+        }
+
+        if self.config.normalization {
+            self.timed("normalization", || normalization::record(image, report));
+        }
+
+        let hashed = profiling || self.config.duplicates || self.config.bursts;
+        if hashed || !self.placeholders.is_empty() {
+            let hash = self.timed("hash", || hash::perceptual_hash(image));
+            if hashed {
+                report.hash = Some(hash);
+            }
+            if self.config.duplicates && self.config.duplicate_orientations {
+                report.oriented_hashes = self.timed("hash", || hash::oriented_hashes(image));
+            }
+            if self.config.duplicates && self.config.duplicate_match == DuplicateMatch::Pixels {
+                report.pixel_hash = Some(self.timed("hash", || hash::pixel_hash(image)));
+            }
+
+            // Images looking like a known placeholder are noise too
+            if self
+                .placeholders
+                .contains_within(hash, self.config.placeholder_distance)
+            {
+                report.flag(Reason::Placeholder);
+            }
+        }
+
+        if self.config.grayscale
+            && self.timed("grayscale", || {
+                check_grayscale(image, self.config.grayscale_tolerance)
+            })
+        {
+            report.flag(Reason::Grayscale);
+        }
+
+        if self.config.upscaled {
+            let score = self.timed("upscaled", || detail_score(image));
+            report.metric("detail", score);
+
+            if score < self.config.min_detail {
+                report.flag(Reason::Upscaled);
+            }
+        }
+
+        if self.config.banding {
+            let (levels, banding) = self.timed("banding", || {
+                let levels = distinct_levels(image);
+                let banding = check_banding(image, &levels, self.config.min_level_ratio);
+                (levels, banding)
+            });
+            for (name, count) in &levels {
+                report.metric(name, *count as f64);
+            }
+
+            if banding {
+                report.flag(Reason::Banding);
+            }
+        }
+
+        if self.config.dead_lines {
+            let (rows, columns) = self.timed("dead_lines", || {
+                dead_lines(image, self.config.dead_line_tolerance)
+            });
+            report.metric("dead_rows", rows as f64);
+            report.metric("dead_columns", columns as f64);
+
+            if rows + columns > 0 {
+                report.flag(Reason::DeadLine);
+            }
+        }
+
+        if self.config.color_cast {
+            if let Some(cast) = self.timed("color_cast", || color_cast(&stats_image)) {
+                let magnitude = cast
+                    .iter()
+                    .map(|(_, deviation)| deviation.abs())
+                    .fold(0.0, f64::max);
+                for (name, deviation) in &cast {
+                    report.metric(name, *deviation);
+                }
+                report.metric("color_cast", magnitude);
+
+                if magnitude > self.config.max_color_cast {
+                    report.flag(Reason::ColorCast);
+                }
+            }
+        }
+
+        if self.config.near_blank {
+            let content = self.timed("near_blank", || {
+                content_area(image, self.config.background_tolerance)
+            });
+            report.metric("content_area", content);
+
+            if content < self.config.min_content_area {
+                report.flag(Reason::NearBlank);
+            }
+        }
+
+        if self.config.edge_content {
+            let content = self.timed("edge_content", || {
+                content_box(&stats_image.to_luma8(), self.config.content_variance)
+            });
+            // Where content lies, to crop it back later
+            if let Some(content) = content {
+                for (name, bound) in [
+                    "content_left",
+                    "content_top",
+                    "content_right",
+                    "content_bottom",
+                ]
+                .iter()
+                .zip(content)
+                {
+                    report.metric(name, bound);
+                }
+
+                if hugs_edge(&content, self.config.max_content_extent) {
+                    report.flag(Reason::EdgeContent);
+                }
+            }
+        }
+
+        if self.config.repeated_tiles {
+            let repetition = self.timed("repeated_tiles", || tile_repetition(&image.to_luma8()));
+            report.metric("tile_repetition", repetition);
+
+            if repetition >= self.config.min_tile_repetition {
+                report.flag(Reason::RepeatedTiles);
+            }
+        }
+
+        if let Some(model) = &self.model {
+            match self.timed("model", || model.score(image)) {
+                Ok(score) => {
+                    let score = score as f64;
+                    report.metric("model_score", score);
+
+                    let flagged = match self.config.model_flag {
+                        ModelFlag::Above => score > self.config.model_threshold,
+                        ModelFlag::Below => score < self.config.model_threshold,
+                    };
+                    if flagged {
+                        report.flag(Reason::Model);
+                    }
+                }
+                // Not the image's fault, left unflagged
+                Err(err) => warn!("{}: model check: {}", report.path, err),
+            }
+        }
+
+        if self.config.watermarks {
+            let (corner_edges, periodicity) = self.timed("watermarks", || {
+                let luma = image.to_luma8();
+                (corner_edges(&luma), periodicity(&luma))
+            });
+            report.metric("corner_edges", corner_edges);
+            report.metric("periodicity", periodicity);
+
+            if corner_edges >= self.config.min_corner_edges
+                || periodicity >= self.config.min_periodicity
+            {
+                report.flag(Reason::Watermark);
+            }
+        }
+
+        if self.config.clipping {
+            let (shadows, highlights) = self.timed("clipping", || clipping(&stats_image));
+            report.metric("shadow_clipping", shadows);
+            report.metric("highlight_clipping", highlights);
+
+            if shadows > self.config.max_shadow_clipping
+                || highlights > self.config.max_highlight_clipping
+            {
+                report.flag(Reason::Clipping);
+            }
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use image::{DynamicImage, GrayImage};
+use rayon::prelude::*;
+
+/// Pixels accumulated by a single rayon task.
+const BLOCK_PIXELS: usize = 16 * 1024;
+
+/// A value for each channel, 0 for channels missing from an image.
+#[derive(Debug)]
+pub struct Pixel {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+impl Pixel {
+    /// Pixel holding given channel values, in RGBA order.
+    pub fn from_channels(values: &[f64]) -> Self {
+        let value = |channel: usize| values.get(channel).copied().unwrap_or(0.0);
+        Pixel {
+            r: value(0),
+            g: value(1),
+            b: value(2),
+            a: value(3),
+        }
+    }
+
+    /// Check if every channel is below the one of `thresholds`.
+    pub fn is_below(&self, thresholds: &Pixel) -> bool {
+        self.r < thresholds.r
+            && self.g < thresholds.g
+            && self.b < thresholds.b
+            && self.a < thresholds.a
+    }
+
+    /// Same pixel with every channel multiplied by `factor`.
+    pub fn scaled(&self, factor: f64) -> Self {
+        Pixel {
+            r: self.r * factor,
+            g: self.g * factor,
+            b: self.b * factor,
+            a: self.a * factor,
+        }
+    }
+}
+
+/// Count, mean and sum of squared deviations from the mean of values
+/// of a channel, along with their range.
+#[derive(Clone, Copy, Debug)]
+pub struct Moments {
+    count: f64,
+    mean: f64,
+    m2: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for Moments {
+    fn default() -> Self {
+        Moments {
+            count: 0.0,
+            mean: 0.0,
+            m2: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl Moments {
+    /// Moments of `count` values of given mean and variance.
+    pub fn from_parts(count: f64, mean: f64, variance: f64) -> Self {
+        Moments {
+            count,
+            mean,
+            m2: variance * count,
+            ..Moments::default()
+        }
+    }
+
+    /// Moments of `count` values of given exact sum, sum of squares and range.
+    fn from_sums(count: u64, sum: u64, squares: u64, min: u64, max: u64) -> Self {
+        if count == 0 {
+            return Moments::default();
+        }
+
+        // n * ss - s * s is exact in 128 bits, a single division is left
+        let spread = count as u128 * squares as u128 - sum as u128 * sum as u128;
+        Moments {
+            count: count as f64,
+            mean: sum as f64 / count as f64,
+            m2: spread as f64 / count as f64,
+            min: min as f64,
+            max: max as f64,
+        }
+    }
+
+    /// Moments of values accumulated by both, combined as Chan et al. do.
+    pub fn merge(self, other: Self) -> Self {
+        let count = self.count + other.count;
+        if count == 0.0 {
+            return self;
+        }
+
+        let delta = other.mean - self.mean;
+        Moments {
+            count,
+            mean: self.mean + delta * other.count / count,
+            m2: self.m2 + other.m2 + delta * delta * self.count * other.count / count,
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn count(&self) -> f64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Population variance, 0 without values.
+    pub fn variance(&self) -> f64 {
+        match self.count > 0.0 {
+            true => self.m2 / self.count,
+            false => 0.0,
+        }
+    }
+}
+
+/// Moments of each channel over one pixel every `step` of given buffer,
+/// holding `C` values per pixel. Blocks are summed in parallel with exact
+/// integers, then turned into moments and merged.
+fn accumulate<T, const C: usize>(buffer: &[T], step: usize) -> [Moments; C]
+where
+    T: Copy + Sync + Into<u64>,
+{
+    buffer
+        // Blocks are a multiple of the step, sampled pixels stay evenly spaced
+        .par_chunks(C * step * BLOCK_PIXELS)
+        .map(|block| {
+            // 16 bits squares of a block stay far below u64::MAX
+            let mut count = 0u64;
+            let mut sums = [0u64; C];
+            let mut squares = [0u64; C];
+            let mut min = [u64::MAX; C];
+            let mut max = [0u64; C];
+            for pixel in block.chunks_exact(C).step_by(step) {
+                count += 1;
+                for channel in 0..C {
+                    let value: u64 = pixel[channel].into();
+                    sums[channel] += value;
+                    squares[channel] += value * value;
+                    min[channel] = min[channel].min(value);
+                    max[channel] = max[channel].max(value);
+                }
+            }
+
+            let mut moments = [Moments::default(); C];
+            for channel in 0..C {
+                moments[channel] = Moments::from_sums(
+                    count,
+                    sums[channel],
+                    squares[channel],
+                    min[channel],
+                    max[channel],
+                );
+            }
+            moments
+        })
+        .reduce(
+            || [Moments::default(); C],
+            |a, b| {
+                let mut merged = a;
+                for channel in 0..C {
+                    merged[channel] = a[channel].merge(b[channel]);
+                }
+                merged
+            },
+        )
+}
+
+/// Mean and variance of each channel of given buffer, holding `C` values
+/// per pixel.
+fn moments<T, const C: usize>(buffer: &[T], step: usize) -> (Pixel, Pixel)
+where
+    T: Copy + Sync + Into<u64>,
+{
+    let moments = accumulate::<T, C>(buffer, step);
+    let means: Vec<f64> = moments.iter().map(Moments::mean).collect();
+    let variances: Vec<f64> = moments.iter().map(Moments::variance).collect();
+
+    (
+        Pixel::from_channels(&means),
+        Pixel::from_channels(&variances),
+    )
+}
+
+/// Same as `get_image_statistics`, for buffers holding an alpha channel.
+pub fn get_image_statistics_with_alpha<T: Copy + Sync + Into<u64>>(
+    image: &[T],
+    step: usize,
+) -> (Pixel, Pixel) {
+    moments::<T, 4>(image, step)
+}
+
+/// Compute mean and variance from colors of given image.
+/// Only one pixel every `step` is visited, 1 visiting all of them.
+pub fn get_image_statistics<T: Copy + Sync + Into<u64>>(
+    image: &[T],
+    step: usize,
+) -> (Pixel, Pixel) {
+    moments::<T, 3>(image, step)
+}
+
+/// Same as `get_image_statistics`, for grayscale buffers. Gray values
+/// stand for red, green and blue alike.
+pub fn get_grayscale_statistics<T: Copy + Sync + Into<u64>>(
+    image: &[T],
+    step: usize,
+) -> (Pixel, Pixel) {
+    let (mean, variance) = moments::<T, 1>(image, step);
+    let gray = |value: f64| Pixel::from_channels(&[value, value, value]);
+
+    (gray(mean.r), gray(variance.r))
+}
+
+/// Same as `get_grayscale_statistics`, for buffers holding an alpha channel.
+pub fn get_grayscale_statistics_with_alpha<T: Copy + Sync + Into<u64>>(
+    image: &[T],
+    step: usize,
+) -> (Pixel, Pixel) {
+    let (mean, variance) = moments::<T, 2>(image, step);
+    let gray = |value: &Pixel| Pixel::from_channels(&[value.r, value.r, value.r, value.g]);
+
+    (gray(&mean), gray(&variance))
+}
+
+/// Moments of every channel of given image over all of its pixels,
+/// in the order given by `channel_names`, on the scale of the image.
+pub fn get_moments(image: &DynamicImage) -> Vec<Moments> {
+    match image {
+        DynamicImage::ImageLuma8(pixels) => accumulate::<_, 1>(pixels.as_raw(), 1).to_vec(),
+        DynamicImage::ImageLumaA8(pixels) => accumulate::<_, 2>(pixels.as_raw(), 1).to_vec(),
+        DynamicImage::ImageRgb8(pixels) => accumulate::<_, 3>(pixels.as_raw(), 1).to_vec(),
+        DynamicImage::ImageRgba8(pixels) => accumulate::<_, 4>(pixels.as_raw(), 1).to_vec(),
+        DynamicImage::ImageBgr8(pixels) => accumulate::<_, 3>(pixels.as_raw(), 1).to_vec(),
+        DynamicImage::ImageBgra8(pixels) => accumulate::<_, 4>(pixels.as_raw(), 1).to_vec(),
+        DynamicImage::ImageLuma16(pixels) => accumulate::<_, 1>(pixels.as_raw(), 1).to_vec(),
+        DynamicImage::ImageLumaA16(pixels) => accumulate::<_, 2>(pixels.as_raw(), 1).to_vec(),
+        DynamicImage::ImageRgb16(pixels) => accumulate::<_, 3>(pixels.as_raw(), 1).to_vec(),
+        DynamicImage::ImageRgba16(pixels) => accumulate::<_, 4>(pixels.as_raw(), 1).to_vec(),
+    }
+}
+
+/// Compute a 256 bins histogram for each channel of given buffer.
+/// Values are shifted right by `shift` bits, bringing 16 bits channels
+/// back to 8 bits.
+pub fn get_histograms<T: Sync + num::traits::PrimInt>(
+    buffer: &[T],
+    channels: usize,
+    shift: usize,
+) -> Vec<[u64; 256]> {
+    buffer
+        .par_chunks(channels)
+        .fold(
+            || vec![[0u64; 256]; channels],
+            |mut histograms, pixel| {
+                for (histogram, value) in histograms.iter_mut().zip(pixel) {
+                    histogram[value.to_usize().unwrap() >> shift] += 1;
+                }
+                histograms
+            },
+        )
+        .reduce(
+            || vec![[0u64; 256]; channels],
+            |mut a, b| {
+                for (ha, hb) in a.iter_mut().zip(b.iter()) {
+                    for (x, y) in ha.iter_mut().zip(hb.iter()) {
+                        *x += y;
+                    }
+                }
+                a
+            },
+        )
+}
+
+/// Shannon entropy of values counted by given histogram, in bits,
+/// from 0 for a single value to 8 for values spread over every bin.
+pub fn histogram_entropy(histogram: &[u64; 256]) -> f64 {
+    let count = histogram.iter().sum::<u64>() as f64;
+    if count == 0.0 {
+        return 0.0;
+    }
+
+    histogram
+        .iter()
+        .filter(|n| **n > 0)
+        .map(|n| {
+            let p = *n as f64 / count;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+/// Compute the entropy of given luminance, in bits.
+pub fn get_entropy(luma: &GrayImage) -> f64 {
+    histogram_entropy(&get_histograms(luma.as_raw(), 1, 0)[0])
+}
+
+/// Name of each channel of given image, in buffer order.
+pub fn channel_names(image: &DynamicImage) -> &'static [&'static str] {
+    match image {
+        DynamicImage::ImageLuma8(_) | DynamicImage::ImageLuma16(_) => &["l"],
+        DynamicImage::ImageLumaA8(_) | DynamicImage::ImageLumaA16(_) => &["l", "a"],
+        DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgb16(_) => &["r", "g", "b"],
+        DynamicImage::ImageRgba8(_) | DynamicImage::ImageRgba16(_) => &["r", "g", "b", "a"],
+        DynamicImage::ImageBgr8(_) => &["b", "g", "r"],
+        DynamicImage::ImageBgra8(_) => &["b", "g", "r", "a"],
+    }
+}
+
+/// Compute 256 bins histograms of every channel of given image,
+/// in the order given by `channel_names`.
+pub fn get_image_histograms(image: &DynamicImage) -> Vec<[u64; 256]> {
+    let channels = channel_names(image).len();
+
+    match image {
+        DynamicImage::ImageLuma8(pixels) => get_histograms(pixels.as_raw(), channels, 0),
+        DynamicImage::ImageLumaA8(pixels) => get_histograms(pixels.as_raw(), channels, 0),
+        DynamicImage::ImageRgb8(pixels) => get_histograms(pixels.as_raw(), channels, 0),
+        DynamicImage::ImageRgba8(pixels) => get_histograms(pixels.as_raw(), channels, 0),
+        DynamicImage::ImageBgr8(pixels) => get_histograms(pixels.as_raw(), channels, 0),
+        DynamicImage::ImageBgra8(pixels) => get_histograms(pixels.as_raw(), channels, 0),
+        DynamicImage::ImageLuma16(pixels) => get_histograms(pixels.as_raw(), channels, 8),
+        DynamicImage::ImageLumaA16(pixels) => get_histograms(pixels.as_raw(), channels, 8),
+        DynamicImage::ImageRgb16(pixels) => get_histograms(pixels.as_raw(), channels, 8),
+        DynamicImage::ImageRgba16(pixels) => get_histograms(pixels.as_raw(), channels, 8),
+    }
+}
+
+/// Compute mean and standard deviation of every channel of given image,
+/// in the order given by `channel_names`. Values are on a 0-255 scale.
+pub fn get_channel_moments(image: &DynamicImage) -> Vec<(f64, f64)> {
+    get_image_histograms(image)
+        .iter()
+        .map(|histogram| {
+            let count = histogram.iter().sum::<u64>() as f64;
+            let mean = histogram
+                .iter()
+                .enumerate()
+                .map(|(value, n)| value as f64 * *n as f64)
+                .sum::<f64>()
+                / count;
+            let variance = histogram
+                .iter()
+                .enumerate()
+                .map(|(value, n)| (value as f64 - mean).powi(2) * *n as f64)
+                .sum::<f64>()
+                / count;
+
+            (mean, variance.sqrt())
+        })
+        .collect()
+}
+
+/// Compute the spread (max - min) of luminance along each row
+/// and along each column of given image.
+pub fn get_line_spreads(luma: &GrayImage) -> (Vec<u8>, Vec<u8>) {
+    let width = luma.width() as usize;
+
+    let rows = luma
+        .as_raw()
+        .par_chunks(width)
+        .map(|row| row.iter().max().unwrap() - row.iter().min().unwrap())
+        .collect();
+
+    let (min, max) = luma
+        .as_raw()
+        .par_chunks(width)
+        .fold(
+            || (vec![u8::MAX; width], vec![u8::MIN; width]),
+            |(mut min, mut max), row| {
+                for (x, value) in row.iter().enumerate() {
+                    min[x] = min[x].min(*value);
+                    max[x] = max[x].max(*value);
+                }
+                (min, max)
+            },
+        )
+        .reduce(
+            || (vec![u8::MAX; width], vec![u8::MIN; width]),
+            |(mut min, mut max), (other_min, other_max)| {
+                for x in 0..width {
+                    min[x] = min[x].min(other_min[x]);
+                    max[x] = max[x].max(other_max[x]);
+                }
+                (min, max)
+            },
+        );
+
+    let columns = max.iter().zip(min.iter()).map(|(a, b)| a - b).collect();
+
+    (rows, columns)
+}
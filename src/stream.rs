@@ -0,0 +1,135 @@
+use crate::paths;
+use crate::report::Report;
+use pyo3::exceptions::{PyRuntimeError, PyStopAsyncIteration};
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+use pyo3::{PyAsyncProtocol, PyIterProtocol};
+use std::io;
+use std::sync::mpsc::Receiver;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+
+/// Wait for the result of given blocking method of a stream
+/// on the default executor of the running asyncio loop.
+fn in_executor(py: Python, stream: PyObject, method: &str) -> PyResult<PyObject> {
+    let method = stream.getattr(py, method)?;
+    let future = py
+        .import("asyncio")?
+        .call0("get_running_loop")?
+        .call_method1("run_in_executor", (py.None(), method))?;
+
+    Ok(future.into())
+}
+
+/// Reports of a scan running in the background, handed
+/// to python as soon as files get checked.
+/// Read them with `for` or `async for`, or `await` all of them.
+#[pyclass]
+pub struct ReportStream {
+    /// Only a receiver is needed, the mutex makes it shareable
+    /// with the thread waiting for reports.
+    results: Mutex<Receiver<Report>>,
+    /// Taken by whichever call sees the last report. Methods only
+    /// borrow the stream, so that it isn't locked while they block.
+    scan: Mutex<Option<JoinHandle<io::Result<()>>>>,
+    /// Yield paths of flagged files rather than every report.
+    flagged_only: bool,
+}
+
+impl ReportStream {
+    pub fn new(
+        results: Receiver<Report>,
+        scan: JoinHandle<io::Result<()>>,
+        flagged_only: bool,
+    ) -> Self {
+        ReportStream {
+            results: Mutex::new(results),
+            scan: Mutex::new(Some(scan)),
+            flagged_only,
+        }
+    }
+
+    /// Wait for the scan to be over, raising its error if it failed.
+    fn join(&self) -> PyResult<()> {
+        let scan = self.scan.lock().unwrap().take();
+        match scan {
+            Some(scan) => match scan.join() {
+                Ok(result) => Ok(result?),
+                Err(_) => Err(PyRuntimeError::new_err("scan panicked")),
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Wait for the next report, while letting the scan go on.
+    /// Return nothing once every report was handed.
+    fn next_item(&self, py: Python) -> PyResult<Option<PyObject>> {
+        loop {
+            let results = &self.results;
+            let report = match py.allow_threads(|| results.lock().unwrap().recv()) {
+                Ok(report) => report,
+                Err(_) => {
+                    self.join()?;
+                    return Ok(None);
+                }
+            };
+
+            if !self.flagged_only {
+                return Ok(Some(report.to_dict(py)?.into()));
+            }
+            if report.is_flagged() {
+                return Ok(Some(paths::to_py(py, &report.path)));
+            }
+        }
+    }
+}
+
+#[pymethods]
+impl ReportStream {
+    /// Next report, blocking. Run by the executor for `async for`.
+    fn _anext(&self, py: Python) -> PyResult<PyObject> {
+        match self.next_item(py)? {
+            Some(item) => Ok(item),
+            None => Err(PyStopAsyncIteration::new_err(())),
+        }
+    }
+
+    /// Every report left, blocking. Run by the executor for `await`.
+    fn _collect(&self, py: Python) -> PyResult<Py<PyList>> {
+        let list = PyList::empty(py);
+        while let Some(item) = self.next_item(py)? {
+            list.append(item)?;
+        }
+
+        Ok(list.into())
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for ReportStream {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(slf: PyRef<Self>) -> PyResult<Option<PyObject>> {
+        Python::with_gil(|py| slf.next_item(py))
+    }
+}
+
+#[pyproto]
+impl PyAsyncProtocol for ReportStream {
+    fn __await__(slf: Py<Self>) -> PyResult<PyObject> {
+        Python::with_gil(|py| {
+            let future = in_executor(py, slf.into_py(py), "_collect")?;
+            future.call_method0(py, "__await__")
+        })
+    }
+
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __anext__(slf: Py<Self>) -> PyResult<Option<PyObject>> {
+        Python::with_gil(|py| Ok(Some(in_executor(py, slf.into_py(py), "_anext")?)))
+    }
+}
@@ -0,0 +1,244 @@
+use crate::report::{Reason, Report};
+use crate::timings::Timings;
+use pyo3::prelude::*;
+use pyo3::PyObjectProtocol;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Figures of a scan, for jobs cleaning datasets to be monitored.
+pub struct Summary {
+    files: usize,
+    flagged: usize,
+    /// Files flagged for each reason, those never found included
+    /// so that series don't come and go.
+    reasons: BTreeMap<&'static str, usize>,
+    bytes_read: u64,
+    /// Files whose report was reused from the cache.
+    cached: u64,
+    duration: Duration,
+    /// Seconds since the epoch at the end of the scan.
+    finished: f64,
+    /// Time spent in each stage of checks, if recorded.
+    timings: Option<Value>,
+}
+
+impl Summary {
+    pub fn new(
+        reports: &[Report],
+        bytes_read: u64,
+        cached: u64,
+        duration: Duration,
+        timings: Option<&Timings>,
+    ) -> Self {
+        let mut reasons: BTreeMap<&'static str, usize> = Reason::ALL
+            .iter()
+            .map(|reason| (reason.as_str(), 0))
+            .collect();
+        for report in reports {
+            for reason in &report.reasons {
+                *reasons.entry(reason.as_str()).or_default() += 1;
+            }
+        }
+
+        Summary {
+            files: reports.len(),
+            flagged: reports.iter().filter(|report| report.is_flagged()).count(),
+            reasons,
+            bytes_read,
+            cached,
+            duration,
+            finished: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |time| time.as_secs_f64()),
+            timings: timings.map(Timings::to_json),
+        }
+    }
+
+    fn per_second(&self, count: f64) -> f64 {
+        match self.duration.as_secs_f64() {
+            seconds if seconds > 0.0 => count / seconds,
+            _ => 0.0,
+        }
+    }
+
+    fn flag_rate(&self) -> f64 {
+        match self.files {
+            0 => 0.0,
+            files => self.flagged as f64 / files as f64,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut summary = json!({
+            "files": self.files,
+            "flagged": self.flagged,
+            "flag_rate": self.flag_rate(),
+            "reasons": self.reasons,
+            "bytes_read": self.bytes_read,
+            "cached": self.cached,
+            "seconds": self.duration.as_secs_f64(),
+            "files_per_second": self.per_second(self.files as f64),
+            "bytes_per_second": self.per_second(self.bytes_read as f64),
+            "finished": self.finished,
+        });
+        if let Some(timings) = &self.timings {
+            summary["timings"] = timings.clone();
+        }
+        summary
+    }
+
+    /// Summary in the Prometheus text format, as read by the textfile
+    /// collector of node_exporter.
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        let mut gauge = |name: &str, help: &str, values: &[(Option<&str>, f64)]| {
+            text.push_str(&format!("# HELP cleanax_{} {}\n", name, help));
+            text.push_str(&format!("# TYPE cleanax_{} gauge\n", name));
+            for (reason, value) in values {
+                let labels = match reason {
+                    Some(reason) => format!("{{reason=\"{}\"}}", reason),
+                    None => String::new(),
+                };
+                text.push_str(&format!("cleanax_{}{} {}\n", name, labels, value));
+            }
+        };
+
+        gauge(
+            "files",
+            "Files checked by the last scan.",
+            &[(None, self.files as f64)],
+        );
+        gauge(
+            "flagged",
+            "Files flagged by the last scan.",
+            &[(None, self.flagged as f64)],
+        );
+        gauge(
+            "flag_rate",
+            "Share of files flagged by the last scan.",
+            &[(None, self.flag_rate())],
+        );
+        let reasons: Vec<_> = self
+            .reasons
+            .iter()
+            .map(|(reason, count)| (Some(*reason), *count as f64))
+            .collect();
+        gauge(
+            "flagged_by_reason",
+            "Files flagged for each reason by the last scan.",
+            &reasons,
+        );
+        gauge(
+            "bytes_read",
+            "Bytes of files read by the last scan.",
+            &[(None, self.bytes_read as f64)],
+        );
+        gauge(
+            "cached",
+            "Files whose report was reused from the cache by the last scan.",
+            &[(None, self.cached as f64)],
+        );
+        gauge(
+            "duration_seconds",
+            "Wall time of the last scan.",
+            &[(None, self.duration.as_secs_f64())],
+        );
+        gauge(
+            "files_per_second",
+            "Files checked per second by the last scan.",
+            &[(None, self.per_second(self.files as f64))],
+        );
+        gauge(
+            "last_run_timestamp_seconds",
+            "Time the last scan finished at.",
+            &[(None, self.finished)],
+        );
+
+        text
+    }
+}
+
+/// Figures of a scan, returned by `clean(..., with_summary=True)` so that
+/// scripts don't count them again from reports.
+#[pyclass]
+pub struct ScanSummary {
+    summary: Summary,
+}
+
+impl From<Summary> for ScanSummary {
+    fn from(summary: Summary) -> Self {
+        ScanSummary { summary }
+    }
+}
+
+#[pymethods]
+impl ScanSummary {
+    /// Files checked.
+    #[getter]
+    fn files(&self) -> usize {
+        self.summary.files
+    }
+
+    /// Files flagged.
+    #[getter]
+    fn flagged(&self) -> usize {
+        self.summary.flagged
+    }
+
+    /// Files flagged for each reason, those never found included.
+    #[getter]
+    fn reasons(&self) -> BTreeMap<&'static str, usize> {
+        self.summary.reasons.clone()
+    }
+
+    /// Bytes of files read.
+    #[getter]
+    fn bytes_read(&self) -> u64 {
+        self.summary.bytes_read
+    }
+
+    /// Files whose report was reused from the cache rather than checked.
+    #[getter]
+    fn cached(&self) -> u64 {
+        self.summary.cached
+    }
+
+    /// Wall time of the scan.
+    #[getter]
+    fn seconds(&self) -> f64 {
+        self.summary.duration.as_secs_f64()
+    }
+
+    /// Figures as written to `run_summary`, timings included if recorded.
+    fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        let json = py.import("json")?;
+        Ok(json
+            .call_method1("loads", (self.summary.to_json().to_string(),))?
+            .into())
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for ScanSummary {
+    fn __repr__(&self) -> String {
+        format!(
+            "ScanSummary(files={}, flagged={}, cached={}, bytes_read={}, seconds={:.2})",
+            self.summary.files,
+            self.summary.flagged,
+            self.summary.cached,
+            self.summary.bytes_read,
+            self.summary.duration.as_secs_f64()
+        )
+    }
+}
+
+/// Write given content next to its destination first, then move it there,
+/// so that readers never see it half written.
+pub fn write_atomic(path: &str, content: &str) -> io::Result<()> {
+    let temporary = format!("{}.tmp", path);
+    fs::write(&temporary, content)?;
+    fs::rename(temporary, path)
+}
@@ -0,0 +1,67 @@
+use crate::config::TagTarget;
+use crate::paths;
+use crate::report::Report;
+use rayon::prelude::*;
+use std::fs;
+use std::io;
+
+/// Suffix of sidecar files, appended to the name of the image they tag.
+const SIDECAR_SUFFIX: &str = ".cleanax.json";
+
+/// Check if given file is a sidecar written by a previous scan.
+pub fn is_sidecar(path: &str) -> bool {
+    path.ends_with(SIDECAR_SUFFIX)
+}
+
+/// Write the verdict of each given report where its file lives, leaving
+/// files themselves untouched. Files inside archives, downloads and
+/// missing files have nowhere to be tagged and are left out.
+pub fn write_tags(reports: &[Report], target: TagTarget) -> io::Result<()> {
+    reports
+        .par_iter()
+        .filter(|report| paths::decode(&report.path).is_file())
+        .try_for_each(|report| match target {
+            TagTarget::Sidecar => write_sidecar(report),
+            TagTarget::Xattr => write_xattrs(report),
+        })
+}
+
+/// Write given report to a JSON file next to its image,
+/// `a.jpg` getting `a.jpg.cleanax.json`.
+fn write_sidecar(report: &Report) -> io::Result<()> {
+    let mut sidecar = paths::decode(&report.path).into_os_string();
+    sidecar.push(SIDECAR_SUFFIX);
+
+    fs::write(sidecar, report.to_json().to_string())
+}
+
+/// Write the verdict of given report to extended attributes of its file:
+/// `user.cleanax.verdict`, "flagged" or "kept", and `user.cleanax.reasons`,
+/// reasons it's flagged for separated by commas.
+#[cfg(unix)]
+fn write_xattrs(report: &Report) -> io::Result<()> {
+    let path = paths::decode(&report.path);
+    let verdict = match report.is_flagged() {
+        true => "flagged",
+        false => "kept",
+    };
+    let reasons: Vec<_> = report
+        .reasons
+        .iter()
+        .map(|reason| reason.as_str())
+        .collect();
+
+    xattr::set(&path, "user.cleanax.verdict", verdict.as_bytes())?;
+    xattr::set(&path, "user.cleanax.reasons", reasons.join(",").as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_xattrs(report: &Report) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "can't tag {}, extended attributes are only written on unix",
+            report.path
+        ),
+    ))
+}
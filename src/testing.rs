@@ -0,0 +1,13 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+/// Empty folder of given name for a test to write files in,
+/// emptied if a previous run left it behind.
+pub fn scratch(name: &str) -> PathBuf {
+    let folder = env::temp_dir().join(format!("cleanax-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&folder);
+    fs::create_dir_all(&folder).unwrap();
+    folder
+}
@@ -0,0 +1,46 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Rates of reads allowed to scanning threads together, in bytes and in
+/// reads per second, leaving shared storage (NFS, buckets) to other
+/// clients. Reads are spaced out as if done one after the other at the
+/// allowed rates, whichever thread does them.
+pub struct Throttle {
+    bytes_per_second: Option<f64>,
+    reads_per_second: Option<f64>,
+    /// When reads charged so far are done, at the allowed rates.
+    done: Mutex<Instant>,
+}
+
+impl Throttle {
+    pub fn new(bytes_per_second: Option<u64>, reads_per_second: Option<f64>) -> Self {
+        Throttle {
+            bytes_per_second: bytes_per_second.map(|rate| rate as f64),
+            reads_per_second,
+            done: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Charge a read of given bytes, waiting until reads charged so far
+    /// would be done at the allowed rates. A read following a quiet period
+    /// doesn't wait, the time it took already spacing it out.
+    pub fn wait(&self, bytes: u64) {
+        let mut seconds: f64 = 0.0;
+        if let Some(rate) = self.bytes_per_second {
+            seconds = seconds.max(bytes as f64 / rate);
+        }
+        if let Some(rate) = self.reads_per_second {
+            seconds = seconds.max(1.0 / rate);
+        }
+
+        let now = Instant::now();
+        let until = {
+            let mut done = self.done.lock().unwrap();
+            *done = (*done + Duration::from_secs_f64(seconds)).max(now);
+            *done
+        };
+
+        thread::sleep(until - now);
+    }
+}
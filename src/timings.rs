@@ -0,0 +1,78 @@
+use crate::paths;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Time spent on something, and how many times.
+#[derive(Clone, Copy, Default)]
+struct Timing {
+    count: u64,
+    seconds: f64,
+}
+
+impl Timing {
+    fn add(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.seconds += elapsed.as_secs_f64();
+    }
+}
+
+/// Time scanning threads spent in each stage of checks (reading, decoding,
+/// each check), and on files of each extension. Times of threads add up,
+/// they exceed the duration of a scan running on several cores.
+#[derive(Default)]
+pub struct Timings {
+    stages: Mutex<BTreeMap<&'static str, Timing>>,
+    extensions: Mutex<BTreeMap<String, Timing>>,
+}
+
+impl Timings {
+    /// Record time spent in given stage.
+    pub fn stage(&self, stage: &'static str, elapsed: Duration) {
+        self.stages
+            .lock()
+            .unwrap()
+            .entry(stage)
+            .or_default()
+            .add(elapsed);
+    }
+
+    /// Record time spent checking given file, under its extension.
+    pub fn file(&self, path: &str, elapsed: Duration) {
+        let extension = paths::decode(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        self.extensions
+            .lock()
+            .unwrap()
+            .entry(extension)
+            .or_default()
+            .add(elapsed);
+    }
+
+    /// Timings as `{"stages": {"decode": {"calls": 10, "seconds": 1.2}, ...},
+    /// "extensions": {"jpg": {"files": 10, "seconds": 2.5}, ...}}`,
+    /// files without extension being under `""`.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "stages": table(&self.stages.lock().unwrap(), "calls"),
+            "extensions": table(&self.extensions.lock().unwrap(), "files"),
+        })
+    }
+}
+
+/// Given timings as a JSON object, with counts under given key.
+fn table<K: ToString>(timings: &BTreeMap<K, Timing>, count: &str) -> Map<String, Value> {
+    timings
+        .iter()
+        .map(|(name, timing)| {
+            (
+                name.to_string(),
+                json!({ count: timing.count, "seconds": timing.seconds }),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,2 @@
+// Root of unit tests: the library, linked against libpython by build.rs
+include!("lib.rs");
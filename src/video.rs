@@ -0,0 +1,177 @@
+use crate::animation::Animation;
+use image::error::{DecodingError, ImageFormatHint};
+use image::{ImageError, ImageResult};
+
+/// Brands of ISO base media files holding still images rather than videos.
+const IMAGE_BRANDS: &[&[u8]] = &[
+    b"avif", b"avis", b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1", b"msf1",
+];
+
+fn video_error(message: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("video".to_string()),
+        message,
+    ))
+}
+
+/// Check given file for a video (MP4, MOV, WebM, MKV, AVI, FLV, WMV, MPEG),
+/// by its magic bytes.
+pub fn is_video(data: &[u8]) -> bool {
+    match data {
+        // AVIF and HEIC images share the container of MP4 and MOV
+        [_, _, _, _, b'f', b't', b'y', b'p', brand @ ..] => {
+            !IMAGE_BRANDS.iter().any(|image| brand.starts_with(image))
+        }
+        [0x1a, 0x45, 0xdf, 0xa3, ..]
+        | [b'F', b'L', b'V', 0x01, ..]
+        | [0x30, 0x26, 0xb2, 0x75, 0x8e, 0x66, 0xcf, 0x11, ..]
+        | [0x00, 0x00, 0x01, 0xba, ..]
+        | [0x00, 0x00, 0x01, 0xb3, ..] => true,
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'A', b'V', b'I', b' ', ..] => true,
+        _ => false,
+    }
+}
+
+/// Decode `samples` evenly spaced frames of given video, starting with
+/// the first one. Frames are the key frames the video seeks to, which
+/// is close enough for checks and saves decoding the whole video.
+#[cfg(feature = "video")]
+pub fn load_video(name: &str, data: &[u8], samples: usize) -> ImageResult<Animation> {
+    use ffmpeg_next::codec::context::Context;
+    use ffmpeg_next::media::Type;
+
+    ffmpeg_next::init().map_err(video_error)?;
+
+    // FFmpeg opens files by path, files of archives or downloads are written out first
+    let path = crate::paths::decode(name);
+    let spilled = match path.is_file() {
+        true => None,
+        false => Some(Spilled::write(data).map_err(video_error)?),
+    };
+    let path = spilled
+        .as_ref()
+        .map_or(path.as_path(), |spilled| &spilled.0);
+
+    let mut input = ffmpeg_next::format::input(&path).map_err(video_error)?;
+    let stream = input
+        .streams()
+        .best(Type::Video)
+        .ok_or_else(|| video_error("No video stream"))?;
+    let index = stream.index();
+    let frame_count = stream.frames().max(0) as usize;
+    let mut decoder = Context::from_parameters(stream.parameters())
+        .and_then(|context| context.decoder().video())
+        .map_err(video_error)?;
+
+    // In microseconds, unknown for some streams
+    let duration = input.duration();
+    let samples = match duration > 0 {
+        true => samples.max(1),
+        false => 1,
+    };
+
+    let mut frames = Vec::new();
+    for sample in 0..samples {
+        if sample > 0 {
+            let timestamp = duration * sample as i64 / samples as i64;
+            input.seek(timestamp, ..timestamp).map_err(video_error)?;
+            decoder.flush();
+        }
+        if let Some(frame) = next_frame(&mut input, index, &mut decoder)? {
+            frames.push(to_image(&frame)?);
+        }
+    }
+
+    if frames.is_empty() {
+        return Err(video_error("No frame could be decoded"));
+    }
+
+    Ok(Animation {
+        frames,
+        frame_count,
+        duration: duration.max(0) as f64 / 1000.0,
+    })
+}
+
+/// Decode the next frame of given stream.
+#[cfg(feature = "video")]
+fn next_frame(
+    input: &mut ffmpeg_next::format::context::Input,
+    index: usize,
+    decoder: &mut ffmpeg_next::decoder::Video,
+) -> ImageResult<Option<ffmpeg_next::frame::Video>> {
+    let mut frame = ffmpeg_next::frame::Video::empty();
+    for (stream, packet) in input.packets() {
+        if stream.index() != index {
+            continue;
+        }
+
+        decoder.send_packet(&packet).map_err(video_error)?;
+        if decoder.receive_frame(&mut frame).is_ok() {
+            return Ok(Some(frame));
+        }
+    }
+
+    // Frames held back by the decoder
+    decoder.send_eof().map_err(video_error)?;
+    Ok(decoder.receive_frame(&mut frame).is_ok().then_some(frame))
+}
+
+/// Convert given frame to an RGB image.
+#[cfg(feature = "video")]
+fn to_image(frame: &ffmpeg_next::frame::Video) -> ImageResult<image::DynamicImage> {
+    use ffmpeg_next::format::Pixel;
+
+    let (width, height) = (frame.width(), frame.height());
+    let mut rgb = ffmpeg_next::frame::Video::empty();
+    ffmpeg_next::software::converter((width, height), frame.format(), Pixel::RGB24)
+        .and_then(|mut converter| converter.run(frame, &mut rgb))
+        .map_err(video_error)?;
+
+    // Rows may be padded
+    let pixels = rgb
+        .data(0)
+        .chunks(rgb.stride(0))
+        .take(height as usize)
+        .flat_map(|row| &row[..width as usize * 3])
+        .copied()
+        .collect();
+    image::RgbImage::from_raw(width, height, pixels)
+        .map(image::DynamicImage::ImageRgb8)
+        .ok_or_else(|| video_error("Frame doesn't match video size"))
+}
+
+/// Temporary copy of a video, removed once dropped.
+#[cfg(feature = "video")]
+struct Spilled(std::path::PathBuf);
+
+#[cfg(feature = "video")]
+impl Spilled {
+    fn write(data: &[u8]) -> std::io::Result<Self> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "cleanax-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, data)?;
+        Ok(Spilled(path))
+    }
+}
+
+#[cfg(feature = "video")]
+impl Drop for Spilled {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
+
+/// Video decoding isn't compiled in.
+#[cfg(not(feature = "video"))]
+pub fn load_video(_name: &str, _data: &[u8], _samples: usize) -> ImageResult<Animation> {
+    Err(video_error(
+        "Sampling video frames requires the `video` feature",
+    ))
+}
@@ -0,0 +1,170 @@
+use crate::config::SymlinkPolicy;
+use crate::paths;
+use log::warn;
+use std::collections::HashSet;
+use std::fs::{self, DirEntry, ReadDir};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Range of modification times of files scanned, in seconds since epoch.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Modified {
+    /// Files modified before are left out.
+    pub after: Option<f64>,
+    /// Files modified at this time or later are left out.
+    pub before: Option<f64>,
+}
+
+impl Modified {
+    pub fn is_set(&self) -> bool {
+        self.after.is_some() || self.before.is_some()
+    }
+
+    /// Check if given file was modified within range. Files whose
+    /// modification time can't be read are kept, for checks to report them.
+    pub fn contains(&self, path: &Path) -> bool {
+        let modified = match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(_) => return true,
+        };
+        let seconds = match modified.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs_f64(),
+            Err(err) => -err.duration().as_secs_f64(),
+        };
+
+        self.after.is_none_or(|after| seconds >= after)
+            && self.before.is_none_or(|before| seconds < before)
+    }
+}
+
+/// Paths of files of a folder, yielded as they're listed rather than
+/// once the whole listing is over. Subfolders are entered if recursive,
+/// they're yielded like files otherwise. Subfolders that can't be listed,
+/// and their entries that can't be read, are skipped with a warning,
+/// only the root folder failing the walk.
+/// Files modified out of range are left out before being opened.
+pub struct Walk {
+    recursive: bool,
+    symlinks: SymlinkPolicy,
+    modified: Modified,
+    /// Folders being listed along with their path, the deepest last.
+    pending: Vec<(PathBuf, ReadDir)>,
+    /// Resolved paths of files yielded and folders entered so far,
+    /// when resolving symbolic links.
+    seen: HashSet<PathBuf>,
+}
+
+impl Walk {
+    pub fn new(
+        root: &str,
+        recursive: bool,
+        symlinks: SymlinkPolicy,
+        modified: Modified,
+    ) -> io::Result<Self> {
+        let root = paths::decode(root);
+        let mut seen = HashSet::new();
+        if symlinks == SymlinkPolicy::Resolve {
+            seen.insert(fs::canonicalize(&root)?);
+        }
+
+        Ok(Walk {
+            recursive,
+            symlinks,
+            modified,
+            pending: vec![(root.clone(), fs::read_dir(&root)?)],
+            seen,
+        })
+    }
+
+    /// Path an entry is yielded or entered as, and whether it's a folder.
+    /// None if it's left out.
+    fn visit(&mut self, entry: &DirEntry) -> io::Result<Option<(PathBuf, bool)>> {
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+
+        // Symbolic links are only followed when resolved,
+        // otherwise they can't make a loop
+        match self.symlinks {
+            SymlinkPolicy::Keep => Ok(Some((path, file_type.is_dir()))),
+            SymlinkPolicy::Skip if file_type.is_symlink() => Ok(None),
+            SymlinkPolicy::Skip => Ok(Some((path, file_type.is_dir()))),
+            SymlinkPolicy::Resolve => {
+                // Dangling links are reported as they are
+                let resolved = match fs::canonicalize(&path) {
+                    Ok(resolved) => resolved,
+                    Err(_) => return Ok(Some((path, false))),
+                };
+                if !self.seen.insert(resolved.clone()) {
+                    return Ok(None);
+                }
+
+                let is_dir = resolved.is_dir();
+                match file_type.is_symlink() {
+                    true => Ok(Some((resolved, is_dir))),
+                    false => Ok(Some((path, is_dir))),
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Walk {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let root = self.pending.len() == 1;
+            let (folder, entries) = self.pending.last_mut()?;
+            let entry = match entries.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(err)) if root => return Some(Err(err)),
+                // Listing may not go on, the rest of the folder is left out
+                Some(Err(err)) => {
+                    warn!("{}: skipping unreadable folder: {}", folder.display(), err);
+                    self.pending.pop();
+                    continue;
+                }
+                None => {
+                    self.pending.pop();
+                    continue;
+                }
+            };
+
+            let (path, is_dir) = match self.visit(&entry) {
+                Ok(Some(visited)) => visited,
+                Ok(None) => continue,
+                Err(err) if root => return Some(Err(err)),
+                Err(err) => {
+                    warn!(
+                        "{}: skipping unreadable file: {}",
+                        entry.path().display(),
+                        err
+                    );
+                    continue;
+                }
+            };
+
+            if self.recursive && is_dir {
+                match fs::read_dir(&path) {
+                    Ok(folder) => {
+                        self.pending.push((path, folder));
+                        continue;
+                    }
+                    // Nothing of it gets flagged, let alone deleted
+                    Err(err) => {
+                        warn!("{}: skipping unreadable folder: {}", path.display(), err);
+                        continue;
+                    }
+                }
+            }
+
+            // Only stat files when their modification time matters
+            if !is_dir && self.modified.is_set() && !self.modified.contains(&path) {
+                continue;
+            }
+
+            return Some(Ok(path));
+        }
+    }
+}
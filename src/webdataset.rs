@@ -0,0 +1,156 @@
+use crate::archive::{self, Kind};
+use crate::paths;
+use flate2::{write::GzEncoder, Compression};
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::mem;
+use std::path::Path;
+
+/// Number of samples read from a shard before running checks over them.
+/// Bounds memory used by large shards while keeping their order.
+const BATCH_SAMPLES: usize = 64;
+
+/// Extensions of sample members holding an image.
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "ppm", "pgm", "bmp", "gif", "tif", "tiff", "webp", "avif", "heic",
+];
+
+/// File of a sample, along with its header to write it back.
+pub struct Member {
+    pub name: String,
+    pub header: tar::Header,
+    pub data: Vec<u8>,
+}
+
+impl Member {
+    /// Check extension of member for an image.
+    pub fn is_image(&self) -> bool {
+        let (_, extension) = split_name(&self.name);
+        let extension = extension.rsplit('.').next().unwrap_or("").to_lowercase();
+
+        IMAGE_EXTENSIONS.contains(&extension.as_str())
+    }
+}
+
+/// Files of a shard sharing the same key (`{key}.jpg`, `{key}.json`, ...).
+pub struct Sample {
+    pub key: String,
+    pub members: Vec<Member>,
+}
+
+/// Split a member name into its sample key and extension.
+/// Key goes up to the first dot of the file name, so that
+/// `a/b.seg.png` belongs to sample `a/b`.
+fn split_name(name: &str) -> (&str, &str) {
+    let start = name.rfind('/').map_or(0, |slash| slash + 1);
+
+    match name[start..].find('.') {
+        Some(dot) => (&name[..start + dot], &name[start + dot + 1..]),
+        None => (name, ""),
+    }
+}
+
+/// Read samples of given shard in order, handing them over by batches.
+/// Members of a sample are expected to be stored next to each other,
+/// as WebDataset writers do.
+pub fn read_samples(
+    path: &str,
+    mut on_batch: impl FnMut(Vec<Sample>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut archive = tar::Archive::new(archive::open_tar(path)?);
+    let mut batch: Vec<Sample> = Vec::with_capacity(BATCH_SAMPLES);
+
+    for file in archive.entries()? {
+        let mut file = file?;
+        if !file.header().entry_type().is_file() {
+            continue;
+        }
+
+        let name = file.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)?;
+
+        let member = Member {
+            header: file.header().clone(),
+            name,
+            data,
+        };
+        let key = split_name(&member.name).0;
+
+        match batch.last_mut() {
+            Some(sample) if sample.key == key => sample.members.push(member),
+            _ => {
+                // Previous sample is complete once a new key shows up
+                if batch.len() == BATCH_SAMPLES {
+                    on_batch(mem::replace(&mut batch, Vec::with_capacity(BATCH_SAMPLES)))?;
+                }
+
+                batch.push(Sample {
+                    key: key.to_string(),
+                    members: vec![member],
+                });
+            }
+        }
+    }
+
+    if !batch.is_empty() {
+        on_batch(batch)?;
+    }
+
+    Ok(())
+}
+
+enum Output {
+    Tar(tar::Builder<BufWriter<File>>),
+    TarGz(tar::Builder<GzEncoder<BufWriter<File>>>),
+}
+
+/// Copy of a shard holding kept samples only.
+pub struct ShardWriter {
+    output: Output,
+}
+
+impl ShardWriter {
+    /// Create a shard named after given one in output folder,
+    /// compressed the same way.
+    pub fn create(folder: &str, shard: &str) -> io::Result<Self> {
+        let name = paths::decode(shard);
+        let name = name.file_name().unwrap();
+        let file = BufWriter::new(File::create(Path::new(folder).join(name))?);
+
+        let output = match archive::archive_kind(shard) {
+            Some(Kind::TarGz) => Output::TarGz(tar::Builder::new(GzEncoder::new(
+                file,
+                Compression::default(),
+            ))),
+            _ => Output::Tar(tar::Builder::new(file)),
+        };
+
+        Ok(ShardWriter { output })
+    }
+
+    pub fn append(&mut self, sample: &Sample) -> io::Result<()> {
+        for member in &sample.members {
+            let mut header = member.header.clone();
+
+            match &mut self.output {
+                Output::Tar(builder) => {
+                    builder.append_data(&mut header, &member.name, member.data.as_slice())?
+                }
+                Output::TarGz(builder) => {
+                    builder.append_data(&mut header, &member.name, member.data.as_slice())?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write end of archive, reporting errors dropping would hide.
+    pub fn finish(self) -> io::Result<()> {
+        match self.output {
+            Output::Tar(builder) => builder.into_inner()?.flush(),
+            Output::TarGz(builder) => builder.into_inner()?.finish()?.flush(),
+        }
+    }
+}
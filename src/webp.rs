@@ -0,0 +1,105 @@
+use image::error::{DecodingError, ImageFormatHint};
+use image::{DynamicImage, ImageBuffer, ImageError, ImageFormat, ImageResult, Rgb, Rgba};
+use image_webp::WebPDecoder;
+use std::io::Cursor;
+
+fn webp_error(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Exact(ImageFormat::WebP),
+        err,
+    ))
+}
+
+/// Iterator over frames of a WebP file, decoded one at a time along with
+/// their duration in milliseconds. A still WebP is a single frame.
+pub struct WebPFrames<'a> {
+    decoder: WebPDecoder<Cursor<&'a [u8]>>,
+    remaining: u32,
+    failed: bool,
+}
+
+/// Open given WebP file for frame by frame decoding.
+pub fn open_frames(data: &[u8]) -> ImageResult<WebPFrames<'_>> {
+    check_truncation(data)?;
+
+    let decoder = WebPDecoder::new(Cursor::new(data)).map_err(webp_error)?;
+    let remaining = if decoder.is_animated() {
+        decoder.num_frames()
+    } else {
+        1
+    };
+
+    Ok(WebPFrames {
+        decoder,
+        remaining,
+        failed: false,
+    })
+}
+
+/// The RIFF header tells how many bytes follow it. A file holding less
+/// bytes got truncated, even if what remains happens to decode.
+fn check_truncation(data: &[u8]) -> ImageResult<()> {
+    let length = data.len() as u64;
+    if length < 8 {
+        return Err(webp_error("Truncated WebP: missing RIFF header"));
+    }
+
+    let declared = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as u64;
+
+    if length < declared + 8 {
+        return Err(webp_error(format!(
+            "Truncated WebP: RIFF header declares {} bytes, file holds {}",
+            declared + 8,
+            length
+        )));
+    }
+
+    Ok(())
+}
+
+impl WebPFrames<'_> {
+    /// Decode next frame of the file.
+    fn read_frame(&mut self) -> ImageResult<(DynamicImage, f64)> {
+        let (width, height) = self.decoder.dimensions();
+        let size = self
+            .decoder
+            .output_buffer_size()
+            .ok_or_else(|| webp_error("Image too large"))?;
+        let mut buffer = vec![0u8; size];
+
+        let delay = if self.decoder.is_animated() {
+            self.decoder.read_frame(&mut buffer).map_err(webp_error)?
+        } else {
+            self.decoder.read_image(&mut buffer).map_err(webp_error)?;
+            0
+        };
+
+        let image = if self.decoder.has_alpha() {
+            ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, buffer)
+                .map(DynamicImage::ImageRgba8)
+        } else {
+            ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, buffer).map(DynamicImage::ImageRgb8)
+        };
+
+        match image {
+            Some(image) => Ok((image, delay as f64)),
+            None => Err(webp_error("Frame doesn't match image size")),
+        }
+    }
+}
+
+impl Iterator for WebPFrames<'_> {
+    type Item = ImageResult<(DynamicImage, f64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let frame = self.read_frame();
+        self.failed = frame.is_err();
+
+        Some(frame)
+    }
+}
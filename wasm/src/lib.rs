@@ -0,0 +1,227 @@
+// Pixel kernels are those of the python module, they don't touch the
+// filesystem. Rayon runs them on the current thread when it can't spawn any.
+#[allow(dead_code)]
+#[path = "../../src/checks.rs"]
+mod checks;
+#[allow(dead_code)]
+#[path = "../../src/stats.rs"]
+mod stats;
+
+use checks::{
+    blur_score, check_banding, check_grayscale, check_solid_color, clipping, color_cast,
+    dead_lines, detail_score, distinct_levels, downscale, entropy_score,
+};
+use image::GenericImageView;
+use serde_json::{json, Map, Value};
+use stats::Pixel;
+use std::ffi::{CStr, CString};
+use std::mem;
+use std::os::raw::c_char;
+use std::slice;
+
+/// Options of the checks, keyed and defaulting like keyword arguments
+/// of `clean`.
+struct Options(Map<String, Value>);
+
+impl Options {
+    fn parse(options: &[u8]) -> Result<Self, String> {
+        match serde_json::from_slice(options) {
+            Ok(Value::Object(options)) => Ok(Options(options)),
+            Ok(_) => Err("options must be an object".to_string()),
+            Err(err) => Err(format!("invalid options: {}", err)),
+        }
+    }
+
+    fn bool(&self, key: &str, default: bool) -> bool {
+        self.0.get(key).and_then(Value::as_bool).unwrap_or(default)
+    }
+
+    fn f64(&self, key: &str, default: f64) -> f64 {
+        self.0.get(key).and_then(Value::as_f64).unwrap_or(default)
+    }
+
+    /// Solid color thresholds, a single one for every channel, or
+    /// one for red, green and blue then optionally alpha.
+    fn thresholds(&self) -> Pixel {
+        let mut thresholds = match self.0.get("solid_color_threshold") {
+            Some(Value::Number(threshold)) => [threshold.as_f64().unwrap_or(20.0); 4],
+            Some(Value::Array(values)) => {
+                let mut thresholds = [20.0; 4];
+                for (threshold, value) in thresholds.iter_mut().zip(values) {
+                    *threshold = value.as_f64().unwrap_or(20.0);
+                }
+                thresholds
+            }
+            _ => [20.0; 4],
+        };
+        if self.bool("solid_color_ignore_alpha", false) {
+            thresholds[3] = f64::INFINITY;
+        }
+
+        Pixel::from_channels(&thresholds)
+    }
+}
+
+/// Decode given image and run enabled checks over it, the way the python
+/// module does for a single image. Return its reasons and metrics.
+fn check(data: &[u8], options: &Options) -> Value {
+    let mut reasons = Vec::new();
+    let mut metrics = Map::new();
+
+    let image = match image::load_from_memory(data) {
+        Ok(image) => image,
+        Err(err) => {
+            return json!({
+                "reasons": ["undecodable"],
+                "metrics": metrics,
+                "error": err.to_string(),
+            })
+        }
+    };
+
+    let (width, height) = image.dimensions();
+    metrics.insert("width".to_string(), json!(width));
+    metrics.insert("height".to_string(), json!(height));
+
+    // Images of unsupported pixel formats are flagged, as they can't be checked
+    let max_dimension = options.f64("stats_max_dimension", 256.0) as u32;
+    let stats_image = downscale(&image, max_dimension);
+    if check_solid_color(&stats_image, 1, &options.thresholds()) {
+        reasons.push("solid_color");
+    }
+
+    let blur = blur_score(&image);
+    metrics.insert("blur".to_string(), json!(blur));
+    if options.bool("blur", false) && blur < options.f64("min_sharpness", 100.0) {
+        reasons.push("blurry");
+    }
+
+    let entropy = entropy_score(&stats_image);
+    metrics.insert("entropy".to_string(), json!(entropy));
+    if options.bool("entropy", false) && entropy < options.f64("min_entropy", 3.0) {
+        reasons.push("low_entropy");
+    }
+
+    if options.bool("grayscale", false)
+        && check_grayscale(&image, options.f64("grayscale_tolerance", 2.0) as u32)
+    {
+        reasons.push("grayscale");
+    }
+
+    if options.bool("upscaled", false) {
+        let score = detail_score(&image);
+        metrics.insert("detail".to_string(), json!(score));
+
+        if score < options.f64("min_detail", 0.22) {
+            reasons.push("upscaled");
+        }
+    }
+
+    if options.bool("banding", false) {
+        let levels = distinct_levels(&image);
+        for (name, count) in &levels {
+            metrics.insert(name.to_string(), json!(count));
+        }
+
+        if check_banding(&image, &levels, options.f64("min_level_ratio", 0.125)) {
+            reasons.push("banding");
+        }
+    }
+
+    if options.bool("dead_lines", false) {
+        let tolerance = options.f64("dead_line_tolerance", 2.0) as u8;
+        let (rows, columns) = dead_lines(&image, tolerance);
+        metrics.insert("dead_rows".to_string(), json!(rows));
+        metrics.insert("dead_columns".to_string(), json!(columns));
+
+        if rows + columns > 0 {
+            reasons.push("dead_line");
+        }
+    }
+
+    if options.bool("color_cast", false) {
+        if let Some(cast) = color_cast(&stats_image) {
+            let magnitude = cast
+                .iter()
+                .map(|(_, deviation)| deviation.abs())
+                .fold(0.0, f64::max);
+            for (name, deviation) in &cast {
+                metrics.insert(name.to_string(), json!(deviation));
+            }
+            metrics.insert("color_cast".to_string(), json!(magnitude));
+
+            if magnitude > options.f64("max_color_cast", 30.0) {
+                reasons.push("color_cast");
+            }
+        }
+    }
+
+    if options.bool("clipping", false) {
+        let (shadows, highlights) = clipping(&stats_image);
+        metrics.insert("shadow_clipping".to_string(), json!(shadows));
+        metrics.insert("highlight_clipping".to_string(), json!(highlights));
+
+        if shadows > options.f64("max_shadow_clipping", 0.1)
+            || highlights > options.f64("max_highlight_clipping", 0.1)
+        {
+            reasons.push("clipping");
+        }
+    }
+
+    json!({ "reasons": reasons, "metrics": metrics, "error": null })
+}
+
+/// Allocate `len` bytes, for the caller to write an image or options in.
+#[no_mangle]
+pub extern "C" fn cleanax_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::with_capacity(len);
+    let pointer = buffer.as_mut_ptr();
+    mem::forget(buffer);
+    pointer
+}
+
+/// Release bytes allocated by `cleanax_alloc`.
+///
+/// # Safety
+/// `pointer` must come from `cleanax_alloc(len)`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cleanax_dealloc(pointer: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(pointer, 0, len));
+}
+
+/// Check an image held in memory, returning `{"report": {...}}` or
+/// `{"error": "..."}` as a JSON string to release with `cleanax_free`.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes, `options` to a NUL
+/// terminated JSON object or be null.
+#[no_mangle]
+pub unsafe extern "C" fn cleanax_check(
+    data: *const u8,
+    len: usize,
+    options: *const c_char,
+) -> *mut c_char {
+    let options = match options.is_null() {
+        true => Ok(Options(Map::new())),
+        false => Options::parse(CStr::from_ptr(options).to_bytes()),
+    };
+
+    let result = match options {
+        Ok(options) => json!({ "report": check(slice::from_raw_parts(data, len), &options) }),
+        Err(err) => json!({ "error": err }),
+    };
+
+    // JSON escapes control characters, NUL included
+    CString::new(result.to_string()).unwrap().into_raw()
+}
+
+/// Release a result returned by `cleanax_check`.
+///
+/// # Safety
+/// `result` must come from it, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cleanax_free(result: *mut c_char) {
+    if !result.is_null() {
+        drop(CString::from_raw(result));
+    }
+}